@@ -0,0 +1,34 @@
+//! Read-only HTTP/JSON API over the database `predection-indexer` writes.
+//!
+//! Routes (all `GET`):
+//!
+//! - `/markets?status=&category=&creator=&sort=newest|volume|trades&limit=&offset=`
+//! - `/markets/{address}`
+//! - `/markets/{address}/prices?since=&limit=`: YES price after each trade
+//! - `/markets/{address}/candles?interval=&from=&to=`: OHLCV, interval in seconds
+//! - `/wallets/{owner}/positions`: positions with current value and PnL
+//! - `/leaderboard?by=pnl|volume&limit=`
+//! - `/creators?limit=`: creators ranked by fee revenue
+//! - `/creators/{creator}/fees`: a creator's revenue per market
+//! - `/health`
+
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+pub mod error;
+pub mod queries;
+pub mod routes;
+
+pub use routes::{handle, Response};
+
+/// Opens the indexer's database read-only, waiting out the indexer's writes.
+pub fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let db = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    db.busy_timeout(Duration::from_secs(5))?;
+    Ok(db)
+}
@@ -0,0 +1,62 @@
+//! `predection-api`: serves the indexer's SQLite database over HTTP.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use tiny_http::{Header, Server};
+
+#[derive(Parser)]
+#[command(
+    name = "predection-api",
+    about = "Serve indexed predection data as JSON"
+)]
+struct Cli {
+    /// SQLite database written by predection-indexer
+    #[arg(long, env = "PREDECTION_INDEXER_DB", default_value = "predection.db")]
+    db: PathBuf,
+
+    #[arg(long, env = "PREDECTION_API_LISTEN", default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Request-handling threads, each with its own connection
+    #[arg(long, default_value_t = 4)]
+    workers: usize,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    // Fail fast on a missing database rather than in every worker
+    predection_api::open(&cli.db).with_context(|| format!("opening {}", cli.db.display()))?;
+    let server = Arc::new(Server::http(&cli.listen).map_err(|e| anyhow!("{e}"))?);
+    println!("serving {} on http://{}", cli.db.display(), cli.listen);
+
+    let workers: Vec<_> = (0..cli.workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let path = cli.db.clone();
+            thread::spawn(move || -> Result<()> {
+                let db = predection_api::open(&path)?;
+                let content_type =
+                    Header::from_bytes("Content-Type", "application/json").expect("valid header");
+                for request in server.incoming_requests() {
+                    let response =
+                        predection_api::handle(&db, request.method().as_str(), request.url());
+                    let reply = tiny_http::Response::from_string(response.body.to_string())
+                        .with_status_code(response.status)
+                        .with_header(content_type.clone());
+                    if let Err(error) = request.respond(reply) {
+                        eprintln!("failed to respond: {error}");
+                    }
+                }
+                Ok(())
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().map_err(|_| anyhow!("worker panicked"))??;
+    }
+    Ok(())
+}
@@ -0,0 +1,511 @@
+//! Reads over the indexer's tables (see `predection_indexer::store`).
+//!
+//! Amounts are raw collateral units and prices are scaled by
+//! `PRICE_SCALE`, as on-chain.
+
+use std::collections::HashMap;
+
+use predection_math::{pro_rata, yes_price};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::error::{ApiError, Result};
+
+const MARKET_COLUMNS: &str = "address, creator, resolver, collateral_mint, question, category,
+    fee_bps, close_timestamp, status, resolution, resolved_at, yes_supply, no_supply,
+    vault_balance, volume, trade_count, created_at";
+
+#[derive(Clone, Copy)]
+pub enum MarketSort {
+    Newest,
+    Volume,
+    Trades,
+}
+
+pub struct MarketFilter {
+    pub status: Option<String>,
+    pub category: Option<String>,
+    pub creator: Option<String>,
+    pub sort: MarketSort,
+    pub limit: u64,
+    pub offset: u64,
+}
+
+#[derive(Serialize)]
+pub struct MarketSummary {
+    pub address: String,
+    pub creator: String,
+    pub resolver: String,
+    pub collateral_mint: String,
+    pub question: String,
+    pub category: String,
+    pub fee_bps: u64,
+    pub close_timestamp: Option<i64>,
+    pub status: String,
+    pub resolution: Option<String>,
+    pub resolved_at: Option<i64>,
+    pub yes_price: u64,
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub vault_balance: u64,
+    pub volume: u64,
+    pub trade_count: u64,
+    pub created_at: Option<i64>,
+}
+
+impl MarketSummary {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let yes_supply = amount(row, 11)?;
+        let no_supply = amount(row, 12)?;
+        Ok(Self {
+            address: row.get(0)?,
+            creator: row.get(1)?,
+            resolver: row.get(2)?,
+            collateral_mint: row.get(3)?,
+            question: row.get(4)?,
+            category: row.get(5)?,
+            fee_bps: amount(row, 6)?,
+            close_timestamp: row.get(7)?,
+            status: row.get(8)?,
+            resolution: row.get(9)?,
+            resolved_at: row.get(10)?,
+            yes_price: yes_price(yes_supply, no_supply),
+            yes_supply,
+            no_supply,
+            vault_balance: amount(row, 13)?,
+            volume: amount(row, 14)?,
+            trade_count: amount(row, 15)?,
+            created_at: row.get(16)?,
+        })
+    }
+
+    /// Collateral `yes` and `no` shares are worth now. Once resolved they
+    /// are worth their claim, and disputed markets are valued at the
+    /// resolver's outcome until ruled; before that, shares sell back 1:1.
+    fn share_value(&self, yes: u64, no: u64) -> u64 {
+        let (shares, supply) = match self.resolution.as_deref() {
+            Some("yes") => (yes, self.yes_supply),
+            Some("no") => (no, self.no_supply),
+            Some("invalid") => (
+                yes.saturating_add(no),
+                self.yes_supply.saturating_add(self.no_supply),
+            ),
+            _ => return yes.saturating_add(no),
+        };
+        if shares == 0 {
+            return 0;
+        }
+        pro_rata(shares, self.vault_balance, supply).unwrap_or(0)
+    }
+}
+
+pub fn markets(db: &Connection, filter: &MarketFilter) -> Result<Vec<MarketSummary>> {
+    let order = match filter.sort {
+        MarketSort::Newest => "created_slot DESC",
+        MarketSort::Volume => "volume DESC",
+        MarketSort::Trades => "trade_count DESC",
+    };
+    let sql = format!(
+        "SELECT {MARKET_COLUMNS} FROM markets
+         WHERE (?1 IS NULL OR status = ?1)
+           AND (?2 IS NULL OR category = ?2)
+           AND (?3 IS NULL OR creator = ?3)
+         ORDER BY {order}, address
+         LIMIT ?4 OFFSET ?5"
+    );
+    let rows = db
+        .prepare(&sql)?
+        .query_map(
+            params![
+                filter.status,
+                filter.category,
+                filter.creator,
+                int(filter.limit),
+                int(filter.offset)
+            ],
+            MarketSummary::from_row,
+        )?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+pub fn market(db: &Connection, address: &str) -> Result<MarketSummary> {
+    db.query_row(
+        &format!("SELECT {MARKET_COLUMNS} FROM markets WHERE address = ?1"),
+        [address],
+        MarketSummary::from_row,
+    )
+    .optional()?
+    .ok_or_else(|| ApiError::NotFound(format!("market {address}")))
+}
+
+/// A buy or sell and the YES price it left behind.
+#[derive(Serialize)]
+pub struct PricePoint {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: String,
+    pub side: Option<String>,
+    pub collateral: u64,
+    pub shares: u64,
+    pub yes_price: u64,
+}
+
+/// Trades oldest first, from `since` (a unix timestamp) if given.
+pub fn price_history(
+    db: &Connection,
+    address: &str,
+    since: Option<i64>,
+    limit: u64,
+) -> Result<Vec<PricePoint>> {
+    market(db, address)?;
+    let rows = db
+        .prepare(
+            "SELECT signature, slot, block_time, kind, side, collateral, shares, yes_price
+             FROM trades
+             WHERE market = ?1 AND kind IN ('buy', 'sell')
+               AND (?2 IS NULL OR block_time >= ?2)
+             ORDER BY id
+             LIMIT ?3",
+        )?
+        .query_map(params![address, since, int(limit)], |row| {
+            Ok(PricePoint {
+                signature: row.get(0)?,
+                slot: amount(row, 1)?,
+                block_time: row.get(2)?,
+                kind: row.get(3)?,
+                side: row.get(4)?,
+                collateral: amount(row, 5)?,
+                shares: amount(row, 6)?,
+                yes_price: amount(row, 7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+/// OHLC of the YES price and collateral volume over one interval.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Candle {
+    /// Unix timestamp the interval starts at
+    pub start: i64,
+    pub open: u64,
+    pub high: u64,
+    pub low: u64,
+    pub close: u64,
+    pub volume: u64,
+    pub trades: u64,
+}
+
+/// Candles of `interval` seconds between `from` and `to`. Intervals
+/// without trades are left out.
+pub fn candles(
+    db: &Connection,
+    address: &str,
+    interval: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+) -> Result<Vec<Candle>> {
+    market(db, address)?;
+    let mut statement = db.prepare(
+        "SELECT block_time, yes_price, collateral FROM trades
+         WHERE market = ?1 AND kind IN ('buy', 'sell') AND block_time IS NOT NULL
+           AND (?2 IS NULL OR block_time >= ?2)
+           AND (?3 IS NULL OR block_time < ?3)
+         ORDER BY id",
+    )?;
+    let mut rows = statement.query(params![address, from, to])?;
+
+    let mut candles: Vec<Candle> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let time: i64 = row.get(0)?;
+        let price = amount(row, 1)?;
+        let volume = amount(row, 2)?;
+        let start = time - time.rem_euclid(interval);
+        match candles.last_mut() {
+            Some(candle) if candle.start == start => {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume = candle.volume.saturating_add(volume);
+                candle.trades += 1;
+            }
+            _ => candles.push(Candle {
+                start,
+                open: price,
+                high: price,
+                low: price,
+                close: price,
+                volume,
+                trades: 1,
+            }),
+        }
+    }
+    Ok(candles)
+}
+
+#[derive(Serialize)]
+pub struct Position {
+    pub market: String,
+    pub question: String,
+    pub status: String,
+    pub resolution: Option<String>,
+    pub yes_shares: u64,
+    pub no_shares: u64,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    /// What the remaining shares are worth now
+    pub value: u64,
+    /// withdrawn + value - deposited
+    pub pnl: i64,
+}
+
+#[derive(Serialize)]
+pub struct Wallet {
+    pub owner: String,
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub value: u64,
+    pub pnl: i64,
+    pub positions: Vec<Position>,
+}
+
+pub fn wallet(db: &Connection, owner: &str) -> Result<Wallet> {
+    let mut positions = Vec::new();
+    each_position(db, Some(owner), |_, position| positions.push(position))?;
+    Ok(Wallet {
+        owner: owner.to_string(),
+        deposited: total(positions.iter().map(|position| position.deposited)),
+        withdrawn: total(positions.iter().map(|position| position.withdrawn)),
+        value: total(positions.iter().map(|position| position.value)),
+        pnl: positions
+            .iter()
+            .fold(0, |pnl: i64, position| pnl.saturating_add(position.pnl)),
+        positions,
+    })
+}
+
+/// Calls `visit` with the owner of each position of `owner`, or of
+/// everyone, valued against its market in the same query.
+fn each_position(
+    db: &Connection,
+    owner: Option<&str>,
+    mut visit: impl FnMut(String, Position),
+) -> Result<()> {
+    let mut statement = db.prepare(&format!(
+        "SELECT {MARKET_COLUMNS}, p.owner, p.yes_shares, p.no_shares,
+                p.collateral_deposited, p.collateral_withdrawn
+         FROM positions p JOIN markets ON address = p.market
+         WHERE ?1 IS NULL OR p.owner = ?1
+         ORDER BY p.owner, p.market"
+    ))?;
+    let mut rows = statement.query([owner])?;
+
+    while let Some(row) = rows.next()? {
+        let market = MarketSummary::from_row(row)?;
+        let yes_shares = amount(row, 18)?;
+        let no_shares = amount(row, 19)?;
+        let deposited = amount(row, 20)?;
+        let withdrawn = amount(row, 21)?;
+        let value = market.share_value(yes_shares, no_shares);
+        let pnl = withdrawn as i128 + value as i128 - deposited as i128;
+        visit(
+            row.get(17)?,
+            Position {
+                market: market.address,
+                question: market.question,
+                status: market.status,
+                resolution: market.resolution,
+                yes_shares,
+                no_shares,
+                deposited,
+                withdrawn,
+                value,
+                pnl: pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+            },
+        );
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+pub enum LeaderboardSort {
+    Pnl,
+    Volume,
+}
+
+#[derive(Serialize)]
+pub struct Trader {
+    pub owner: String,
+    pub pnl: i64,
+    /// Collateral bought and sold
+    pub volume: u64,
+    pub markets: u64,
+}
+
+impl Trader {
+    fn new(owner: String) -> Self {
+        Self {
+            owner,
+            pnl: 0,
+            volume: 0,
+            markets: 0,
+        }
+    }
+
+    fn add(&mut self, position: &Position) {
+        self.pnl = self.pnl.saturating_add(position.pnl);
+        self.markets += 1;
+    }
+}
+
+/// Volume is ranked in SQL. PnL depends on pro-rata claim values, so it
+/// is folded per trader while streaming positions; only the traders
+/// returned are looked up again for the rest of their row.
+pub fn leaderboard(db: &Connection, sort: LeaderboardSort, limit: u64) -> Result<Vec<Trader>> {
+    let traders = match sort {
+        LeaderboardSort::Pnl => {
+            let mut by_owner: HashMap<String, Trader> = HashMap::new();
+            each_position(db, None, |owner, position| {
+                by_owner
+                    .entry(owner.clone())
+                    .or_insert_with(|| Trader::new(owner))
+                    .add(&position);
+            })?;
+            let mut traders: Vec<Trader> = by_owner.into_values().collect();
+            traders.sort_by(|a, b| b.pnl.cmp(&a.pnl).then(a.owner.cmp(&b.owner)));
+            traders.truncate(limit as usize);
+            for trader in &mut traders {
+                trader.volume = volume(db, &trader.owner)?;
+            }
+            traders
+        }
+        LeaderboardSort::Volume => {
+            let mut traders: Vec<Trader> = db
+                .prepare(
+                    "SELECT p.owner, COALESCE(SUM(t.collateral), 0) AS volume
+                     FROM (SELECT DISTINCT owner FROM positions) p
+                     LEFT JOIN trades t ON t.trader = p.owner AND t.kind IN ('buy', 'sell')
+                     GROUP BY p.owner
+                     ORDER BY volume DESC, p.owner
+                     LIMIT ?1",
+                )?
+                .query_map([int(limit)], |row| {
+                    let mut trader = Trader::new(row.get(0)?);
+                    trader.volume = amount(row, 1)?;
+                    Ok(trader)
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            for trader in &mut traders {
+                let owner = trader.owner.clone();
+                each_position(db, Some(&owner), |_, position| trader.add(&position))?;
+            }
+            traders
+        }
+    };
+    Ok(traders)
+}
+
+/// Collateral `trader` bought and sold with.
+fn volume(db: &Connection, trader: &str) -> Result<u64> {
+    Ok(db.query_row(
+        "SELECT COALESCE(SUM(collateral), 0) FROM trades
+         WHERE trader = ?1 AND kind IN ('buy', 'sell')",
+        [trader],
+        |row| amount(row, 0),
+    )?)
+}
+
+#[derive(Serialize)]
+pub struct CreatorRevenue {
+    pub creator: String,
+    /// Creator share of trading fees
+    pub revenue: u64,
+    pub markets: u64,
+    pub volume: u64,
+}
+
+/// Creators ranked by fee revenue.
+pub fn creators(db: &Connection, limit: u64) -> Result<Vec<CreatorRevenue>> {
+    let rows = db
+        .prepare(
+            "SELECT m.creator,
+                    COALESCE((SELECT SUM(amount) FROM fee_payouts
+                              WHERE kind = 'creator' AND recipient = m.creator), 0) AS revenue,
+                    COUNT(*), SUM(m.volume)
+             FROM markets m
+             GROUP BY m.creator
+             ORDER BY revenue DESC, m.creator
+             LIMIT ?1",
+        )?
+        .query_map([int(limit)], |row| {
+            Ok(CreatorRevenue {
+                creator: row.get(0)?,
+                revenue: amount(row, 1)?,
+                markets: amount(row, 2)?,
+                volume: amount(row, 3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+#[derive(Serialize)]
+pub struct MarketRevenue {
+    pub market: String,
+    pub question: String,
+    pub revenue: u64,
+    pub volume: u64,
+}
+
+#[derive(Serialize)]
+pub struct CreatorFees {
+    pub creator: String,
+    pub revenue: u64,
+    pub markets: Vec<MarketRevenue>,
+}
+
+pub fn creator_fees(db: &Connection, creator: &str) -> Result<CreatorFees> {
+    let markets: Vec<MarketRevenue> = db
+        .prepare(
+            "SELECT m.address, m.question,
+                    COALESCE((SELECT SUM(amount) FROM fee_payouts f
+                              WHERE f.kind = 'creator' AND f.market = m.address), 0) AS revenue,
+                    m.volume
+             FROM markets m
+             WHERE m.creator = ?1
+             ORDER BY revenue DESC, m.address",
+        )?
+        .query_map([creator], |row| {
+            Ok(MarketRevenue {
+                market: row.get(0)?,
+                question: row.get(1)?,
+                revenue: amount(row, 2)?,
+                volume: amount(row, 3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    if markets.is_empty() {
+        return Err(ApiError::NotFound(format!("creator {creator}")));
+    }
+    Ok(CreatorFees {
+        creator: creator.to_string(),
+        revenue: total(markets.iter().map(|market| market.revenue)),
+        markets,
+    })
+}
+
+/// Sums amounts, saturating rather than overflowing on absurd totals.
+fn total(amounts: impl Iterator<Item = u64>) -> u64 {
+    amounts.fold(0, u64::saturating_add)
+}
+
+/// The indexer stores amounts as non-negative SQLite integers.
+fn amount(row: &Row, index: usize) -> rusqlite::Result<u64> {
+    Ok(row.get::<_, i64>(index)?.max(0) as u64)
+}
+
+fn int(value: u64) -> i64 {
+    value.min(i64::MAX as u64) as i64
+}
@@ -0,0 +1,136 @@
+//! Maps requests onto queries. Kept free of the HTTP server so tests can
+//! call [`handle`] directly.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::{ApiError, Result};
+use crate::queries::{self, LeaderboardSort, MarketFilter, MarketSort};
+
+const DEFAULT_LIMIT: u64 = 50;
+const MAX_LIMIT: u64 = 1_000;
+const DEFAULT_CANDLE_INTERVAL: i64 = 3_600;
+
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+/// Answers `method` on `url` (path and query string).
+pub fn handle(db: &Connection, method: &str, url: &str) -> Response {
+    match route(db, method, url) {
+        Ok(body) => Response { status: 200, body },
+        Err(error) => Response {
+            status: error.status(),
+            body: error.to_json(),
+        },
+    }
+}
+
+fn route(db: &Connection, method: &str, url: &str) -> Result<Value> {
+    if method != "GET" {
+        return Err(ApiError::MethodNotAllowed);
+    }
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let query = Query::parse(query);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match segments.as_slice() {
+        ["health"] => Ok(json!({ "status": "ok" })),
+        ["markets"] => {
+            let filter = MarketFilter {
+                status: query.string("status"),
+                category: query.string("category"),
+                creator: query.string("creator"),
+                sort: match query.get("sort") {
+                    None | Some("newest") => MarketSort::Newest,
+                    Some("volume") => MarketSort::Volume,
+                    Some("trades") => MarketSort::Trades,
+                    Some(other) => return Err(bad_value("sort", other)),
+                },
+                limit: query.limit()?,
+                offset: query.u64("offset")?.unwrap_or(0),
+            };
+            to_json(queries::markets(db, &filter)?)
+        }
+        ["markets", address] => to_json(queries::market(db, address)?),
+        ["markets", address, "prices"] => to_json(queries::price_history(
+            db,
+            address,
+            query.i64("since")?,
+            query.limit()?,
+        )?),
+        ["markets", address, "candles"] => {
+            let interval = query.i64("interval")?.unwrap_or(DEFAULT_CANDLE_INTERVAL);
+            if interval <= 0 {
+                return Err(bad_value("interval", &interval.to_string()));
+            }
+            to_json(queries::candles(
+                db,
+                address,
+                interval,
+                query.i64("from")?,
+                query.i64("to")?,
+            )?)
+        }
+        ["wallets", owner, "positions"] => to_json(queries::wallet(db, owner)?),
+        ["leaderboard"] => {
+            let sort = match query.get("by") {
+                None | Some("pnl") => LeaderboardSort::Pnl,
+                Some("volume") => LeaderboardSort::Volume,
+                Some(other) => return Err(bad_value("by", other)),
+            };
+            to_json(queries::leaderboard(db, sort, query.limit()?)?)
+        }
+        ["creators"] => to_json(queries::creators(db, query.limit()?)?),
+        ["creators", creator, "fees"] => to_json(queries::creator_fees(db, creator)?),
+        _ => Err(ApiError::NotFound(format!("route {path}"))),
+    }
+}
+
+struct Query(HashMap<String, String>);
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        Self(
+            form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+        )
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn string(&self, name: &str) -> Option<String> {
+        self.get(name).map(str::to_string)
+    }
+
+    fn u64(&self, name: &str) -> Result<Option<u64>> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| bad_value(name, value)))
+            .transpose()
+    }
+
+    fn i64(&self, name: &str) -> Result<Option<i64>> {
+        self.get(name)
+            .map(|value| value.parse().map_err(|_| bad_value(name, value)))
+            .transpose()
+    }
+
+    fn limit(&self) -> Result<u64> {
+        Ok(self.u64("limit")?.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+    }
+}
+
+fn bad_value(name: &str, value: &str) -> ApiError {
+    ApiError::BadRequest(format!("invalid {name}: {value}"))
+}
+
+fn to_json(value: impl Serialize) -> Result<Value> {
+    Ok(serde_json::to_value(value).expect("responses serialize"))
+}
@@ -0,0 +1,301 @@
+//! Runs routes against a database created by the indexer's `Store` and
+//! seeded with rows directly.
+
+use std::path::PathBuf;
+
+use predection_api::{handle, open};
+use predection_indexer::Store;
+use rusqlite::{params, Connection};
+use serde_json::{json, Value};
+
+struct Db {
+    path: PathBuf,
+    writer: Store,
+    reader: Connection,
+}
+
+impl Db {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("predection-api-{}-{name}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let writer = Store::open(&path).unwrap();
+        let reader = open(&path).unwrap();
+        Self {
+            path,
+            writer,
+            reader,
+        }
+    }
+
+    fn get(&self, url: &str) -> (u16, Value) {
+        let response = handle(&self.reader, "GET", url);
+        (response.status, response.body)
+    }
+
+    fn ok(&self, url: &str) -> Value {
+        let (status, body) = self.get(url);
+        assert_eq!(status, 200, "{url}: {body}");
+        body
+    }
+
+    fn execute(&self, sql: &str, values: impl rusqlite::Params) {
+        self.writer.connection().execute(sql, values).unwrap();
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn market(
+        &self,
+        address: &str,
+        creator: &str,
+        category: &str,
+        (yes_supply, no_supply, vault): (i64, i64, i64),
+        volume: i64,
+        resolution: Option<&str>,
+        slot: i64,
+    ) {
+        self.execute(
+            "INSERT INTO markets (address, creator, resolver, collateral_mint, protocol_fee_collector,
+                question, category, fee_bps, status, resolution, initial_liquidity, yes_supply,
+                no_supply, vault_balance, volume, trade_count, created_slot, signature)
+             VALUES (?1, ?2, ?2, 'usdc', 'treasury', ?3, ?4, 100, ?5, ?6, 0, ?7, ?8, ?9, ?10, 1, ?11, ?1)",
+            params![
+                address,
+                creator,
+                format!("Question {address}?"),
+                category,
+                if resolution.is_some() { "resolved" } else { "open" },
+                resolution,
+                yes_supply,
+                no_supply,
+                vault,
+                volume,
+                slot,
+            ],
+        );
+    }
+
+    fn trade(
+        &self,
+        market: &str,
+        trader: &str,
+        kind: &str,
+        time: i64,
+        price: i64,
+        collateral: i64,
+    ) {
+        self.execute(
+            "INSERT INTO trades (signature, market, trader, kind, side, collateral, shares, fee,
+                yes_price, slot, block_time)
+             VALUES ('sig', ?1, ?2, ?3, 'yes', ?4, ?4, 0, ?5, ?6, ?6)",
+            params![market, trader, kind, collateral, price, time],
+        );
+    }
+
+    fn position(
+        &self,
+        market: &str,
+        owner: &str,
+        yes: i64,
+        no: i64,
+        deposited: i64,
+        withdrawn: i64,
+    ) {
+        self.execute(
+            "INSERT INTO positions VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![market, owner, yes, no, deposited, withdrawn],
+        );
+    }
+
+    fn creator_fee(&self, market: &str, creator: &str, amount: i64) {
+        self.execute(
+            "INSERT INTO fee_payouts (signature, market, recipient, kind, amount, slot)
+             VALUES ('sig', ?1, ?2, 'creator', ?3, 1)",
+            params![market, creator, amount],
+        );
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn addresses(body: &Value, field: &str) -> Vec<String> {
+    body.as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item[field].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn lists_and_filters_markets() {
+    let db = Db::new("markets");
+    db.market("a", "alice", "sports", (300, 100, 400), 50, None, 1);
+    db.market("b", "alice", "weather", (0, 0, 0), 900, None, 2);
+    db.market("c", "bob", "sports", (10, 10, 20), 500, Some("yes"), 3);
+
+    assert_eq!(addresses(&db.ok("/markets"), "address"), ["c", "b", "a"]);
+    assert_eq!(
+        addresses(&db.ok("/markets?sort=volume&limit=2"), "address"),
+        ["b", "c"]
+    );
+    assert_eq!(
+        addresses(&db.ok("/markets?category=sports&status=open"), "address"),
+        ["a"]
+    );
+    assert_eq!(
+        addresses(&db.ok("/markets?creator=alice&offset=1"), "address"),
+        ["a"]
+    );
+
+    let market = db.ok("/markets/a");
+    assert_eq!(market["yes_price"], 750_000);
+    assert_eq!(market["vault_balance"], 400);
+    // An empty pool is priced 50/50
+    assert_eq!(db.ok("/markets/b")["yes_price"], 500_000);
+
+    assert_eq!(db.get("/markets/zzz").0, 404);
+    assert_eq!(db.get("/markets?sort=random").0, 400);
+    assert_eq!(db.get("/markets?limit=-1").0, 400);
+    assert_eq!(db.get("/nowhere").0, 404);
+    assert_eq!(handle(&db.reader, "POST", "/markets").status, 405);
+}
+
+#[test]
+fn price_history_and_candles() {
+    let db = Db::new("candles");
+    db.market("m", "alice", "sports", (0, 0, 0), 0, None, 1);
+    db.trade("m", "t", "buy", 7_200, 500_000, 10);
+    db.trade("m", "t", "buy", 7_300, 700_000, 20);
+    db.trade("m", "t", "sell", 7_400, 400_000, 5);
+    db.trade("m", "t", "buy", 10_900, 600_000, 40);
+    // Claims don't move the price
+    db.trade("m", "t", "claim", 11_000, 100_000, 99);
+
+    let prices = db.ok("/markets/m/prices");
+    assert_eq!(prices.as_array().unwrap().len(), 4);
+    assert_eq!(prices[1]["yes_price"], 700_000);
+    assert_eq!(
+        db.ok("/markets/m/prices?since=7400")
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    assert_eq!(
+        db.ok("/markets/m/candles"),
+        json!([
+            { "start": 7_200, "open": 500_000, "high": 700_000, "low": 400_000,
+              "close": 400_000, "volume": 35, "trades": 3 },
+            { "start": 10_800, "open": 600_000, "high": 600_000, "low": 600_000,
+              "close": 600_000, "volume": 40, "trades": 1 },
+        ])
+    );
+    let window = db.ok("/markets/m/candles?interval=60&from=7300&to=7400");
+    assert_eq!(window.as_array().unwrap().len(), 1);
+    assert_eq!(window[0]["start"], 7_260);
+
+    assert_eq!(db.get("/markets/m/candles?interval=0").0, 400);
+    assert_eq!(db.get("/markets/x/candles").0, 404);
+}
+
+#[test]
+fn wallet_positions_and_leaderboard() {
+    let db = Db::new("wallets");
+    // Resolved YES: 250 of 500 winning shares claim half the vault
+    db.market(
+        "won",
+        "alice",
+        "sports",
+        (500, 300, 1_000),
+        0,
+        Some("yes"),
+        1,
+    );
+    // Open: shares sell back 1:1
+    db.market("open", "alice", "sports", (100, 50, 150), 0, None, 2);
+    db.position("won", "carol", 250, 0, 300, 0);
+    db.position("open", "carol", 100, 50, 150, 0);
+    db.position("won", "dave", 0, 300, 400, 100);
+    db.trade("won", "carol", "buy", 1, 0, 300);
+    db.trade("won", "dave", "buy", 1, 0, 400);
+    db.trade("won", "dave", "sell", 2, 0, 100);
+
+    let wallet = db.ok("/wallets/carol/positions");
+    assert_eq!(wallet["value"], 650);
+    assert_eq!(wallet["pnl"], 200);
+    let positions = wallet["positions"].as_array().unwrap();
+    assert_eq!(positions.len(), 2);
+    assert_eq!(positions[1]["market"], "won");
+    assert_eq!(positions[1]["value"], 500);
+
+    let empty = db.ok("/wallets/nobody/positions");
+    assert_eq!(empty["positions"], json!([]));
+
+    assert_eq!(
+        addresses(&db.ok("/leaderboard"), "owner"),
+        ["carol", "dave"]
+    );
+    assert_eq!(db.ok("/leaderboard")[1]["pnl"], -300);
+    let by_volume = db.ok("/leaderboard?by=volume&limit=1");
+    assert_eq!(addresses(&by_volume, "owner"), ["dave"]);
+    assert_eq!(by_volume[0]["volume"], 500);
+}
+
+#[test]
+fn wallet_totals_saturate_instead_of_overflowing() {
+    let db = Db::new("saturating");
+    for (index, market) in ["a", "b", "c"].into_iter().enumerate() {
+        db.market(
+            market,
+            "alice",
+            "sports",
+            (i64::MAX, 0, i64::MAX),
+            0,
+            None,
+            index as i64,
+        );
+        db.position(market, "whale", i64::MAX, 0, i64::MAX, i64::MAX);
+    }
+    db.position("a", "minnow", 1, 0, 1, 0);
+
+    let wallet = db.ok("/wallets/whale/positions");
+    assert_eq!(wallet["deposited"], u64::MAX);
+    assert_eq!(wallet["withdrawn"], u64::MAX);
+    assert_eq!(wallet["value"], u64::MAX);
+    assert_eq!(wallet["pnl"], i64::MAX);
+
+    let leaderboard = db.ok("/leaderboard");
+    assert_eq!(addresses(&leaderboard, "owner"), ["whale", "minnow"]);
+    assert_eq!(leaderboard[0]["pnl"], i64::MAX);
+    assert_eq!(leaderboard[0]["markets"], 3);
+    let by_volume = db.ok("/leaderboard?by=volume");
+    assert_eq!(addresses(&by_volume, "owner"), ["minnow", "whale"]);
+    assert_eq!(by_volume[1]["pnl"], i64::MAX);
+}
+
+#[test]
+fn creator_fee_revenue() {
+    let db = Db::new("creators");
+    db.market("a", "alice", "sports", (0, 0, 0), 1_000, None, 1);
+    db.market("b", "alice", "sports", (0, 0, 0), 3_000, None, 2);
+    db.market("c", "bob", "sports", (0, 0, 0), 9_000, None, 3);
+    db.creator_fee("a", "alice", 5);
+    db.creator_fee("b", "alice", 15);
+    db.creator_fee("c", "bob", 45);
+
+    let creators = db.ok("/creators");
+    assert_eq!(addresses(&creators, "creator"), ["bob", "alice"]);
+    assert_eq!(creators[1]["revenue"], 20);
+    assert_eq!(creators[1]["markets"], 2);
+    assert_eq!(creators[1]["volume"], 4_000);
+
+    let fees = db.ok("/creators/alice/fees");
+    assert_eq!(fees["revenue"], 20);
+    assert_eq!(addresses(&fees["markets"], "market"), ["b", "a"]);
+    assert_eq!(db.get("/creators/nobody/fees").0, 404);
+}
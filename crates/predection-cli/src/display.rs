@@ -0,0 +1,119 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use anyhow::Result;
+use comfy_table::Table;
+use predection::states::{yes_price, PRICE_SCALE};
+use predection_sdk::accounts::{resolution_label, status_label};
+use predection_sdk::rpc::RpcClient;
+use predection_sdk::Market;
+use serde_json::{json, Value};
+
+use crate::OutputFormat;
+
+/// A market plus the token balances that live outside the `Market` account.
+pub struct MarketSnapshot {
+    pub address: Pubkey,
+    pub market: Market,
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub vault_balance: u64,
+}
+
+impl MarketSnapshot {
+    pub fn fetch(client: &RpcClient, address: Pubkey, market: Market) -> Result<Self> {
+        let yes_mint: Mint = client.get_anchor_account(&market.yes_mint)?;
+        let no_mint: Mint = client.get_anchor_account(&market.no_mint)?;
+        let vault: TokenAccount = client.get_anchor_account(&market.market_vault)?;
+        Ok(Self {
+            address,
+            yes_supply: yes_mint.supply,
+            no_supply: no_mint.supply,
+            vault_balance: vault.amount,
+            market,
+        })
+    }
+
+    fn yes_probability(&self) -> f64 {
+        yes_price(self.yes_supply, self.no_supply) as f64 / PRICE_SCALE as f64
+    }
+
+    fn to_json(&self) -> Value {
+        let m = &self.market;
+        json!({
+            "address": self.address.to_string(),
+            "market_id": m.market_id,
+            "question": m.question,
+            "category": m.category,
+            "status": status_label(&m.status),
+            "resolution": resolution_label(m.resolution),
+            "authority": m.authority.to_string(),
+            "resolver": m.resolver.to_string(),
+            "collateral_mint": m.collateral_mint.to_string(),
+            "yes_supply": self.yes_supply,
+            "no_supply": self.no_supply,
+            "yes_probability": self.yes_probability(),
+            "vault": m.market_vault.to_string(),
+            "vault_balance": self.vault_balance,
+            "fee_bps": m.fee,
+            "fee_collector": m.fee_collector.to_string(),
+            "protocol_fee_collector": m.protocol_fee_collector.to_string(),
+            "close_timestamp": m.market_close_timestamp,
+            "resolved_at": m.resolved_at,
+            "creator_bond": m.bond_amount,
+            "trade_count": m.trade_count,
+        })
+    }
+}
+
+pub fn print_market(snapshot: &MarketSnapshot, format: OutputFormat) {
+    let value = snapshot.to_json();
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&value).unwrap()),
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_header(["Field", "Value"]);
+            for (field, value) in value.as_object().unwrap() {
+                let value = match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                table.add_row([field.as_str(), value.as_str()]);
+            }
+            println!("{table}");
+        }
+    }
+}
+
+pub fn print_market_list(markets: &[(Pubkey, Market)], format: OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let rows: Vec<Value> = markets
+                .iter()
+                .map(|(address, m)| {
+                    json!({
+                        "address": address.to_string(),
+                        "market_id": m.market_id,
+                        "question": m.question,
+                        "status": status_label(&m.status),
+                        "close_timestamp": m.market_close_timestamp,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&rows).unwrap());
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.set_header(["Id", "Address", "Question", "Status", "Closes"]);
+            for (address, m) in markets {
+                table.add_row([
+                    m.market_id.to_string(),
+                    address.to_string(),
+                    m.question.clone(),
+                    status_label(&m.status).to_string(),
+                    m.market_close_timestamp.to_string(),
+                ]);
+            }
+            println!("{table}");
+        }
+    }
+}
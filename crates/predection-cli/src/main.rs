@@ -0,0 +1,373 @@
+//! `predection-cli`: create, trade, resolve and inspect markets from a shell.
+//!
+//! Amounts are raw collateral units (1 USDC = 1_000_000).
+
+mod display;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::{token::ID as TOKEN_PROGRAM_ID, token_2022::ID as TOKEN_2022_PROGRAM_ID};
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use predection::states::{CategoryRegistry, CreatorProfile, MarketRegistry, ProtocolConfig};
+use predection_sdk::instructions::{self, InitializeParams};
+use predection_sdk::rpc::{load_keypair, ClientError, RpcClient};
+use predection_sdk::{pda, InitialLiquidity, MarketLimits, Resolution, TradeOutcome};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+#[derive(Parser)]
+#[command(name = "predection-cli", about = "Operate predection markets")]
+struct Cli {
+    /// JSON-RPC endpoint
+    #[arg(
+        long,
+        env = "PREDECTION_RPC_URL",
+        default_value = "https://api.devnet.solana.com"
+    )]
+    url: String,
+
+    /// Signer and fee payer
+    #[arg(
+        long,
+        env = "PREDECTION_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Side {
+    Yes,
+    No,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Outcome {
+    Yes,
+    No,
+    Invalid,
+}
+
+#[derive(Args)]
+struct CreateMarketArgs {
+    #[arg(long)]
+    collateral_mint: Pubkey,
+    #[arg(long)]
+    question: String,
+    #[arg(long)]
+    category: String,
+    /// Defaults to the signer
+    #[arg(long)]
+    resolver: Option<Pubkey>,
+    /// Seconds until trading closes
+    #[arg(long)]
+    duration: i64,
+    /// Market fee in bps (100 = 1%)
+    #[arg(long)]
+    fee: u64,
+    /// Create YES/NO as Token-2022 mints
+    #[arg(long)]
+    token_2022: bool,
+    /// Token-2022 metadata URI for the outcome mints
+    #[arg(long, requires = "token_2022")]
+    metadata_uri: Option<String>,
+    #[arg(long)]
+    max_open_interest: Option<u64>,
+    #[arg(long)]
+    max_position_per_wallet: Option<u64>,
+    #[arg(long, requires_all = ["price_move_window", "halt_cooldown"])]
+    max_price_move_bps: Option<u64>,
+    #[arg(long, default_value_t = 0)]
+    price_move_window: i64,
+    #[arg(long, default_value_t = 0)]
+    halt_cooldown: i64,
+    /// Outstanding shares below which the circuit breaker is skipped
+    #[arg(long, default_value_t = 0)]
+    breaker_min_liquidity: u64,
+    /// Collateral to seed as creator liquidity
+    #[arg(long)]
+    initial_liquidity: Option<u64>,
+    /// Opening YES probability in bps, used with --initial-liquidity
+    #[arg(long, default_value_t = 5_000)]
+    initial_yes_bps: u64,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a market; its id comes from the creator's profile
+    CreateMarket(Box<CreateMarketArgs>),
+    /// Buy YES or NO with collateral
+    Buy {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Pay with native SOL (wSOL markets only; the wSOL account must be empty)
+        #[arg(long)]
+        wrap_sol: bool,
+        /// Allowlist proof node as 64 hex characters, leaf to root; repeat
+        /// once per node
+        #[arg(long = "proof", value_name = "HEX", value_parser = parse_proof_node)]
+        proof: Vec<[u8; 32]>,
+    },
+    /// Sell shares back for collateral
+    Sell {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Receive native SOL (wSOL markets only; the wSOL account must be empty)
+        #[arg(long)]
+        unwrap_sol: bool,
+    },
+    /// Resolve a market (signer must be its resolver)
+    Resolve {
+        #[arg(long)]
+        market: Pubkey,
+        #[arg(long, value_enum)]
+        outcome: Outcome,
+    },
+    /// Redeem winning shares
+    Claim {
+        #[arg(long)]
+        market: Pubkey,
+        /// Receive native SOL (wSOL markets only; the wSOL account must be empty)
+        #[arg(long)]
+        unwrap_sol: bool,
+    },
+    /// List markets in creation order from the on-chain registry
+    List {
+        /// Only markets registered under this category
+        #[arg(long)]
+        category: Option<String>,
+        /// Only markets created by this wallet
+        #[arg(long)]
+        creator: Option<Pubkey>,
+    },
+    /// Print a market's state
+    Show {
+        #[arg(long)]
+        market: Pubkey,
+    },
+}
+
+fn parse_proof_node(node: &str) -> Result<[u8; 32]> {
+    if node.len() != 64 || !node.is_ascii() {
+        bail!("expected 64 hex characters");
+    }
+    let mut bytes = [0; 32];
+    for (byte, pair) in bytes.iter_mut().zip(node.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair)?;
+        *byte = u8::from_str_radix(pair, 16).with_context(|| format!("{pair:?} is not hex"))?;
+    }
+    Ok(bytes)
+}
+
+/// A counter read from `account`, which holds 0 until the account is first
+/// created. Other RPC failures are errors, not a fresh counter.
+fn counter(account: predection_sdk::rpc::Result<u64>) -> Result<u64> {
+    match account {
+        Err(ClientError::AccountNotFound(_)) => Ok(0),
+        account => Ok(account?),
+    }
+}
+
+/// Token program that owns `mint`.
+fn token_program_of(client: &RpcClient, mint: &Pubkey) -> Result<Pubkey> {
+    let account = client
+        .get_account(mint)?
+        .with_context(|| format!("mint {mint} not found"))?;
+    Ok(account.owner)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let client = RpcClient::new(&cli.url);
+
+    match cli.command {
+        Command::List { category, creator } => {
+            let mut markets = client.get_registered_markets(category.as_deref())?;
+            if let Some(creator) = creator {
+                markets.retain(|(_, market)| market.authority == creator);
+            }
+            display::print_market_list(&markets, cli.output);
+        }
+        Command::Show { market } => {
+            let state = client.get_market(&market)?;
+            let snapshot = display::MarketSnapshot::fetch(&client, market, state)?;
+            display::print_market(&snapshot, cli.output);
+        }
+        command => {
+            let payer = load_keypair(&cli.keypair)?;
+            let signature = execute(&client, &payer, command)?;
+            println!("Signature: {signature}");
+        }
+    }
+    Ok(())
+}
+
+/// Builds and sends the transaction for a state-changing command.
+fn execute(client: &RpcClient, payer: &Keypair, command: Command) -> Result<String> {
+    let signer = payer.pubkey();
+    let is_trade = matches!(command, Command::Buy { .. } | Command::Sell { .. });
+    let instruction = match command {
+        Command::CreateMarket(args) => {
+            let CreateMarketArgs {
+                collateral_mint,
+                question,
+                category,
+                resolver,
+                duration,
+                fee,
+                token_2022,
+                metadata_uri,
+                max_open_interest,
+                max_position_per_wallet,
+                max_price_move_bps,
+                price_move_window,
+                halt_cooldown,
+                breaker_min_liquidity,
+                initial_liquidity,
+                initial_yes_bps,
+            } = *args;
+
+            // The program only accepts the treasury set in the protocol config
+            let protocol_fee_collector = client
+                .get_anchor_account::<ProtocolConfig>(&pda::config().0)
+                .context("reading the protocol config")?
+                .treasury;
+            let market_id = counter(
+                client
+                    .get_anchor_account::<CreatorProfile>(&pda::creator_profile(&signer).0)
+                    .map(|profile| profile.next_market_id),
+            )?;
+            let registry_total_markets = counter(
+                client
+                    .get_anchor_account::<MarketRegistry>(&pda::registry().0)
+                    .map(|registry| registry.total_markets),
+            )?;
+            let category_total_markets = counter(
+                client
+                    .get_anchor_account::<CategoryRegistry>(&pda::category_registry(&category).0)
+                    .map(|registry| registry.total_markets),
+            )?;
+
+            let params = InitializeParams {
+                market_id,
+                resolver: resolver.unwrap_or(signer),
+                collateral_mint,
+                token_program: token_program_of(client, &collateral_mint)?,
+                outcome_token_program: if token_2022 {
+                    TOKEN_2022_PROGRAM_ID
+                } else {
+                    TOKEN_PROGRAM_ID
+                },
+                protocol_fee_collector,
+                question,
+                duration_time: duration,
+                fee,
+                limits: MarketLimits {
+                    max_open_interest,
+                    max_position_per_wallet,
+                    max_price_move_bps,
+                    price_move_window,
+                    halt_cooldown,
+                    breaker_min_liquidity,
+                },
+                allowlist_root: None,
+                metadata_uri,
+                category,
+                initial_liquidity: initial_liquidity.map(|amount| InitialLiquidity {
+                    amount,
+                    yes_bps: initial_yes_bps,
+                }),
+                registry_total_markets,
+                category_total_markets,
+            };
+            println!("Market: {}", pda::market(&signer, market_id).0);
+            instructions::initialize(&signer, params)
+        }
+        Command::Buy {
+            market,
+            amount,
+            side,
+            wrap_sol,
+            proof,
+        } => {
+            let state = client.get_market(&market)?;
+            let token_program = token_program_of(client, &state.collateral_mint)?;
+            instructions::buy_share(
+                &market,
+                &state,
+                &signer,
+                &token_program,
+                amount,
+                matches!(side, Side::Yes),
+                proof,
+                wrap_sol,
+            )
+        }
+        Command::Sell {
+            market,
+            amount,
+            side,
+            unwrap_sol,
+        } => {
+            let state = client.get_market(&market)?;
+            let token_program = token_program_of(client, &state.collateral_mint)?;
+            instructions::sell_share(
+                &market,
+                &state,
+                &signer,
+                &token_program,
+                amount,
+                matches!(side, Side::Yes),
+                unwrap_sol,
+            )
+        }
+        Command::Resolve { market, outcome } => {
+            let state = client.get_market(&market)?;
+            let resolution = match outcome {
+                Outcome::Yes => Resolution::Yes,
+                Outcome::No => Resolution::No,
+                Outcome::Invalid => Resolution::Invalid,
+            };
+            instructions::resolve_market(&market, &state, resolution)
+        }
+        Command::Claim { market, unwrap_sol } => {
+            let state = client.get_market(&market)?;
+            let token_program = token_program_of(client, &state.collateral_mint)?;
+            instructions::claim_winning(&market, &state, &signer, &token_program, unwrap_sol)
+        }
+        Command::List { .. } | Command::Show { .. } => unreachable!("read-only commands"),
+    };
+
+    if !is_trade {
+        return Ok(client.send_and_confirm(&[instruction], payer, &[])?);
+    }
+    // A trade the circuit breaker stops still confirms, without filling
+    let (signature, outcome) = client.send_trade(instruction, payer)?;
+    match outcome {
+        TradeOutcome::Filled { shares } => println!("Shares: {shares}"),
+        TradeOutcome::Halted { halted_until } => bail!(
+            "circuit breaker tripped: nothing traded, market halted until {halted_until} (transaction {signature})"
+        ),
+    }
+    Ok(signature)
+}
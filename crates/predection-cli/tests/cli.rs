@@ -0,0 +1,529 @@
+//! Runs the binary against a stub JSON-RPC server that serves a fixed set
+//! of accounts, so reads and error handling are checked without a cluster.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountSerialize, AnchorSerialize};
+use anchor_spl::token::ID as TOKEN_PROGRAM_ID;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use predection::states::{
+    CategoryRegistry, CreatorProfile, Market, MarketRegistry, ProtocolConfig, RegistryPage, Status,
+    TradeOutcome,
+};
+use predection_sdk::pda;
+use serde_json::{json, Value};
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+/// What the stub serves for an address.
+enum Entry {
+    Account { owner: Pubkey, data: Vec<u8> },
+    RpcError,
+}
+
+#[derive(Default)]
+struct Cluster {
+    entries: HashMap<Pubkey, Entry>,
+    /// Return data of every transaction sent; `None` leaves sending unstubbed
+    sent: Option<Vec<u8>>,
+    /// Wire bytes of the transactions sent, readable after `serve`
+    transactions: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl Cluster {
+    fn anchor<T: AccountSerialize>(&mut self, address: Pubkey, account: &T) {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        self.entries.insert(
+            address,
+            Entry::Account {
+                owner: predection::ID,
+                data,
+            },
+        );
+    }
+
+    fn account_json(&self, address: &str) -> Result<Value, Value> {
+        let address: Pubkey = address.parse().unwrap();
+        match self.entries.get(&address) {
+            None => Ok(Value::Null),
+            Some(Entry::RpcError) => Err(json!({ "code": -32005, "message": "node is behind" })),
+            Some(Entry::Account { owner, data }) => Ok(json!({
+                "owner": owner.to_string(),
+                "lamports": 1_000_000,
+                "data": [BASE64.encode(data), "base64"],
+            })),
+        }
+    }
+
+    fn respond(&self, request: &Value) -> Value {
+        let params = &request["params"];
+        let result = match request["method"].as_str().unwrap() {
+            "getAccountInfo" => self
+                .account_json(params[0].as_str().unwrap())
+                .map(|value| json!({ "value": value })),
+            "getMultipleAccounts" => params[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|address| self.account_json(address.as_str().unwrap()))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|values| json!({ "value": values })),
+            "getLatestBlockhash" if self.sent.is_some() => {
+                Ok(json!({ "value": { "blockhash": Pubkey::default().to_string() } }))
+            }
+            "sendTransaction" if self.sent.is_some() => {
+                let wire = BASE64.decode(params[0].as_str().unwrap()).unwrap();
+                self.transactions.lock().unwrap().push(wire);
+                Ok(json!("sent"))
+            }
+            "getSignatureStatuses" if self.sent.is_some() => Ok(json!({
+                "value": [{ "err": null, "confirmationStatus": "confirmed" }]
+            })),
+            "getTransaction" => match &self.sent {
+                Some(data) => Ok(json!({
+                    "meta": { "returnData": {
+                        "programId": predection::ID.to_string(),
+                        "data": [BASE64.encode(data), "base64"],
+                    } }
+                })),
+                None => Err(json!({ "code": -32601, "message": "getTransaction not stubbed" })),
+            },
+            method => Err(json!({ "code": -32601, "message": format!("{method} not stubbed") })),
+        };
+        match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": 1, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": 1, "error": error }),
+        }
+    }
+
+    /// Serves the cluster on a local port until the test exits.
+    fn serve(self) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let cluster = Arc::new(self);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim_end().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let response = cluster
+                    .respond(&serde_json::from_slice(&body).unwrap())
+                    .to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+}
+
+fn keypair_file(keypair: &Keypair) -> PathBuf {
+    let path =
+        PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.json", keypair.pubkey()));
+    std::fs::write(&path, json!(keypair.to_bytes().to_vec()).to_string()).unwrap();
+    path
+}
+
+fn run(url: &str, keypair: Option<&Keypair>, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_predection-cli"));
+    command.arg("--url").arg(url);
+    if let Some(keypair) = keypair {
+        command.arg("--keypair").arg(keypair_file(keypair));
+    }
+    command.args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn market(authority: Pubkey, market_id: u64) -> Market {
+    Market {
+        market_id,
+        authority,
+        resolver: authority,
+        resolution_time: 0,
+        option: None,
+        status: Status::Open,
+        yes_mint: Pubkey::new_unique(),
+        no_mint: Pubkey::new_unique(),
+        market_vault: Pubkey::new_unique(),
+        collateral_mint: Pubkey::new_unique(),
+        market_close_timestamp: 1_000,
+        question: format!("Market {market_id}?"),
+        fee_collector: authority,
+        fee_collector_ata: Pubkey::new_unique(),
+        protocol_fee_collector: Pubkey::new_unique(),
+        protocol_fee_collector_ata: Pubkey::new_unique(),
+        fee: 100,
+        bump: 0,
+        max_open_interest: None,
+        max_position_per_wallet: None,
+        max_price_move_bps: None,
+        price_move_window: 0,
+        halt_cooldown: 0,
+        halted_until: 0,
+        breaker_min_liquidity: 0,
+        allowlist_root: None,
+        outcome_token_program: TOKEN_PROGRAM_ID,
+        min_trade_size: 0,
+        trade_count: 0,
+        category: "crypto".to_string(),
+        registry_index: market_id,
+        resolution: None,
+        resolved_at: 0,
+        dispute_window: 0,
+        disputer: None,
+        dispute_bond: 0,
+        bond_amount: 0,
+    }
+}
+
+/// Cluster with an SPL collateral mint and the protocol config, ready for
+/// `create-market`.
+fn cluster_with_mint() -> (Cluster, Pubkey) {
+    let mint = Pubkey::new_unique();
+    let mut cluster = Cluster::default();
+    cluster.anchor(
+        pda::config().0,
+        &ProtocolConfig {
+            admin: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            bump: 0,
+        },
+    );
+    cluster.entries.insert(
+        mint,
+        Entry::Account {
+            owner: TOKEN_PROGRAM_ID,
+            data: vec![0; 82],
+        },
+    );
+    (cluster, mint)
+}
+
+fn create_market(url: &str, creator: &Keypair, mint: &Pubkey) -> Output {
+    let mint = mint.to_string();
+    run(
+        url,
+        Some(creator),
+        &[
+            "create-market",
+            "--collateral-mint",
+            &mint,
+            "--question",
+            "Will it rain?",
+            "--category",
+            "weather",
+            "--duration",
+            "3600",
+            "--fee",
+            "100",
+        ],
+    )
+}
+
+/// Buys YES on a market that returns `outcome` for every trade, returning
+/// the transactions sent.
+fn buy(outcome: TradeOutcome, args: &[&str]) -> (Output, Vec<Vec<u8>>) {
+    let (mut cluster, mint) = cluster_with_mint();
+    let market_address = Pubkey::new_unique();
+    cluster.anchor(
+        market_address,
+        &Market {
+            collateral_mint: mint,
+            ..market(Pubkey::new_unique(), 0)
+        },
+    );
+    let mut data = Vec::new();
+    outcome.serialize(&mut data).unwrap();
+    cluster.sent = Some(data);
+    let transactions = cluster.transactions.clone();
+    let market_address = market_address.to_string();
+    let mut command = vec![
+        "buy",
+        "--market",
+        &market_address,
+        "--amount",
+        "1000000",
+        "--side",
+        "yes",
+    ];
+    command.extend(args);
+    let output = run(&cluster.serve(), Some(&Keypair::new()), &command);
+    let transactions = transactions.lock().unwrap().clone();
+    (output, transactions)
+}
+
+fn listed(output: &Output) -> Vec<String> {
+    assert!(output.status.success(), "{}", stderr(output));
+    let rows: Value = serde_json::from_str(&stdout(output)).unwrap();
+    rows.as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row["address"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn first_market_of_a_creator_gets_id_zero() {
+    let (cluster, mint) = cluster_with_mint();
+    let creator = Keypair::new();
+    let output = create_market(&cluster.serve(), &creator, &mint);
+
+    // Sending fails at the stub, after the market address is derived
+    let market = pda::market(&creator.pubkey(), 0).0;
+    assert!(stdout(&output).contains(&format!("Market: {market}")));
+    assert!(stderr(&output).contains("getLatestBlockhash not stubbed"));
+}
+
+#[test]
+fn next_market_id_comes_from_the_creator_profile() {
+    let (mut cluster, mint) = cluster_with_mint();
+    let creator = Keypair::new();
+    cluster.anchor(
+        pda::creator_profile(&creator.pubkey()).0,
+        &CreatorProfile {
+            creator: creator.pubkey(),
+            next_market_id: 3,
+            markets_created: 3,
+            total_volume: 0,
+            disputes_lost: 0,
+            bump: 0,
+        },
+    );
+    let output = create_market(&cluster.serve(), &creator, &mint);
+
+    let market = pda::market(&creator.pubkey(), 3).0;
+    assert!(stdout(&output).contains(&format!("Market: {market}")));
+}
+
+#[test]
+fn rpc_errors_are_not_read_as_missing_counters() {
+    for counter in [
+        pda::config().0,
+        pda::registry().0,
+        pda::category_registry("weather").0,
+    ] {
+        let (mut cluster, mint) = cluster_with_mint();
+        cluster.entries.insert(counter, Entry::RpcError);
+        let output = create_market(&cluster.serve(), &Keypair::new(), &mint);
+
+        assert!(!output.status.success());
+        assert!(stderr(&output).contains("node is behind"));
+        assert!(!stdout(&output).contains("Market:"));
+    }
+}
+
+#[test]
+fn list_walks_the_registry_pages() {
+    let alice = Pubkey::new_unique();
+    let bob = Pubkey::new_unique();
+    let mut cluster = Cluster::default();
+    let mut addresses = Vec::new();
+    // 70 markets span two pages
+    for index in 0..70 {
+        let creator = if index % 2 == 0 { alice } else { bob };
+        let address = pda::market(&creator, index / 2).0;
+        cluster.anchor(address, &market(creator, index / 2));
+        addresses.push(address);
+    }
+    for (page_index, markets) in (0u64..).zip(addresses.chunks(64)) {
+        cluster.anchor(
+            pda::registry_page(page_index * 64).0,
+            &RegistryPage {
+                page_index,
+                markets: markets.to_vec(),
+                bump: 0,
+            },
+        );
+    }
+    cluster.anchor(
+        pda::registry().0,
+        &MarketRegistry {
+            total_markets: 70,
+            bump: 0,
+        },
+    );
+    let url = cluster.serve();
+
+    let all = listed(&run(&url, None, &["--output", "json", "list"]));
+    let expected: Vec<String> = addresses.iter().map(Pubkey::to_string).collect();
+    assert_eq!(all, expected);
+
+    let bob = bob.to_string();
+    let by_bob = listed(&run(
+        &url,
+        None,
+        &["--output", "json", "list", "--creator", &bob],
+    ));
+    let bobs: Vec<String> = expected.iter().skip(1).step_by(2).cloned().collect();
+    assert_eq!(by_bob, bobs);
+}
+
+#[test]
+fn list_by_category_reads_the_category_pages() {
+    let creator = Pubkey::new_unique();
+    let in_category = pda::market(&creator, 1).0;
+    let mut cluster = Cluster::default();
+    cluster.anchor(in_category, &market(creator, 1));
+    cluster.anchor(
+        pda::category_registry("crypto").0,
+        &CategoryRegistry {
+            category: "crypto".to_string(),
+            total_markets: 1,
+            bump: 0,
+        },
+    );
+    cluster.anchor(
+        pda::category_page("crypto", 0).0,
+        &RegistryPage {
+            page_index: 0,
+            markets: vec![in_category],
+            bump: 0,
+        },
+    );
+    let url = cluster.serve();
+
+    let crypto = listed(&run(
+        &url,
+        None,
+        &["--output", "json", "list", "--category", "crypto"],
+    ));
+    assert_eq!(crypto, vec![in_category.to_string()]);
+    let sports = listed(&run(
+        &url,
+        None,
+        &["--output", "json", "list", "--category", "sports"],
+    ));
+    assert!(sports.is_empty());
+}
+
+#[test]
+fn list_fails_on_a_missing_market() {
+    let missing = Pubkey::new_unique();
+    let mut cluster = Cluster::default();
+    cluster.anchor(
+        pda::registry().0,
+        &MarketRegistry {
+            total_markets: 1,
+            bump: 0,
+        },
+    );
+    cluster.anchor(
+        pda::registry_page(0).0,
+        &RegistryPage {
+            page_index: 0,
+            markets: vec![missing],
+            bump: 0,
+        },
+    );
+    let output = run(&cluster.serve(), None, &["list"]);
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains(&format!("account {missing} not found")));
+}
+
+#[test]
+fn trades_report_the_shares_filled() {
+    let (output, _) = buy(TradeOutcome::Filled { shares: 990_000 }, &[]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Shares: 990000"));
+}
+
+#[test]
+fn trades_the_circuit_breaker_stops_fail() {
+    let (output, _) = buy(
+        TradeOutcome::Halted {
+            halted_until: 1_700,
+        },
+        &[],
+    );
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("nothing traded, market halted until 1700"));
+}
+
+#[test]
+fn buys_send_the_allowlist_proof() {
+    let (leaf_sibling, root_sibling) = ([0x1a; 32], [0x2b; 32]);
+    let (output, transactions) = buy(
+        TradeOutcome::Filled { shares: 990_000 },
+        &["--proof", &"1a".repeat(32), "--proof", &"2B".repeat(32)],
+    );
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    // The proof is a Borsh `Vec<[u8; 32]>` in the instruction data
+    let mut proof = 2u32.to_le_bytes().to_vec();
+    proof.extend(leaf_sibling);
+    proof.extend(root_sibling);
+    assert_eq!(transactions.len(), 1);
+    assert!(transactions[0]
+        .windows(proof.len())
+        .any(|window| window == proof));
+}
+
+#[test]
+fn proof_nodes_must_be_32_bytes_of_hex() {
+    for node in ["1a".repeat(31), "zz".repeat(32)] {
+        let (output, transactions) = buy(
+            TradeOutcome::Filled { shares: 990_000 },
+            &["--proof", &node],
+        );
+
+        assert!(!output.status.success());
+        assert!(stderr(&output).contains("--proof"), "{}", stderr(&output));
+        assert!(transactions.is_empty());
+    }
+}
+
+#[test]
+fn missing_keypairs_name_the_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_predection-cli"))
+        .args(["--url", "http://127.0.0.1:1", "--keypair", "~/missing.json"])
+        .args(["resolve", "--market", &Pubkey::new_unique().to_string()])
+        .args(["--outcome", "yes"])
+        .env("HOME", env!("CARGO_TARGET_TMPDIR"))
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    let missing = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("missing.json");
+    assert!(
+        stderr(&output).contains(&format!("reading keypair {}", missing.display())),
+        "{}",
+        stderr(&output)
+    );
+}
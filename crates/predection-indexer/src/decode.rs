@@ -0,0 +1,459 @@
+//! Turns confirmed transactions, in the RPC `json` encoding, into the
+//! program events the store records.
+//!
+//! The program emits no Anchor events, so instructions are decoded from their
+//! data and accounts, and amounts the handlers compute on-chain are read from
+//! the transaction's token balance changes. Those changes cover the whole
+//! transaction and can't be split between instructions, so a transaction
+//! that moves one market's vault more than once is rejected. A trade that
+//! leaves the vault untouched was stopped by the circuit breaker and decodes
+//! to nothing.
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::{borsh, Pubkey};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{bail, Context, Result};
+use predection::instruction as ix;
+use predection::states::Resolution;
+use serde_json::Value;
+
+/// `buy_share` arguments before the unused market id was dropped. The two
+/// layouts differ in length by 8 bytes, so only one parses in full.
+#[derive(AnchorDeserialize)]
+struct LegacyBuyShare {
+    amount: u64,
+    _market_id: u64,
+    is_yes: bool,
+    _proof: Vec<[u8; 32]>,
+    _wrap_sol: bool,
+}
+
+// Account positions follow the field order of the program's Accounts
+// structs; tests/decode.rs checks them against the SDK builders.
+mod accounts {
+    pub mod initialize {
+        pub const CREATOR: usize = 0;
+        pub const MARKET: usize = 2;
+        pub const COLLATERAL_MINT: usize = 3;
+        pub const CONFIG: usize = 5;
+        pub const PROTOCOL_FEE_COLLECTOR: usize = 9;
+        pub const MARKET_VAULT: usize = 13;
+        /// Account count before `config` was added at `CONFIG`.
+        pub const LEGACY_LEN: usize = 25;
+    }
+    pub mod buy {
+        pub const TRADER: usize = 0;
+        pub const FEE_COLLECTOR_ATA: usize = 1;
+        pub const PROTOCOL_FEE_COLLECTOR_ATA: usize = 2;
+        pub const MARKET: usize = 3;
+        pub const MARKET_VAULT: usize = 5;
+        pub const TRADER_COLLATERAL_ATA: usize = 7;
+    }
+    pub mod sell {
+        pub const TRADER: usize = 0;
+        pub const MARKET: usize = 1;
+        pub const MARKET_VAULT: usize = 3;
+    }
+    pub mod claim {
+        pub const TRADER: usize = 0;
+        pub const MARKET: usize = 1;
+        pub const MARKET_VAULT: usize = 2;
+        pub const YES_ATA: usize = 7;
+        pub const NO_ATA: usize = 8;
+    }
+    pub mod resolve {
+        pub const RESOLVER: usize = 0;
+        pub const MARKET: usize = 1;
+    }
+    pub mod expire {
+        pub const MARKET: usize = 0;
+    }
+    pub mod dispute {
+        pub const DISPUTER: usize = 0;
+        pub const MARKET: usize = 1;
+    }
+    pub mod rule {
+        pub const ADMIN: usize = 0;
+        pub const MARKET: usize = 2;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    /// Program instructions in execution order, CPIs included.
+    pub events: Vec<Event>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    MarketCreated {
+        market: Pubkey,
+        creator: Pubkey,
+        resolver: Pubkey,
+        collateral_mint: Pubkey,
+        protocol_fee_collector: Pubkey,
+        question: String,
+        category: String,
+        fee_bps: u64,
+        /// Block time plus duration; the program uses its clock at execution.
+        close_timestamp: Option<i64>,
+        /// Collateral the vault received from the creator's seed
+        initial_liquidity: u64,
+        initial_yes_bps: u64,
+    },
+    Buy {
+        market: Pubkey,
+        trader: Pubkey,
+        is_yes: bool,
+        /// Gross collateral paid, fees included
+        amount: u64,
+        /// Collateral the vault received, which is also the shares minted
+        shares: u64,
+        /// Fees the collectors received. None when the collector's account
+        /// also pays or receives in the trade, so its change isn't the fee.
+        protocol_fee: Option<u64>,
+        creator_fee: Option<u64>,
+    },
+    Sell {
+        market: Pubkey,
+        trader: Pubkey,
+        is_yes: bool,
+        /// Collateral the vault paid out, which is also the shares burned
+        shares: u64,
+    },
+    Claim {
+        market: Pubkey,
+        trader: Pubkey,
+        yes_burned: u64,
+        no_burned: u64,
+        payout: u64,
+    },
+    Resolved {
+        market: Pubkey,
+        resolver: Pubkey,
+        resolution: Resolution,
+    },
+    /// Voided as INVALID by `expire_market`, which anyone can send
+    Expired {
+        market: Pubkey,
+        /// Fee payer of the transaction that sent it
+        caller: Pubkey,
+    },
+    Disputed {
+        market: Pubkey,
+        disputer: Pubkey,
+    },
+    DisputeRuled {
+        market: Pubkey,
+        admin: Pubkey,
+        ruling: Resolution,
+    },
+}
+
+impl Event {
+    /// The market whose vault this event moves, if any.
+    fn vault_market(&self) -> Option<&Pubkey> {
+        match self {
+            Event::MarketCreated {
+                market,
+                initial_liquidity,
+                ..
+            } if *initial_liquidity > 0 => Some(market),
+            Event::Buy { market, .. }
+            | Event::Sell { market, .. }
+            | Event::Claim { market, .. } => Some(market),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a `getTransaction` result. Failed transactions decode with no
+/// events.
+pub fn parse_transaction(value: &Value) -> Result<Transaction> {
+    let signature = value["transaction"]["signatures"][0]
+        .as_str()
+        .context("transaction has no signature")?
+        .to_string();
+    let slot = value["slot"].as_u64().context("transaction has no slot")?;
+    let block_time = value["blockTime"].as_i64();
+
+    let meta = &value["meta"];
+    let mut events = Vec::new();
+    if meta["err"].is_null() {
+        let context = TxContext::new(value)?;
+        for instruction in context.instructions()? {
+            if let Some(event) = context.decode(&instruction, block_time)? {
+                events.push(event);
+            }
+        }
+        let mut traded = Vec::new();
+        for market in events.iter().filter_map(Event::vault_market) {
+            if traded.contains(market) {
+                bail!("transaction {signature} moves the vault of {market} more than once");
+            }
+            traded.push(*market);
+        }
+    }
+
+    Ok(Transaction {
+        signature,
+        slot,
+        block_time,
+        events,
+    })
+}
+
+struct Instruction {
+    program_id: Pubkey,
+    accounts: Vec<Pubkey>,
+    data: Vec<u8>,
+}
+
+impl Instruction {
+    fn account(&self, index: usize) -> Result<Pubkey> {
+        self.accounts
+            .get(index)
+            .copied()
+            .with_context(|| format!("instruction has no account {index}"))
+    }
+}
+
+struct TxContext<'a> {
+    value: &'a Value,
+    keys: Vec<Pubkey>,
+}
+
+impl<'a> TxContext<'a> {
+    fn new(value: &'a Value) -> Result<Self> {
+        let message = &value["transaction"]["message"];
+        let loaded = &value["meta"]["loadedAddresses"];
+        // Lookup-table accounts follow the static keys, writable first
+        let keys = [
+            &message["accountKeys"],
+            &loaded["writable"],
+            &loaded["readonly"],
+        ]
+        .into_iter()
+        .filter_map(Value::as_array)
+        .flatten()
+        .map(parse_pubkey)
+        .collect::<Result<_>>()?;
+        Ok(Self { value, keys })
+    }
+
+    /// Top-level instructions, each followed by the inner instructions it
+    /// invoked.
+    fn instructions(&self) -> Result<Vec<Instruction>> {
+        let empty = Vec::new();
+        let top = self.value["transaction"]["message"]["instructions"]
+            .as_array()
+            .context("transaction has no instructions")?;
+        let inner = self.value["meta"]["innerInstructions"]
+            .as_array()
+            .unwrap_or(&empty);
+
+        let mut instructions = Vec::new();
+        for (index, instruction) in top.iter().enumerate() {
+            instructions.push(self.instruction(instruction)?);
+            let invoked = inner
+                .iter()
+                .filter(|group| group["index"].as_u64() == Some(index as u64))
+                .filter_map(|group| group["instructions"].as_array())
+                .flatten();
+            for instruction in invoked {
+                instructions.push(self.instruction(instruction)?);
+            }
+        }
+        Ok(instructions)
+    }
+
+    fn instruction(&self, value: &Value) -> Result<Instruction> {
+        let key = |index: &Value| -> Result<Pubkey> {
+            let index = index.as_u64().context("account index is not a number")? as usize;
+            self.keys
+                .get(index)
+                .copied()
+                .with_context(|| format!("account index {index} out of range"))
+        };
+        let data = value["data"].as_str().context("instruction has no data")?;
+        Ok(Instruction {
+            program_id: key(&value["programIdIndex"])?,
+            accounts: value["accounts"]
+                .as_array()
+                .context("instruction has no accounts")?
+                .iter()
+                .map(key)
+                .collect::<Result<_>>()?,
+            data: bs58::decode(data)
+                .into_vec()
+                .context("instruction data is not base58")?,
+        })
+    }
+
+    fn fee_payer(&self) -> Result<Pubkey> {
+        self.keys
+            .first()
+            .copied()
+            .context("transaction has no account keys")
+    }
+
+    /// Change in `account`'s token balance over the whole transaction.
+    /// Accounts created or closed by it count as zero on that side.
+    fn token_delta(&self, account: &Pubkey) -> i128 {
+        let Some(index) = self.keys.iter().position(|key| key == account) else {
+            return 0;
+        };
+        let balance = |field: &str| -> i128 {
+            self.value["meta"][field]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|balance| balance["accountIndex"].as_u64() == Some(index as u64))
+                .and_then(|balance| balance["uiTokenAmount"]["amount"].as_str())
+                .and_then(|amount| amount.parse().ok())
+                .unwrap_or(0)
+        };
+        balance("postTokenBalances") - balance("preTokenBalances")
+    }
+
+    fn decode(&self, instruction: &Instruction, block_time: Option<i64>) -> Result<Option<Event>> {
+        if instruction.program_id != predection::ID || instruction.data.len() < 8 {
+            return Ok(None);
+        }
+        let (discriminator, mut args) = instruction.data.split_at(8);
+
+        let event = if discriminator == ix::Initialize::DISCRIMINATOR {
+            use accounts::initialize::*;
+            let args = ix::Initialize::deserialize(&mut args)?;
+            let liquidity = args.initial_liquidity.as_ref();
+            let legacy = instruction.accounts.len() == LEGACY_LEN;
+            let account = |index: usize| {
+                instruction.account(if legacy && index > CONFIG {
+                    index - 1
+                } else {
+                    index
+                })
+            };
+            Event::MarketCreated {
+                market: account(MARKET)?,
+                creator: account(CREATOR)?,
+                resolver: args.resolver,
+                collateral_mint: account(COLLATERAL_MINT)?,
+                protocol_fee_collector: account(PROTOCOL_FEE_COLLECTOR)?,
+                question: String::from_utf8_lossy(&args.question).into_owned(),
+                category: args.category,
+                fee_bps: args.fee,
+                close_timestamp: block_time.map(|time| time.saturating_add(args.duration_time)),
+                initial_liquidity: match liquidity {
+                    Some(_) => amount(self.token_delta(&account(MARKET_VAULT)?))?,
+                    None => 0,
+                },
+                initial_yes_bps: liquidity.map_or(0, |seed| seed.yes_bps),
+            }
+        } else if discriminator == ix::BuyShare::DISCRIMINATOR {
+            use accounts::buy::*;
+            let (collateral, is_yes) = match ix::BuyShare::try_from_slice(args) {
+                Ok(args) => (args.amount, args.is_yes),
+                Err(_) => {
+                    let args = LegacyBuyShare::try_from_slice(args)?;
+                    (args.amount, args.is_yes)
+                }
+            };
+            let shares = amount(self.token_delta(&instruction.account(MARKET_VAULT)?))?;
+            // The circuit breaker halted the market instead of filling
+            if shares == 0 {
+                return Ok(None);
+            }
+            let creator_ata = instruction.account(FEE_COLLECTOR_ATA)?;
+            let protocol_ata = instruction.account(PROTOCOL_FEE_COLLECTOR_ATA)?;
+            let trader_ata = instruction.account(TRADER_COLLATERAL_ATA)?;
+            // An account shared with another party nets their transfers out
+            let fee = |collector: Pubkey, others: [Pubkey; 2]| -> Result<Option<u64>> {
+                if others.contains(&collector) {
+                    return Ok(None);
+                }
+                amount(self.token_delta(&collector)).map(Some)
+            };
+            Event::Buy {
+                market: instruction.account(MARKET)?,
+                trader: instruction.account(TRADER)?,
+                is_yes,
+                amount: collateral,
+                shares,
+                protocol_fee: fee(protocol_ata, [creator_ata, trader_ata])?,
+                creator_fee: fee(creator_ata, [protocol_ata, trader_ata])?,
+            }
+        } else if discriminator == ix::SellShare::DISCRIMINATOR {
+            use accounts::sell::*;
+            let args = ix::SellShare::deserialize(&mut args)?;
+            let shares = amount(-self.token_delta(&instruction.account(MARKET_VAULT)?))?;
+            // The circuit breaker halted the market instead of filling
+            if shares == 0 {
+                return Ok(None);
+            }
+            Event::Sell {
+                market: instruction.account(MARKET)?,
+                trader: instruction.account(TRADER)?,
+                is_yes: args.is_yes,
+                shares,
+            }
+        } else if discriminator == ix::ClaimWinning::DISCRIMINATOR {
+            use accounts::claim::*;
+            Event::Claim {
+                market: instruction.account(MARKET)?,
+                trader: instruction.account(TRADER)?,
+                yes_burned: amount(-self.token_delta(&instruction.account(YES_ATA)?))?,
+                no_burned: amount(-self.token_delta(&instruction.account(NO_ATA)?))?,
+                payout: amount(-self.token_delta(&instruction.account(MARKET_VAULT)?))?,
+            }
+        } else if discriminator == ix::ResolveMarket::DISCRIMINATOR {
+            use accounts::resolve::*;
+            let args = ix::ResolveMarket::deserialize(&mut args)?;
+            Event::Resolved {
+                market: instruction.account(MARKET)?,
+                resolver: instruction.account(RESOLVER)?,
+                resolution: args.resolution,
+            }
+        } else if discriminator == ix::ExpireMarket::DISCRIMINATOR {
+            use accounts::expire::*;
+            Event::Expired {
+                market: instruction.account(MARKET)?,
+                caller: self.fee_payer()?,
+            }
+        } else if discriminator == ix::DisputeMarket::DISCRIMINATOR {
+            use accounts::dispute::*;
+            Event::Disputed {
+                market: instruction.account(MARKET)?,
+                disputer: instruction.account(DISPUTER)?,
+            }
+        } else if discriminator == ix::RuleDispute::DISCRIMINATOR {
+            use accounts::rule::*;
+            let args = ix::RuleDispute::deserialize(&mut args)?;
+            Event::DisputeRuled {
+                market: instruction.account(MARKET)?,
+                admin: instruction.account(ADMIN)?,
+                ruling: args.ruling,
+            }
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(event))
+    }
+}
+
+fn parse_pubkey(value: &Value) -> Result<Pubkey> {
+    let key = value.as_str().context("account key is not a string")?;
+    Pubkey::from_str(key).with_context(|| format!("invalid account key {key}"))
+}
+
+/// A token balance change that must be a non-negative u64.
+fn amount(delta: i128) -> Result<u64> {
+    match u64::try_from(delta) {
+        Ok(amount) => Ok(amount),
+        Err(_) => bail!("unexpected token balance change {delta}"),
+    }
+}
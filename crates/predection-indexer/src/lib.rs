@@ -0,0 +1,10 @@
+//! Indexes predection transactions into SQLite.
+//!
+//! [`decode`] turns confirmed transactions into program events and [`store`]
+//! folds them into market, trade, position and fee tables.
+
+pub mod decode;
+pub mod store;
+
+pub use decode::{parse_transaction, Event, Transaction};
+pub use store::{Ingested, Store};
@@ -0,0 +1,132 @@
+//! `predection-indexer`: follows the program's transactions into SQLite.
+//!
+//! Transactions come either from a JSON-RPC node or from a dump file holding
+//! a JSON array of `getTransaction` results.
+
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use predection_indexer::{parse_transaction, Store};
+use predection_sdk::rpc::RpcClient;
+use predection_sdk::PROGRAM_ID;
+use serde_json::Value;
+
+/// getSignaturesForAddress's maximum page size
+const PAGE_SIZE: usize = 1_000;
+
+#[derive(Parser)]
+#[command(
+    name = "predection-indexer",
+    about = "Index predection transactions into SQLite"
+)]
+struct Cli {
+    /// SQLite database, created if missing
+    #[arg(long, env = "PREDECTION_INDEXER_DB", default_value = "predection.db")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch the program's transactions since the last run
+    Rpc {
+        /// JSON-RPC endpoint
+        #[arg(
+            long,
+            env = "PREDECTION_RPC_URL",
+            default_value = "http://127.0.0.1:8899"
+        )]
+        url: String,
+        /// Keep polling, sleeping this many seconds between rounds
+        #[arg(long)]
+        poll_interval: Option<u64>,
+    },
+    /// Load a JSON array of getTransaction results
+    Dump { file: PathBuf },
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut store = Store::open(&cli.db)?;
+
+    match cli.command {
+        Command::Rpc { url, poll_interval } => {
+            let client = RpcClient::new(url);
+            loop {
+                let indexed = sync(&client, &mut store)?;
+                if indexed > 0 {
+                    println!("indexed {indexed} transactions");
+                }
+                let Some(seconds) = poll_interval else {
+                    break;
+                };
+                thread::sleep(Duration::from_secs(seconds));
+            }
+        }
+        Command::Dump { file } => {
+            let contents =
+                std::fs::read(&file).with_context(|| format!("reading {}", file.display()))?;
+            let mut transactions: Vec<Value> = serde_json::from_slice(&contents)
+                .with_context(|| format!("{} is not a JSON array", file.display()))?;
+            transactions.sort_by_key(|transaction| transaction["slot"].as_u64());
+
+            let mut indexed = 0;
+            for value in &transactions {
+                indexed += usize::from(ingest(&mut store, value)?);
+            }
+            println!("indexed {indexed} of {} transactions", transactions.len());
+        }
+    }
+    Ok(())
+}
+
+/// Indexes every signature newer than the cursor, oldest first, advancing
+/// the cursor after each so an interrupted run resumes where it stopped.
+fn sync(client: &RpcClient, store: &mut Store) -> Result<usize> {
+    let cursor = store.cursor()?;
+    let mut signatures = Vec::new();
+    loop {
+        let page = client.get_signatures_for_address(
+            &PROGRAM_ID,
+            signatures.last().map(String::as_str),
+            cursor.as_deref(),
+            PAGE_SIZE,
+        )?;
+        let full = page.len() == PAGE_SIZE;
+        signatures.extend(page.into_iter().map(|info| info.signature));
+        if !full {
+            break;
+        }
+    }
+
+    let mut indexed = 0;
+    for signature in signatures.iter().rev() {
+        let value = client
+            .get_transaction(signature)?
+            .with_context(|| format!("transaction {signature} not found"))?;
+        indexed += usize::from(ingest(store, &value)?);
+        store.set_cursor(signature)?;
+    }
+    Ok(indexed)
+}
+
+/// Indexes one `getTransaction` result, logging the markets it skipped.
+/// Returns false if it was already indexed.
+fn ingest(store: &mut Store, value: &Value) -> Result<bool> {
+    let transaction = parse_transaction(value)?;
+    let Some(ingested) = store.ingest(&transaction)? else {
+        return Ok(false);
+    };
+    for market in ingested.skipped_markets {
+        eprintln!(
+            "{}: skipping events for unindexed market {market}",
+            transaction.signature
+        );
+    }
+    Ok(true)
+}
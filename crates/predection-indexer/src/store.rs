@@ -0,0 +1,606 @@
+//! SQLite tables the indexer maintains.
+//!
+//! Every transaction is applied inside one SQLite transaction and recorded in
+//! `transactions`, so re-ingesting overlapping ranges is harmless.
+
+use std::path::Path;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{Context, Result};
+use predection::math::{self, MathError};
+use predection::states::Resolution;
+use predection_sdk::accounts::resolution_label;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::decode::{Event, Transaction};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    signature  TEXT PRIMARY KEY,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS markets (
+    address                TEXT PRIMARY KEY,
+    creator                TEXT NOT NULL,
+    resolver               TEXT NOT NULL,
+    collateral_mint        TEXT NOT NULL,
+    protocol_fee_collector TEXT NOT NULL,
+    question               TEXT NOT NULL,
+    category               TEXT NOT NULL,
+    fee_bps                INTEGER NOT NULL,
+    close_timestamp        INTEGER,
+    status                 TEXT NOT NULL,
+    resolution             TEXT,
+    resolved_at            INTEGER,
+    initial_liquidity      INTEGER NOT NULL,
+    yes_supply             INTEGER NOT NULL,
+    no_supply              INTEGER NOT NULL,
+    vault_balance          INTEGER NOT NULL,
+    volume                 INTEGER NOT NULL DEFAULT 0,
+    trade_count            INTEGER NOT NULL DEFAULT 0,
+    created_slot           INTEGER NOT NULL,
+    created_at             INTEGER,
+    signature              TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS markets_by_creator ON markets (creator);
+CREATE INDEX IF NOT EXISTS markets_by_category ON markets (category);
+
+-- kind is 'buy', 'sell' or 'claim'; side is NULL for INVALID claims
+CREATE TABLE IF NOT EXISTS trades (
+    id          INTEGER PRIMARY KEY,
+    signature   TEXT NOT NULL,
+    market      TEXT NOT NULL,
+    trader      TEXT NOT NULL,
+    kind        TEXT NOT NULL,
+    side        TEXT,
+    collateral  INTEGER NOT NULL,
+    shares      INTEGER NOT NULL,
+    fee         INTEGER NOT NULL,
+    yes_price   INTEGER NOT NULL,
+    slot        INTEGER NOT NULL,
+    block_time  INTEGER
+);
+CREATE INDEX IF NOT EXISTS trades_by_market ON trades (market, slot);
+CREATE INDEX IF NOT EXISTS trades_by_trader ON trades (trader, slot);
+
+CREATE TABLE IF NOT EXISTS positions (
+    market               TEXT NOT NULL,
+    owner                TEXT NOT NULL,
+    yes_shares           INTEGER NOT NULL,
+    no_shares            INTEGER NOT NULL,
+    collateral_deposited INTEGER NOT NULL,
+    collateral_withdrawn INTEGER NOT NULL,
+    PRIMARY KEY (market, owner)
+);
+CREATE INDEX IF NOT EXISTS positions_by_owner ON positions (owner);
+
+-- kind is 'resolve', 'expire', 'dispute' or 'ruling'
+CREATE TABLE IF NOT EXISTS resolutions (
+    id         INTEGER PRIMARY KEY,
+    signature  TEXT NOT NULL,
+    market     TEXT NOT NULL,
+    kind       TEXT NOT NULL,
+    actor      TEXT NOT NULL,
+    resolution TEXT,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER
+);
+
+-- kind is 'protocol' or 'creator'
+CREATE TABLE IF NOT EXISTS fee_payouts (
+    id         INTEGER PRIMARY KEY,
+    signature  TEXT NOT NULL,
+    market     TEXT NOT NULL,
+    recipient  TEXT NOT NULL,
+    kind       TEXT NOT NULL,
+    amount     INTEGER NOT NULL,
+    slot       INTEGER NOT NULL,
+    block_time INTEGER
+);
+CREATE INDEX IF NOT EXISTS fee_payouts_by_recipient ON fee_payouts (recipient);
+
+-- Newest signature fetched from RPC
+CREATE TABLE IF NOT EXISTS cursor (
+    id        INTEGER PRIMARY KEY CHECK (id = 1),
+    signature TEXT NOT NULL
+);
+";
+
+/// Market state the indexer keeps current to price trades.
+struct MarketRow {
+    creator: String,
+    protocol_fee_collector: String,
+    fee_bps: u64,
+    yes_supply: u64,
+    no_supply: u64,
+    vault_balance: u64,
+}
+
+pub struct Store {
+    connection: Connection,
+}
+
+/// What [`Store::ingest`] applied from a new transaction.
+#[derive(Debug, Default, PartialEq)]
+pub struct Ingested {
+    /// Markets created before indexing began, whose events were skipped
+    /// because they can't be priced.
+    pub skipped_markets: Vec<Pubkey>,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let connection =
+            Connection::open(path).with_context(|| format!("opening {}", path.display()))?;
+        Self::with_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn cursor(&self) -> Result<Option<String>> {
+        Ok(self
+            .connection
+            .query_row("SELECT signature FROM cursor WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    pub fn set_cursor(&self, signature: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO cursor (id, signature) VALUES (1, ?1)
+             ON CONFLICT (id) DO UPDATE SET signature = excluded.signature",
+            [signature],
+        )?;
+        Ok(())
+    }
+
+    /// Applies a decoded transaction. Returns None if it was already indexed.
+    pub fn ingest(&mut self, transaction: &Transaction) -> Result<Option<Ingested>> {
+        let db = self.connection.transaction()?;
+        let inserted = db.execute(
+            "INSERT OR IGNORE INTO transactions (signature, slot, block_time) VALUES (?1, ?2, ?3)",
+            params![
+                transaction.signature,
+                int(transaction.slot)?,
+                transaction.block_time
+            ],
+        )?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        let mut writer = Writer {
+            db: &db,
+            transaction,
+            ingested: Ingested::default(),
+        };
+        for event in &transaction.events {
+            writer
+                .apply(event)
+                .with_context(|| format!("indexing {}", transaction.signature))?;
+        }
+        let ingested = writer.ingested;
+        db.commit()?;
+        Ok(Some(ingested))
+    }
+}
+
+struct Writer<'a> {
+    db: &'a rusqlite::Transaction<'a>,
+    transaction: &'a Transaction,
+    ingested: Ingested,
+}
+
+impl Writer<'_> {
+    fn apply(&mut self, event: &Event) -> Result<()> {
+        match event {
+            Event::MarketCreated {
+                market,
+                creator,
+                resolver,
+                collateral_mint,
+                protocol_fee_collector,
+                question,
+                category,
+                fee_bps,
+                close_timestamp,
+                initial_liquidity,
+                initial_yes_bps,
+            } => {
+                let (yes, no) = if *initial_liquidity > 0 {
+                    math::split_by_odds(*initial_liquidity, *initial_yes_bps).map_err(math_error)?
+                } else {
+                    (0, 0)
+                };
+                self.db.execute(
+                    "INSERT OR IGNORE INTO markets (
+                        address, creator, resolver, collateral_mint, protocol_fee_collector,
+                        question, category, fee_bps, close_timestamp, status,
+                        initial_liquidity, yes_supply, no_supply, vault_balance,
+                        created_slot, created_at, signature
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'open', ?10, ?11, ?12, ?10, ?13, ?14, ?15)",
+                    params![
+                        market.to_string(),
+                        creator.to_string(),
+                        resolver.to_string(),
+                        collateral_mint.to_string(),
+                        protocol_fee_collector.to_string(),
+                        question,
+                        category,
+                        int(*fee_bps)?,
+                        close_timestamp,
+                        int(*initial_liquidity)?,
+                        int(yes)?,
+                        int(no)?,
+                        int(self.transaction.slot)?,
+                        self.transaction.block_time,
+                        self.transaction.signature,
+                    ],
+                )?;
+                if *initial_liquidity > 0 {
+                    self.update_position(
+                        market,
+                        creator,
+                        yes as i128,
+                        no as i128,
+                        *initial_liquidity,
+                        0,
+                    )?;
+                }
+            }
+            Event::Buy {
+                market,
+                trader,
+                is_yes,
+                amount,
+                shares,
+                protocol_fee,
+                creator_fee,
+            } => {
+                let Some(mut row) = self.market(market)? else {
+                    return Ok(());
+                };
+                // Quoted only for collectors whose balance change isn't the fee
+                let quote = math::quote_buy(*amount, row.fee_bps).map_err(math_error)?;
+                let protocol_fee = protocol_fee.unwrap_or(quote.fee.protocol);
+                let creator_fee = creator_fee.unwrap_or(quote.fee.creator);
+                let (yes, no) = side_amounts(*is_yes, *shares as i128);
+                row.vault_balance += shares;
+                self.update_supplies(market, &mut row, yes, no, *amount)?;
+                self.insert_trade(
+                    market,
+                    trader,
+                    "buy",
+                    Some(*is_yes),
+                    *amount,
+                    *shares,
+                    protocol_fee.saturating_add(creator_fee),
+                    &row,
+                )?;
+                self.update_position(market, trader, yes, no, *shares, 0)?;
+
+                for (kind, recipient, amount) in [
+                    ("protocol", &row.protocol_fee_collector, protocol_fee),
+                    ("creator", &row.creator, creator_fee),
+                ] {
+                    if amount > 0 {
+                        self.insert_fee_payout(market, recipient, kind, amount)?;
+                    }
+                }
+            }
+            Event::Sell {
+                market,
+                trader,
+                is_yes,
+                shares,
+            } => {
+                let Some(mut row) = self.market(market)? else {
+                    return Ok(());
+                };
+                let (yes, no) = side_amounts(*is_yes, -(*shares as i128));
+                row.vault_balance = row.vault_balance.saturating_sub(*shares);
+                self.update_supplies(market, &mut row, yes, no, *shares)?;
+                self.insert_trade(
+                    market,
+                    trader,
+                    "sell",
+                    Some(*is_yes),
+                    *shares,
+                    *shares,
+                    0,
+                    &row,
+                )?;
+                self.update_position(market, trader, yes, no, 0, *shares)?;
+            }
+            Event::Claim {
+                market,
+                trader,
+                yes_burned,
+                no_burned,
+                payout,
+            } => {
+                let Some(mut row) = self.market(market)? else {
+                    return Ok(());
+                };
+                let side = match (*yes_burned > 0, *no_burned > 0) {
+                    (true, false) => Some(true),
+                    (false, true) => Some(false),
+                    _ => None,
+                };
+                let (yes, no) = (-(*yes_burned as i128), -(*no_burned as i128));
+                row.vault_balance = row.vault_balance.saturating_sub(*payout);
+                self.update_supplies(market, &mut row, yes, no, 0)?;
+                self.insert_trade(
+                    market,
+                    trader,
+                    "claim",
+                    side,
+                    *payout,
+                    yes_burned + no_burned,
+                    0,
+                    &row,
+                )?;
+                self.update_position(market, trader, yes, no, 0, *payout)?;
+            }
+            Event::Resolved {
+                market,
+                resolver,
+                resolution,
+            } => {
+                self.set_status(market, "resolved", Some(*resolution))?;
+                self.insert_resolution(market, "resolve", resolver, Some(*resolution))?;
+            }
+            Event::Expired { market, caller } => {
+                let resolution = Some(Resolution::Invalid);
+                self.set_status(market, "resolved", resolution)?;
+                self.insert_resolution(market, "expire", caller, resolution)?;
+            }
+            Event::Disputed { market, disputer } => {
+                self.set_status(market, "disputed", None)?;
+                self.insert_resolution(market, "dispute", disputer, None)?;
+            }
+            Event::DisputeRuled {
+                market,
+                admin,
+                ruling,
+            } => {
+                self.set_status(market, "resolved", Some(*ruling))?;
+                self.insert_resolution(market, "ruling", admin, Some(*ruling))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// None for markets created before indexing began, which are recorded
+    /// as skipped.
+    fn market(&mut self, market: &Pubkey) -> Result<Option<MarketRow>> {
+        let row = self
+            .db
+            .query_row(
+                "SELECT creator, protocol_fee_collector, fee_bps, yes_supply, no_supply, vault_balance
+                 FROM markets WHERE address = ?1",
+                [market.to_string()],
+                |row| {
+                    Ok(MarketRow {
+                        creator: row.get(0)?,
+                        protocol_fee_collector: row.get(1)?,
+                        fee_bps: row.get::<_, i64>(2)? as u64,
+                        yes_supply: row.get::<_, i64>(3)? as u64,
+                        no_supply: row.get::<_, i64>(4)? as u64,
+                        vault_balance: row.get::<_, i64>(5)? as u64,
+                    })
+                },
+            )
+            .optional()?;
+        if row.is_none() && !self.ingested.skipped_markets.contains(market) {
+            self.ingested.skipped_markets.push(*market);
+        }
+        Ok(row)
+    }
+
+    fn update_supplies(
+        &self,
+        market: &Pubkey,
+        row: &mut MarketRow,
+        yes: i128,
+        no: i128,
+        volume: u64,
+    ) -> Result<()> {
+        row.yes_supply = apply_delta(row.yes_supply, yes);
+        row.no_supply = apply_delta(row.no_supply, no);
+        let traded = i64::from(volume > 0);
+        self.db.execute(
+            "UPDATE markets SET yes_supply = ?2, no_supply = ?3, vault_balance = ?4,
+                volume = volume + ?5, trade_count = trade_count + ?6
+             WHERE address = ?1",
+            params![
+                market.to_string(),
+                int(row.yes_supply)?,
+                int(row.no_supply)?,
+                int(row.vault_balance)?,
+                int(volume)?,
+                traded,
+            ],
+        )?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn insert_trade(
+        &self,
+        market: &Pubkey,
+        trader: &Pubkey,
+        kind: &str,
+        side: Option<bool>,
+        collateral: u64,
+        shares: u64,
+        fee: u64,
+        row: &MarketRow,
+    ) -> Result<()> {
+        self.db.execute(
+            "INSERT INTO trades (signature, market, trader, kind, side, collateral, shares, fee,
+                yes_price, slot, block_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                self.transaction.signature,
+                market.to_string(),
+                trader.to_string(),
+                kind,
+                side.map(|is_yes| if is_yes { "yes" } else { "no" }),
+                int(collateral)?,
+                int(shares)?,
+                int(fee)?,
+                int(math::yes_price(row.yes_supply, row.no_supply))?,
+                int(self.transaction.slot)?,
+                self.transaction.block_time,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update_position(
+        &self,
+        market: &Pubkey,
+        owner: &Pubkey,
+        yes: i128,
+        no: i128,
+        deposited: u64,
+        withdrawn: u64,
+    ) -> Result<()> {
+        let current: (i64, i64) = self
+            .db
+            .query_row(
+                "SELECT yes_shares, no_shares FROM positions WHERE market = ?1 AND owner = ?2",
+                [market.to_string(), owner.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .unwrap_or_default();
+        self.db.execute(
+            "INSERT INTO positions (market, owner, yes_shares, no_shares,
+                collateral_deposited, collateral_withdrawn)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (market, owner) DO UPDATE SET
+                yes_shares = excluded.yes_shares,
+                no_shares = excluded.no_shares,
+                collateral_deposited = collateral_deposited + excluded.collateral_deposited,
+                collateral_withdrawn = collateral_withdrawn + excluded.collateral_withdrawn",
+            params![
+                market.to_string(),
+                owner.to_string(),
+                int(apply_delta(current.0 as u64, yes))?,
+                int(apply_delta(current.1 as u64, no))?,
+                int(deposited)?,
+                int(withdrawn)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn set_status(
+        &self,
+        market: &Pubkey,
+        status: &str,
+        resolution: Option<Resolution>,
+    ) -> Result<()> {
+        match resolution {
+            Some(resolution) => self.db.execute(
+                "UPDATE markets SET status = ?2, resolution = ?3, resolved_at = ?4 WHERE address = ?1",
+                params![
+                    market.to_string(),
+                    status,
+                    resolution_label(Some(resolution)),
+                    self.transaction.block_time,
+                ],
+            )?,
+            None => self.db.execute(
+                "UPDATE markets SET status = ?2 WHERE address = ?1",
+                params![market.to_string(), status],
+            )?,
+        };
+        Ok(())
+    }
+
+    fn insert_resolution(
+        &self,
+        market: &Pubkey,
+        kind: &str,
+        actor: &Pubkey,
+        resolution: Option<Resolution>,
+    ) -> Result<()> {
+        self.db.execute(
+            "INSERT INTO resolutions (signature, market, kind, actor, resolution, slot, block_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.transaction.signature,
+                market.to_string(),
+                kind,
+                actor.to_string(),
+                resolution.map(|resolution| resolution_label(Some(resolution))),
+                int(self.transaction.slot)?,
+                self.transaction.block_time,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn insert_fee_payout(
+        &self,
+        market: &Pubkey,
+        recipient: &str,
+        kind: &str,
+        amount: u64,
+    ) -> Result<()> {
+        self.db.execute(
+            "INSERT INTO fee_payouts (signature, market, recipient, kind, amount, slot, block_time)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                self.transaction.signature,
+                market.to_string(),
+                recipient,
+                kind,
+                int(amount)?,
+                int(self.transaction.slot)?,
+                self.transaction.block_time,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn side_amounts(is_yes: bool, shares: i128) -> (i128, i128) {
+    if is_yes {
+        (shares, 0)
+    } else {
+        (0, shares)
+    }
+}
+
+/// Share balances can't go negative; shares received by transfer leave
+/// through sells and claims without having been indexed in.
+fn apply_delta(balance: u64, delta: i128) -> u64 {
+    (balance as i128 + delta).clamp(0, u64::MAX as i128) as u64
+}
+
+/// SQLite integers are signed 64-bit.
+fn int(value: u64) -> Result<i64> {
+    i64::try_from(value).context("amount exceeds SQLite's integer range")
+}
+
+fn math_error(error: MathError) -> anyhow::Error {
+    anyhow::anyhow!("market math failed: {error:?}")
+}
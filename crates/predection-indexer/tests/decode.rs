@@ -0,0 +1,712 @@
+//! Decodes SDK-built instructions wrapped in `getTransaction`-shaped JSON,
+//! which also pins the decoder's account positions to the program's
+//! Accounts structs, and checks what the store makes of them.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token::ID as TOKEN_PROGRAM_ID;
+use predection::states::{Market, MarketLimits, Status};
+use predection_indexer::{parse_transaction, Event, Ingested, Store, Transaction};
+use predection_sdk::instructions::{self, InitializeParams};
+use predection_sdk::{pda, InitialLiquidity, Resolution};
+use serde_json::{json, Value};
+
+const FEE_BPS: u64 = 100;
+/// Pays for every transaction, so it is always account key 0.
+const FEE_PAYER: Pubkey = Pubkey::new_from_array([9; 32]);
+
+struct Fixture {
+    creator: Pubkey,
+    address: Pubkey,
+    market: Market,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let creator = Pubkey::new_unique();
+        let collateral_mint = Pubkey::new_unique();
+        let address = pda::market(&creator, 0).0;
+        let market = Market {
+            market_id: 0,
+            authority: creator,
+            resolver: creator,
+            resolution_time: 0,
+            option: None,
+            status: Status::Open,
+            yes_mint: pda::yes_mint(&address).0,
+            no_mint: pda::no_mint(&address).0,
+            market_vault: pda::market_vault(&address, &collateral_mint, &TOKEN_PROGRAM_ID),
+            collateral_mint,
+            market_close_timestamp: 0,
+            question: "Will it rain?".to_string(),
+            fee_collector: creator,
+            fee_collector_ata: Pubkey::new_unique(),
+            protocol_fee_collector: Pubkey::new_unique(),
+            protocol_fee_collector_ata: Pubkey::new_unique(),
+            fee: FEE_BPS,
+            bump: 0,
+            max_open_interest: None,
+            max_position_per_wallet: None,
+            max_price_move_bps: None,
+            price_move_window: 0,
+            halt_cooldown: 0,
+            halted_until: 0,
+            breaker_min_liquidity: 0,
+            allowlist_root: None,
+            outcome_token_program: TOKEN_PROGRAM_ID,
+            min_trade_size: 0,
+            trade_count: 0,
+            category: "weather".to_string(),
+            registry_index: 0,
+            resolution: None,
+            resolved_at: 0,
+            dispute_window: 0,
+            disputer: None,
+            dispute_bond: 0,
+            bond_amount: 0,
+        };
+        Self {
+            creator,
+            address,
+            market,
+        }
+    }
+
+    fn initialize(&self, initial_liquidity: Option<InitialLiquidity>) -> Instruction {
+        instructions::initialize(
+            &self.creator,
+            InitializeParams {
+                market_id: 0,
+                resolver: self.market.resolver,
+                collateral_mint: self.market.collateral_mint,
+                token_program: TOKEN_PROGRAM_ID,
+                outcome_token_program: TOKEN_PROGRAM_ID,
+                protocol_fee_collector: self.market.protocol_fee_collector,
+                question: self.market.question.clone(),
+                duration_time: 3_600,
+                fee: FEE_BPS,
+                limits: MarketLimits::default(),
+                allowlist_root: None,
+                metadata_uri: None,
+                category: self.market.category.clone(),
+                initial_liquidity,
+                registry_total_markets: 0,
+                category_total_markets: 0,
+            },
+        )
+    }
+
+    fn buy(&self, trader: &Pubkey, amount: u64, is_yes: bool) -> Instruction {
+        instructions::buy_share(
+            &self.address,
+            &self.market,
+            trader,
+            &TOKEN_PROGRAM_ID,
+            amount,
+            is_yes,
+            Vec::new(),
+            false,
+        )
+    }
+
+    fn sell(&self, trader: &Pubkey, amount: u64, is_yes: bool) -> Instruction {
+        instructions::sell_share(
+            &self.address,
+            &self.market,
+            trader,
+            &TOKEN_PROGRAM_ID,
+            amount,
+            is_yes,
+            false,
+        )
+    }
+
+    fn resolve(&self, resolution: Resolution) -> Instruction {
+        instructions::resolve_market(&self.address, &self.market, resolution)
+    }
+
+    fn claim(&self, trader: &Pubkey) -> Instruction {
+        instructions::claim_winning(
+            &self.address,
+            &self.market,
+            trader,
+            &TOKEN_PROGRAM_ID,
+            false,
+        )
+    }
+
+    fn outcome_ata(&self, owner: &Pubkey, is_yes: bool) -> Pubkey {
+        let mint = if is_yes {
+            self.market.yes_mint
+        } else {
+            self.market.no_mint
+        };
+        anchor_spl::associated_token::get_associated_token_address(owner, &mint)
+    }
+}
+
+/// A `getTransaction` result in `json` encoding. `balances` lists token
+/// accounts with their pre and post amounts.
+fn transaction_json(
+    signature: &str,
+    slot: u64,
+    instructions: &[Instruction],
+    balances: &[(Pubkey, u64, u64)],
+) -> Value {
+    let mut keys = vec![FEE_PAYER];
+    let mut index = |key: &Pubkey| match keys.iter().position(|existing| existing == key) {
+        Some(position) => position,
+        None => {
+            keys.push(*key);
+            keys.len() - 1
+        }
+    };
+    let compiled: Vec<Value> = instructions
+        .iter()
+        .map(|instruction| {
+            let accounts: Vec<usize> = instruction
+                .accounts
+                .iter()
+                .map(|meta| index(&meta.pubkey))
+                .collect();
+            json!({
+                "programIdIndex": index(&instruction.program_id),
+                "accounts": accounts,
+                "data": bs58::encode(&instruction.data).into_string(),
+            })
+        })
+        .collect();
+    let [pre, post] = [false, true].map(|post| {
+        balances
+            .iter()
+            .map(|(account, pre_amount, post_amount)| {
+                let amount = if post { post_amount } else { pre_amount };
+                json!({
+                    "accountIndex": index(account),
+                    "uiTokenAmount": { "amount": amount.to_string() },
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+
+    json!({
+        "slot": slot,
+        "blockTime": 1_700_000_000 + slot as i64,
+        "meta": {
+            "err": null,
+            "innerInstructions": [],
+            "preTokenBalances": pre,
+            "postTokenBalances": post,
+        },
+        "transaction": {
+            "signatures": [signature],
+            "message": {
+                "accountKeys": keys.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                "instructions": compiled,
+            },
+        },
+    })
+}
+
+fn decode(
+    signature: &str,
+    slot: u64,
+    instructions: &[Instruction],
+    balances: &[(Pubkey, u64, u64)],
+) -> Transaction {
+    parse_transaction(&transaction_json(signature, slot, instructions, balances)).unwrap()
+}
+
+fn query_i64(store: &Store, sql: &str) -> i64 {
+    store
+        .connection()
+        .query_row(sql, [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn decodes_instruction_accounts_and_amounts() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+    let vault = fixture.market.market_vault;
+
+    let created = decode(
+        "create",
+        1,
+        &[fixture.initialize(Some(InitialLiquidity {
+            amount: 2_000_000,
+            yes_bps: 7_000,
+        }))],
+        &[(vault, 0, 2_000_000)],
+    );
+    assert_eq!(
+        created.events,
+        vec![Event::MarketCreated {
+            market: fixture.address,
+            creator: fixture.creator,
+            resolver: fixture.creator,
+            collateral_mint: fixture.market.collateral_mint,
+            protocol_fee_collector: fixture.market.protocol_fee_collector,
+            question: "Will it rain?".to_string(),
+            category: "weather".to_string(),
+            fee_bps: FEE_BPS,
+            close_timestamp: Some(1_700_000_001 + 3_600),
+            initial_liquidity: 2_000_000,
+            initial_yes_bps: 7_000,
+        }]
+    );
+
+    let bought = decode(
+        "buy",
+        2,
+        &[fixture.buy(&trader, 10_000_000, true)],
+        &[
+            (vault, 2_000_000, 11_900_000),
+            (fixture.market.fee_collector_ata, 0, 50_000),
+            (fixture.market.protocol_fee_collector_ata, 7, 50_007),
+        ],
+    );
+    assert_eq!(
+        bought.events,
+        vec![Event::Buy {
+            market: fixture.address,
+            trader,
+            is_yes: true,
+            amount: 10_000_000,
+            shares: 9_900_000,
+            protocol_fee: Some(50_000),
+            creator_fee: Some(50_000),
+        }]
+    );
+
+    let sold = decode(
+        "sell",
+        3,
+        &[fixture.sell(&trader, 400_000, false)],
+        &[(vault, 11_900_000, 11_500_000)],
+    );
+    assert_eq!(
+        sold.events,
+        vec![Event::Sell {
+            market: fixture.address,
+            trader,
+            is_yes: false,
+            shares: 400_000,
+        }]
+    );
+
+    let resolved = decode("resolve", 4, &[fixture.resolve(Resolution::Yes)], &[]);
+    assert_eq!(
+        resolved.events,
+        vec![Event::Resolved {
+            market: fixture.address,
+            resolver: fixture.creator,
+            resolution: Resolution::Yes,
+        }]
+    );
+
+    let claimed = decode(
+        "claim",
+        5,
+        &[fixture.claim(&trader)],
+        &[
+            (vault, 11_900_000, 1_000),
+            (fixture.outcome_ata(&trader, true), 9_900_000, 0),
+            (fixture.outcome_ata(&trader, false), 0, 0),
+        ],
+    );
+    assert_eq!(
+        claimed.events,
+        vec![Event::Claim {
+            market: fixture.address,
+            trader,
+            yes_burned: 9_900_000,
+            no_burned: 0,
+            payout: 11_899_000,
+        }]
+    );
+}
+
+#[test]
+fn decodes_expiry_as_an_invalid_resolution() {
+    let fixture = Fixture::new();
+    let mut store = Store::open_in_memory().unwrap();
+
+    let expired = decode(
+        "expire",
+        2,
+        &[instructions::expire_market(&fixture.address)],
+        &[],
+    );
+    assert_eq!(
+        expired.events,
+        vec![Event::Expired {
+            market: fixture.address,
+            caller: FEE_PAYER,
+        }]
+    );
+
+    store
+        .ingest(&decode("create", 1, &[fixture.initialize(None)], &[]))
+        .unwrap();
+    store.ingest(&expired).unwrap();
+    let status: (String, String) = store
+        .connection()
+        .query_row("SELECT status, resolution FROM markets", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(status, ("resolved".to_string(), "invalid".to_string()));
+    assert_eq!(
+        query_i64(
+            &store,
+            "SELECT COUNT(*) FROM resolutions WHERE kind = 'expire'"
+        ),
+        1
+    );
+}
+
+#[test]
+fn decodes_dispute_and_ruling() {
+    let fixture = Fixture::new();
+    let disputer = Pubkey::new_unique();
+    let admin = Pubkey::new_unique();
+    let program = |accounts: Vec<_>, data: Vec<u8>| Instruction {
+        program_id: predection::ID,
+        accounts,
+        data,
+    };
+
+    let dispute = program(
+        predection::accounts::DisputeMarket {
+            disputer,
+            market: fixture.address,
+            collateral_mint: fixture.market.collateral_mint,
+            disputer_collateral_ata: Pubkey::new_unique(),
+            bond_vault: pda::bond_vault(&fixture.address).0,
+            token_program: TOKEN_PROGRAM_ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+        }
+        .to_account_metas(None),
+        predection::instruction::DisputeMarket {}.data(),
+    );
+    let rule = program(
+        predection::accounts::RuleDispute {
+            admin,
+            config: pda::config().0,
+            market: fixture.address,
+            creator_profile: pda::creator_profile(&fixture.creator).0,
+            collateral_mint: fixture.market.collateral_mint,
+            bond_vault: pda::bond_vault(&fixture.address).0,
+            disputer_collateral_ata: Pubkey::new_unique(),
+            protocol_fee_collector_ata: fixture.market.protocol_fee_collector_ata,
+            token_program: TOKEN_PROGRAM_ID,
+        }
+        .to_account_metas(None),
+        predection::instruction::RuleDispute {
+            ruling: Resolution::Invalid,
+        }
+        .data(),
+    );
+
+    let transaction = decode("dispute", 1, &[dispute, rule], &[]);
+    assert_eq!(
+        transaction.events,
+        vec![
+            Event::Disputed {
+                market: fixture.address,
+                disputer,
+            },
+            Event::DisputeRuled {
+                market: fixture.address,
+                admin,
+                ruling: Resolution::Invalid,
+            },
+        ]
+    );
+}
+
+#[test]
+fn decodes_buys_sent_with_the_old_market_id_argument() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+    let vault = fixture.market.market_vault;
+
+    let mut legacy = fixture.buy(&trader, 1_000, false);
+    // amount, then the market id that used to sit before is_yes
+    legacy.data.splice(16..16, 7u64.to_le_bytes());
+    let bought = decode("legacy", 1, &[legacy], &[(vault, 0, 990)]);
+    assert_eq!(
+        bought.events,
+        vec![Event::Buy {
+            market: fixture.address,
+            trader,
+            is_yes: false,
+            amount: 1_000,
+            shares: 990,
+            protocol_fee: Some(0),
+            creator_fee: Some(0),
+        }]
+    );
+}
+
+#[test]
+fn decodes_markets_created_before_the_protocol_config_account() {
+    let fixture = Fixture::new();
+    let vault = fixture.market.market_vault;
+
+    let mut legacy = fixture.initialize(Some(InitialLiquidity {
+        amount: 500,
+        yes_bps: 5_000,
+    }));
+    let config = legacy.accounts.remove(5);
+    assert_eq!(config.pubkey, pda::config().0);
+    let created = decode("legacy", 1, &[legacy], &[(vault, 0, 500)]);
+    let Event::MarketCreated {
+        protocol_fee_collector,
+        initial_liquidity,
+        ..
+    } = &created.events[0]
+    else {
+        panic!("expected a market, got {:?}", created.events);
+    };
+    assert_eq!(
+        *protocol_fee_collector,
+        fixture.market.protocol_fee_collector
+    );
+    assert_eq!(*initial_liquidity, 500);
+}
+
+#[test]
+fn failed_transactions_and_other_programs_decode_empty() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+
+    let mut failed = transaction_json("failed", 1, &[fixture.buy(&trader, 1_000, true)], &[]);
+    failed["meta"]["err"] = json!({ "InstructionError": [0, "Custom"] });
+    assert!(parse_transaction(&failed).unwrap().events.is_empty());
+
+    let mut other = fixture.buy(&trader, 1_000, true);
+    other.program_id = Pubkey::new_unique();
+    assert!(decode("other", 2, &[other], &[]).events.is_empty());
+}
+
+#[test]
+fn store_tracks_supplies_positions_and_fees() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+    let vault = fixture.market.market_vault;
+    let mut store = Store::open_in_memory().unwrap();
+
+    let history = [
+        decode(
+            "create",
+            1,
+            &[fixture.initialize(Some(InitialLiquidity {
+                amount: 2_000_000,
+                yes_bps: 7_000,
+            }))],
+            &[(vault, 0, 2_000_000)],
+        ),
+        decode(
+            "buy",
+            2,
+            &[fixture.buy(&trader, 10_000_000, false)],
+            &[
+                (vault, 2_000_000, 11_900_000),
+                (fixture.market.fee_collector_ata, 0, 50_000),
+                (fixture.market.protocol_fee_collector_ata, 0, 50_000),
+            ],
+        ),
+        decode(
+            "sell",
+            3,
+            &[fixture.sell(&trader, 900_000, false)],
+            &[(vault, 11_900_000, 11_000_000)],
+        ),
+        decode("resolve", 4, &[fixture.resolve(Resolution::No)], &[]),
+        decode(
+            "claim",
+            5,
+            &[fixture.claim(&trader)],
+            &[
+                (vault, 11_000_000, 1_900_000),
+                (fixture.outcome_ata(&trader, false), 9_000_000, 0),
+            ],
+        ),
+    ];
+    for transaction in &history {
+        assert_eq!(
+            store.ingest(transaction).unwrap(),
+            Some(Ingested::default())
+        );
+    }
+    // Re-ingesting is a no-op
+    assert_eq!(store.ingest(&history[1]).unwrap(), None);
+
+    // The seed splits 1_400_000 YES / 600_000 NO to the creator
+    let supplies: (i64, i64) = store
+        .connection()
+        .query_row("SELECT yes_supply, no_supply FROM markets", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(supplies, (1_400_000, 600_000));
+    assert_eq!(
+        query_i64(&store, "SELECT vault_balance FROM markets"),
+        1_900_000
+    );
+    assert_eq!(query_i64(&store, "SELECT trade_count FROM markets"), 2);
+    assert_eq!(query_i64(&store, "SELECT volume FROM markets"), 10_900_000);
+    let status: (String, String) = store
+        .connection()
+        .query_row("SELECT status, resolution FROM markets", [], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap();
+    assert_eq!(status, ("resolved".to_string(), "no".to_string()));
+
+    let position = format!(
+        "SELECT yes_shares, no_shares, collateral_deposited, collateral_withdrawn
+         FROM positions WHERE owner = '{trader}'"
+    );
+    let position: (i64, i64, i64, i64) = store
+        .connection()
+        .query_row(&position, [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })
+        .unwrap();
+    assert_eq!(position, (0, 0, 9_900_000, 900_000 + 9_100_000));
+
+    // 1% of 10 USDC, split evenly
+    assert_eq!(
+        query_i64(&store, "SELECT SUM(amount) FROM fee_payouts"),
+        100_000
+    );
+    assert_eq!(
+        query_i64(
+            &store,
+            "SELECT COUNT(*) FROM fee_payouts WHERE kind = 'creator'"
+        ),
+        1
+    );
+    assert_eq!(query_i64(&store, "SELECT COUNT(*) FROM trades"), 3);
+    assert_eq!(query_i64(&store, "SELECT COUNT(*) FROM resolutions"), 1);
+}
+
+#[test]
+fn halted_trades_decode_empty() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+
+    // The circuit breaker stopped them: nothing reached or left the vault
+    let bought = decode("buy", 1, &[fixture.buy(&trader, 1_000, true)], &[]);
+    assert!(bought.events.is_empty());
+    let sold = decode("sell", 2, &[fixture.sell(&trader, 1_000, true)], &[]);
+    assert!(sold.events.is_empty());
+}
+
+#[test]
+fn store_quotes_fees_paid_to_the_traders_own_account() {
+    let mut fixture = Fixture::new();
+    let creator = fixture.creator;
+    let vault = fixture.market.market_vault;
+    let creator_ata = anchor_spl::associated_token::get_associated_token_address(
+        &creator,
+        &fixture.market.collateral_mint,
+    );
+    fixture.market.fee_collector_ata = creator_ata;
+    let mut store = Store::open_in_memory().unwrap();
+
+    // The creator buys in their own market: their account pays the buy and
+    // takes the creator fee, so only the protocol fee shows as a change
+    let history = [
+        decode("create", 1, &[fixture.initialize(None)], &[]),
+        decode(
+            "buy",
+            2,
+            &[fixture.buy(&creator, 10_000_000, true)],
+            &[
+                (vault, 0, 9_900_000),
+                (creator_ata, 10_000_000, 50_000),
+                (fixture.market.protocol_fee_collector_ata, 0, 50_000),
+            ],
+        ),
+    ];
+    let Event::Buy { creator_fee, .. } = &history[1].events[0] else {
+        panic!("expected a buy, got {:?}", history[1].events);
+    };
+    assert_eq!(*creator_fee, None);
+    for transaction in &history {
+        store.ingest(transaction).unwrap();
+    }
+
+    assert_eq!(
+        query_i64(
+            &store,
+            "SELECT amount FROM fee_payouts WHERE kind = 'creator'"
+        ),
+        50_000
+    );
+    assert_eq!(query_i64(&store, "SELECT fee FROM trades"), 100_000);
+}
+
+#[test]
+fn store_skips_trades_on_unindexed_markets() {
+    let fixture = Fixture::new();
+    let mut store = Store::open_in_memory().unwrap();
+    let buy = decode(
+        "buy",
+        1,
+        &[fixture.buy(&Pubkey::new_unique(), 1_000, true)],
+        &[(fixture.market.market_vault, 0, 990)],
+    );
+    let ingested = store.ingest(&buy).unwrap().unwrap();
+    assert_eq!(ingested.skipped_markets, vec![fixture.address]);
+    assert_eq!(query_i64(&store, "SELECT COUNT(*) FROM trades"), 0);
+    assert_eq!(query_i64(&store, "SELECT COUNT(*) FROM positions"), 0);
+}
+
+#[test]
+fn rejects_transactions_moving_a_vault_twice() {
+    let fixture = Fixture::new();
+    let trader = Pubkey::new_unique();
+    let vault = fixture.market.market_vault;
+
+    // The vault's balance change can't be split between the two buys
+    let twice = transaction_json(
+        "twice",
+        1,
+        &[
+            fixture.buy(&trader, 1_000, true),
+            fixture.buy(&trader, 2_000, false),
+        ],
+        &[(vault, 0, 2_970)],
+    );
+    let error = parse_transaction(&twice).unwrap_err().to_string();
+    assert!(error.contains("more than once"), "{error}");
+
+    let buy_then_sell = transaction_json(
+        "buy-then-sell",
+        2,
+        &[
+            fixture.buy(&trader, 1_000, true),
+            fixture.sell(&trader, 500, true),
+        ],
+        &[(vault, 0, 490)],
+    );
+    assert!(parse_transaction(&buy_then_sell).is_err());
+
+    // Resolving alongside a claim moves the vault once
+    let resolve_and_claim = decode(
+        "resolve-and-claim",
+        3,
+        &[fixture.resolve(Resolution::Yes), fixture.claim(&trader)],
+        &[
+            (vault, 990, 0),
+            (fixture.outcome_ata(&trader, true), 990, 0),
+        ],
+    );
+    assert_eq!(resolve_and_claim.events.len(), 2);
+}
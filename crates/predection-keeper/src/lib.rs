@@ -0,0 +1,10 @@
+//! Keeper for predection markets: finds the lifecycle cranks that are due
+//! and submits them.
+
+pub mod metrics;
+pub mod plan;
+pub mod submit;
+
+pub use metrics::Metrics;
+pub use plan::{due_cranks, Crank, MarketView};
+pub use submit::RetryPolicy;
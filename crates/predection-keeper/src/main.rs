@@ -0,0 +1,311 @@
+//! `predection-keeper`: polls the program's markets and submits the
+//! cranks that are due.
+//!
+//! Resolution needs an outcome: the keeper resolves markets whose resolver
+//! is its own key, reading outcomes from a JSON file (`{"<market>": "yes"}`)
+//! that is re-read every round so an oracle process can write to it.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar::clock;
+use anchor_spl::token_interface::Mint;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Parser;
+use predection::states::{Market, Status};
+use predection_keeper::submit::{self, RetryPolicy};
+use predection_keeper::{due_cranks, Crank, MarketView, Metrics};
+use predection_sdk::rpc::{load_keypair, RpcClient};
+use predection_sdk::{instructions, Resolution};
+use serde_json::Value;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+
+#[derive(Parser)]
+#[command(name = "predection-keeper", about = "Submit due predection cranks")]
+struct Cli {
+    /// JSON-RPC endpoint
+    #[arg(
+        long,
+        env = "PREDECTION_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Pays for and signs cranks; also the resolver key and claim owner
+    #[arg(
+        long,
+        env = "PREDECTION_KEYPAIR",
+        default_value = "~/.config/solana/id.json"
+    )]
+    keypair: String,
+
+    /// JSON object mapping market addresses to "yes", "no" or "invalid"
+    #[arg(long)]
+    outcomes: Option<PathBuf>,
+
+    /// Seconds between rounds
+    #[arg(long, default_value_t = 30)]
+    interval: u64,
+
+    /// Run a single round and exit
+    #[arg(long)]
+    once: bool,
+
+    /// Serve Prometheus metrics at http://<address>/metrics
+    #[arg(long)]
+    metrics_listen: Option<String>,
+
+    /// Micro-lamports per compute unit on the first attempt; doubles on retry
+    #[arg(long, default_value_t = 1_000)]
+    priority_fee: u64,
+
+    #[arg(long, default_value_t = 100_000)]
+    max_priority_fee: u64,
+
+    #[arg(long, default_value_t = 4)]
+    max_attempts: u32,
+
+    /// Claims bundled into one transaction
+    #[arg(long, default_value_t = 4)]
+    claims_per_transaction: usize,
+}
+
+struct Keeper {
+    client: RpcClient,
+    keypair: Keypair,
+    policy: RetryPolicy,
+    metrics: Arc<Metrics>,
+    claims_per_transaction: usize,
+    /// Collateral mint -> token program owning it
+    token_programs: HashMap<Pubkey, Pubkey>,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let metrics = Arc::new(Metrics::default());
+    if let Some(address) = &cli.metrics_listen {
+        serve_metrics(address, Arc::clone(&metrics))?;
+    }
+
+    let mut keeper = Keeper {
+        client: RpcClient::new(cli.url),
+        keypair: load_keypair(&cli.keypair)?,
+        policy: RetryPolicy {
+            max_attempts: cli.max_attempts.max(1),
+            priority_fee: cli.priority_fee,
+            max_priority_fee: cli.max_priority_fee,
+            backoff: Duration::from_secs(2),
+        },
+        metrics,
+        claims_per_transaction: cli.claims_per_transaction.max(1),
+        token_programs: HashMap::new(),
+    };
+    println!("keeper {}", keeper.keypair.pubkey());
+
+    loop {
+        let round =
+            load_outcomes(cli.outcomes.as_ref()).and_then(|outcomes| keeper.round(&outcomes));
+        if let Err(error) = round {
+            keeper.metrics.record_round_error();
+            if cli.once {
+                return Err(error);
+            }
+            eprintln!("round failed: {error:#}");
+        }
+        if cli.once {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(cli.interval));
+    }
+}
+
+impl Keeper {
+    fn round(&mut self, outcomes: &HashMap<Pubkey, Resolution>) -> Result<()> {
+        let now = self.cluster_time()?;
+        let keeper = self.keypair.pubkey();
+        let positions = self
+            .client
+            .get_positions_by_owner(&keeper)?
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect::<Vec<_>>();
+
+        let mut views = Vec::new();
+        for (address, market) in self.client.get_markets()? {
+            if matches!(market.status, Status::Settled) {
+                continue;
+            }
+            views.push(self.view(address, market, &keeper, now)?);
+        }
+
+        let cranks = due_cranks(&views, &keeper, outcomes, &positions, now);
+        let markets: HashMap<Pubkey, &Market> = views
+            .iter()
+            .map(|view| (view.address, &view.market))
+            .collect();
+
+        let (claims, others): (Vec<_>, Vec<_>) = cranks
+            .iter()
+            .partition(|crank| matches!(crank, Crank::Claim { .. }));
+        for crank in others {
+            let instruction = self.instruction(crank, markets[crank.market()])?;
+            self.send(crank.kind(), &[crank], &[instruction]);
+        }
+        for batch in claims.chunks(self.claims_per_transaction) {
+            let instructions = batch
+                .iter()
+                .map(|crank| self.instruction(crank, markets[crank.market()]))
+                .collect::<Result<Vec<_>>>()?;
+            self.send("claim", batch, &instructions);
+        }
+
+        self.metrics.record_round(views.len(), now);
+        Ok(())
+    }
+
+    fn view(
+        &self,
+        address: Pubkey,
+        market: Market,
+        keeper: &Pubkey,
+        now: i64,
+    ) -> Result<MarketView> {
+        // Supplies only matter for markets this keeper could resolve now
+        let resolvable = market.resolver == *keeper
+            && matches!(market.status, Status::Open | Status::Halted)
+            && now >= market.market_close_timestamp;
+        let supplies = if resolvable {
+            let yes: Mint = self.client.get_anchor_account(&market.yes_mint)?;
+            let no: Mint = self.client.get_anchor_account(&market.no_mint)?;
+            Some((yes.supply, no.supply))
+        } else {
+            None
+        };
+        Ok(MarketView {
+            address,
+            market,
+            supplies,
+        })
+    }
+
+    fn instruction(&mut self, crank: &Crank, market: &Market) -> Result<Instruction> {
+        let token_program = self.token_program(&market.collateral_mint)?;
+        let keeper = self.keypair.pubkey();
+        Ok(match crank {
+            Crank::Resolve {
+                market: address,
+                resolution,
+            } => instructions::resolve_market(address, market, *resolution),
+            Crank::Expire { market: address } => instructions::expire_market(address),
+            Crank::SettleBond { market: address } => {
+                instructions::settle_bond(address, market, &token_program)
+            }
+            Crank::Claim { market: address } => {
+                instructions::claim_winning(address, market, &keeper, &token_program, false)
+            }
+        })
+    }
+
+    fn send(&self, kind: &'static str, cranks: &[&Crank], instructions: &[Instruction]) {
+        let markets = cranks
+            .iter()
+            .map(|crank| crank.market().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        match submit::submit(&self.client, &self.keypair, instructions, &self.policy) {
+            Ok(submitted) => {
+                println!("{kind} {markets}: {}", submitted.signature);
+                for _ in cranks {
+                    self.metrics
+                        .record_crank(kind, "succeeded", submitted.attempts);
+                }
+            }
+            Err(failed) => {
+                eprintln!(
+                    "{kind} {markets} failed after {} attempts: {}",
+                    failed.attempts, failed.error
+                );
+                for _ in cranks {
+                    self.metrics.record_crank(kind, "failed", failed.attempts);
+                }
+            }
+        }
+    }
+
+    fn token_program(&mut self, mint: &Pubkey) -> Result<Pubkey> {
+        if let Some(program) = self.token_programs.get(mint) {
+            return Ok(*program);
+        }
+        let account = self
+            .client
+            .get_account(mint)?
+            .with_context(|| format!("mint {mint} not found"))?;
+        self.token_programs.insert(*mint, account.owner);
+        Ok(account.owner)
+    }
+
+    /// The cluster's clock, which the program checks deadlines against.
+    fn cluster_time(&self) -> Result<i64> {
+        // slot, epoch_start_timestamp, epoch, leader_schedule_epoch, unix_timestamp
+        const UNIX_TIMESTAMP_OFFSET: usize = 32;
+
+        let account = self
+            .client
+            .get_account(&clock::ID)?
+            .context("clock sysvar not found")?;
+        let bytes = account
+            .data
+            .get(UNIX_TIMESTAMP_OFFSET..UNIX_TIMESTAMP_OFFSET + 8)
+            .context("clock sysvar is too short")?;
+        Ok(i64::from_le_bytes(bytes.try_into()?))
+    }
+}
+
+fn load_outcomes(path: Option<&PathBuf>) -> Result<HashMap<Pubkey, Resolution>> {
+    let Some(path) = path else {
+        return Ok(HashMap::new());
+    };
+    let contents = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    let value: Value = serde_json::from_slice(&contents)
+        .with_context(|| format!("{} is not JSON", path.display()))?;
+    let entries = value
+        .as_object()
+        .with_context(|| format!("{} is not a JSON object", path.display()))?;
+
+    let mut outcomes = HashMap::new();
+    for (market, outcome) in entries {
+        let market =
+            Pubkey::from_str(market).with_context(|| format!("invalid market {market}"))?;
+        let resolution = match outcome.as_str() {
+            Some("yes") => Resolution::Yes,
+            Some("no") => Resolution::No,
+            Some("invalid") => Resolution::Invalid,
+            _ => bail!("outcome of {market} must be \"yes\", \"no\" or \"invalid\""),
+        };
+        outcomes.insert(market, resolution);
+    }
+    Ok(outcomes)
+}
+
+fn serve_metrics(address: &str, metrics: Arc<Metrics>) -> Result<()> {
+    let server = tiny_http::Server::http(address).map_err(|e| anyhow!("{e}"))?;
+    println!("metrics on http://{address}/metrics");
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if request.url() == "/metrics" {
+                tiny_http::Response::from_string(metrics.render())
+            } else {
+                tiny_http::Response::from_string("not found").with_status_code(404)
+            };
+            let _ = request.respond(response);
+        }
+    });
+    Ok(())
+}
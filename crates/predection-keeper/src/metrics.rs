@@ -0,0 +1,101 @@
+//! Counters and gauges rendered in the Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Counts {
+    /// (crank kind, outcome) -> cranks
+    cranks: BTreeMap<(&'static str, &'static str), u64>,
+    retries: u64,
+    rounds: u64,
+    round_errors: u64,
+    markets: u64,
+    last_round_timestamp: i64,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    counts: Mutex<Counts>,
+}
+
+impl Metrics {
+    /// `outcome` is "succeeded" or "failed"; `attempts` beyond the first
+    /// count as retries.
+    pub fn record_crank(&self, kind: &'static str, outcome: &'static str, attempts: u32) {
+        let mut counts = self.counts.lock().unwrap();
+        *counts.cranks.entry((kind, outcome)).or_default() += 1;
+        counts.retries += u64::from(attempts.saturating_sub(1));
+    }
+
+    pub fn record_round(&self, markets: usize, timestamp: i64) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.rounds += 1;
+        counts.markets = markets as u64;
+        counts.last_round_timestamp = timestamp;
+    }
+
+    pub fn record_round_error(&self) {
+        self.counts.lock().unwrap().round_errors += 1;
+    }
+
+    pub fn render(&self) -> String {
+        let counts = self.counts.lock().unwrap();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "predection_keeper_cranks_total",
+            "counter",
+            "Cranks submitted, by kind and outcome.",
+        );
+        for ((kind, outcome), count) in &counts.cranks {
+            let _ = writeln!(
+                out,
+                "predection_keeper_cranks_total{{kind=\"{kind}\",outcome=\"{outcome}\"}} {count}"
+            );
+        }
+
+        for (name, kind, help, value) in [
+            (
+                "predection_keeper_retries_total",
+                "counter",
+                "Resubmissions after retryable errors.",
+                counts.retries as i64,
+            ),
+            (
+                "predection_keeper_rounds_total",
+                "counter",
+                "Completed polling rounds.",
+                counts.rounds as i64,
+            ),
+            (
+                "predection_keeper_round_errors_total",
+                "counter",
+                "Rounds aborted by an RPC error.",
+                counts.round_errors as i64,
+            ),
+            (
+                "predection_keeper_markets",
+                "gauge",
+                "Markets seen in the last round.",
+                counts.markets as i64,
+            ),
+            (
+                "predection_keeper_last_round_timestamp",
+                "gauge",
+                "Cluster time of the last completed round.",
+                counts.last_round_timestamp,
+            ),
+        ] {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{name} {value}");
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}");
+}
@@ -0,0 +1,136 @@
+//! Decides which cranks are due from a snapshot of on-chain state.
+//!
+//! Kept free of RPC so the rules can be tested directly. The program has
+//! no dust-sweep or account-close instructions yet, so only resolution,
+//! expiry, bond settlement and the keeper's own claims are planned.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use predection::states::{Market, Position, Resolution, Status};
+
+/// Everything the planner needs to know about one market.
+pub struct MarketView {
+    pub address: Pubkey,
+    pub market: Market,
+    /// Mint supplies, fetched only when they can decide a resolution
+    pub supplies: Option<(u64, u64)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Crank {
+    Resolve {
+        market: Pubkey,
+        resolution: Resolution,
+    },
+    Expire {
+        market: Pubkey,
+    },
+    SettleBond {
+        market: Pubkey,
+    },
+    Claim {
+        market: Pubkey,
+    },
+}
+
+impl Crank {
+    /// Metrics label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Crank::Resolve { .. } => "resolve",
+            Crank::Expire { .. } => "expire",
+            Crank::SettleBond { .. } => "settle_bond",
+            Crank::Claim { .. } => "claim",
+        }
+    }
+
+    pub fn market(&self) -> &Pubkey {
+        match self {
+            Crank::Resolve { market, .. }
+            | Crank::Expire { market }
+            | Crank::SettleBond { market }
+            | Crank::Claim { market } => market,
+        }
+    }
+}
+
+/// Cranks due at cluster time `now`.
+///
+/// - Closed markets the keeper resolves get the outcome from `outcomes`;
+///   one-sided ones are voided since YES or NO can't be resolved.
+/// - Markets left unresolved past the grace period are voided, unless the
+///   keeper can resolve them itself.
+/// - Final markets still holding a creator bond get it settled.
+/// - The keeper claims its own `positions` once they are final.
+pub fn due_cranks(
+    markets: &[MarketView],
+    keeper: &Pubkey,
+    outcomes: &HashMap<Pubkey, Resolution>,
+    positions: &[Position],
+    now: i64,
+) -> Vec<Crank> {
+    let mut cranks = Vec::new();
+    for view in markets {
+        let market = &view.market;
+
+        if let Some(resolution) = resolution_due(view, keeper, outcomes, now) {
+            cranks.push(Crank::Resolve {
+                market: view.address,
+                resolution,
+            });
+        } else if market.is_expired(now) {
+            cranks.push(Crank::Expire {
+                market: view.address,
+            });
+        }
+
+        if market.is_final(now) && market.bond_amount > 0 {
+            cranks.push(Crank::SettleBond {
+                market: view.address,
+            });
+        }
+
+        let claimable = positions
+            .iter()
+            .filter(|position| position.market == view.address)
+            .any(|position| winning_shares(market, position) > 0);
+        if claimable && market.is_final(now) {
+            cranks.push(Crank::Claim {
+                market: view.address,
+            });
+        }
+    }
+    cranks
+}
+
+fn resolution_due(
+    view: &MarketView,
+    keeper: &Pubkey,
+    outcomes: &HashMap<Pubkey, Resolution>,
+    now: i64,
+) -> Option<Resolution> {
+    let market = &view.market;
+    if market.resolver != *keeper
+        || !matches!(market.status, Status::Open | Status::Halted)
+        || now < market.market_close_timestamp
+    {
+        return None;
+    }
+    if let Some((yes_supply, no_supply)) = view.supplies {
+        if yes_supply == 0 || no_supply == 0 {
+            return Some(Resolution::Invalid);
+        }
+    }
+    outcomes.get(&view.address).copied()
+}
+
+/// Shares `claim_winning` would burn for this position.
+fn winning_shares(market: &Market, position: &Position) -> u64 {
+    match market.resolution {
+        Some(Resolution::Yes) => position.yes_shares,
+        Some(Resolution::No) => position.no_shares,
+        Some(Resolution::Invalid) => position.total_shares(),
+        None => 0,
+    }
+}
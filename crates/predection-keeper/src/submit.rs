@@ -0,0 +1,94 @@
+//! Sends crank transactions with a priority fee that rises on every retry.
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use anchor_lang::solana_program::instruction::Instruction;
+use predection_sdk::rpc::{ClientError, RpcClient};
+use solana_compute_budget_interface::ComputeBudgetInstruction;
+use solana_keypair::Keypair;
+
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Micro-lamports per compute unit on the first attempt
+    pub priority_fee: u64,
+    /// Cap the doubling fee stops at
+    pub max_priority_fee: u64,
+    /// Wait before the second attempt, doubling after that
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Priority fee for `attempt` (0-based).
+    pub fn fee(&self, attempt: u32) -> u64 {
+        self.priority_fee
+            .saturating_mul(1u64.checked_shl(attempt).unwrap_or(u64::MAX))
+            .min(self.max_priority_fee.max(self.priority_fee))
+    }
+}
+
+/// Whether sending again could help. Program errors fail the same way
+/// every time, so only transport problems and expired blockhashes retry.
+pub fn is_retryable(error: &ClientError) -> bool {
+    match error {
+        ClientError::Http(_) | ClientError::Io(_) | ClientError::Unconfirmed(_) => true,
+        ClientError::Rpc(message) => message.contains("Blockhash not found"),
+        _ => false,
+    }
+}
+
+pub struct Submitted {
+    pub signature: String,
+    pub attempts: u32,
+}
+
+pub struct Failed {
+    pub error: ClientError,
+    pub attempts: u32,
+}
+
+/// Calls `send` with the fee for each attempt until it succeeds, fails
+/// for good, or runs out of attempts.
+pub fn with_retries(
+    policy: &RetryPolicy,
+    mut send: impl FnMut(u64) -> Result<String, ClientError>,
+) -> Result<Submitted, Failed> {
+    let mut attempt = 0;
+    loop {
+        match send(policy.fee(attempt)) {
+            Ok(signature) => {
+                return Ok(Submitted {
+                    signature,
+                    attempts: attempt + 1,
+                })
+            }
+            Err(error) if attempt + 1 < policy.max_attempts && is_retryable(&error) => {
+                sleep(policy.backoff.saturating_mul(1 << attempt.min(16)));
+                attempt += 1;
+            }
+            Err(error) => {
+                return Err(Failed {
+                    error,
+                    attempts: attempt + 1,
+                })
+            }
+        }
+    }
+}
+
+/// Sends `instructions` signed by `keeper` under `policy`.
+pub fn submit(
+    client: &RpcClient,
+    keeper: &Keypair,
+    instructions: &[Instruction],
+    policy: &RetryPolicy,
+) -> Result<Submitted, Failed> {
+    with_retries(policy, |fee| {
+        let mut transaction = Vec::with_capacity(instructions.len() + 1);
+        if fee > 0 {
+            transaction.push(ComputeBudgetInstruction::set_compute_unit_price(fee));
+        }
+        transaction.extend_from_slice(instructions);
+        client.send_and_confirm(&transaction, keeper, &[])
+    })
+}
@@ -0,0 +1,259 @@
+//! Planner rules, retry policy and metrics output, none of which need a
+//! cluster.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use predection::states::{Market, Position, Status, RESOLUTION_GRACE_PERIOD};
+use predection_keeper::submit::{is_retryable, with_retries};
+use predection_keeper::{due_cranks, Crank, MarketView, Metrics, RetryPolicy};
+use predection_sdk::rpc::ClientError;
+use predection_sdk::Resolution;
+
+const CLOSE: i64 = 1_000;
+
+fn market(resolver: Pubkey) -> Market {
+    Market {
+        market_id: 0,
+        authority: Pubkey::new_unique(),
+        resolver,
+        resolution_time: 0,
+        option: None,
+        status: Status::Open,
+        yes_mint: Pubkey::new_unique(),
+        no_mint: Pubkey::new_unique(),
+        market_vault: Pubkey::new_unique(),
+        collateral_mint: Pubkey::new_unique(),
+        market_close_timestamp: CLOSE,
+        question: "Will it rain?".to_string(),
+        fee_collector: Pubkey::new_unique(),
+        fee_collector_ata: Pubkey::new_unique(),
+        protocol_fee_collector: Pubkey::new_unique(),
+        protocol_fee_collector_ata: Pubkey::new_unique(),
+        fee: 100,
+        bump: 0,
+        max_open_interest: None,
+        max_position_per_wallet: None,
+        max_price_move_bps: None,
+        price_move_window: 0,
+        halt_cooldown: 0,
+        halted_until: 0,
+        breaker_min_liquidity: 0,
+        allowlist_root: None,
+        outcome_token_program: Pubkey::new_unique(),
+        min_trade_size: 0,
+        trade_count: 0,
+        category: "weather".to_string(),
+        registry_index: 0,
+        resolution: None,
+        resolved_at: 0,
+        dispute_window: 0,
+        disputer: None,
+        dispute_bond: 0,
+        bond_amount: 0,
+    }
+}
+
+fn resolved(mut market: Market, resolution: Resolution, dispute_window: i64) -> Market {
+    market.status = Status::Resolved;
+    market.resolution = Some(resolution);
+    market.resolved_at = CLOSE;
+    market.dispute_window = dispute_window;
+    market
+}
+
+fn view(market: Market) -> MarketView {
+    MarketView {
+        address: Pubkey::new_unique(),
+        market,
+        supplies: Some((10, 10)),
+    }
+}
+
+fn position(market: &MarketView, owner: Pubkey, yes_shares: u64, no_shares: u64) -> Position {
+    Position {
+        market: market.address,
+        owner,
+        yes_shares,
+        no_shares,
+        collateral_deposited: 0,
+        collateral_withdrawn: 0,
+        bump: 0,
+    }
+}
+
+#[test]
+fn resolves_closed_markets_the_keeper_owns() {
+    let keeper = Pubkey::new_unique();
+    let mine = view(market(keeper));
+    let mut one_sided = view(market(keeper));
+    one_sided.supplies = Some((10, 0));
+    let unknown = view(market(keeper));
+    let theirs = view(market(Pubkey::new_unique()));
+    let outcomes = HashMap::from([
+        (mine.address, Resolution::No),
+        (one_sided.address, Resolution::Yes),
+        (theirs.address, Resolution::Yes),
+    ]);
+    let markets = [mine, one_sided, unknown, theirs];
+
+    // Nothing before the close
+    assert_eq!(
+        due_cranks(&markets, &keeper, &outcomes, &[], CLOSE - 1),
+        vec![]
+    );
+    assert_eq!(
+        due_cranks(&markets, &keeper, &outcomes, &[], CLOSE),
+        vec![
+            Crank::Resolve {
+                market: markets[0].address,
+                resolution: Resolution::No,
+            },
+            // YES can't resolve a one-sided market, so it is voided
+            Crank::Resolve {
+                market: markets[1].address,
+                resolution: Resolution::Invalid,
+            },
+        ]
+    );
+}
+
+#[test]
+fn expires_markets_nobody_resolved() {
+    let keeper = Pubkey::new_unique();
+    let mine = view(market(keeper));
+    let theirs = view(market(Pubkey::new_unique()));
+    let outcomes = HashMap::from([(mine.address, Resolution::Yes)]);
+    let markets = [mine, theirs];
+    let expiry = CLOSE + RESOLUTION_GRACE_PERIOD;
+
+    assert_eq!(
+        due_cranks(&markets, &keeper, &outcomes, &[], expiry - 1),
+        vec![Crank::Resolve {
+            market: markets[0].address,
+            resolution: Resolution::Yes,
+        }]
+    );
+    // A market the keeper can still resolve is resolved instead
+    assert_eq!(
+        due_cranks(&markets, &keeper, &outcomes, &[], expiry),
+        vec![
+            Crank::Resolve {
+                market: markets[0].address,
+                resolution: Resolution::Yes,
+            },
+            Crank::Expire {
+                market: markets[1].address
+            },
+        ]
+    );
+}
+
+#[test]
+fn settles_bonds_and_claims_once_final() {
+    let keeper = Pubkey::new_unique();
+    let mut bonded = resolved(market(Pubkey::new_unique()), Resolution::Yes, 100);
+    bonded.bond_amount = 50;
+    let settled = resolved(market(Pubkey::new_unique()), Resolution::Yes, 100);
+    let voided = resolved(market(Pubkey::new_unique()), Resolution::Invalid, 100);
+    let markets = [view(bonded), view(settled), view(voided)];
+    let positions = [
+        position(&markets[0], keeper, 5, 0),
+        // Only NO shares in a YES market: nothing to claim
+        position(&markets[1], keeper, 0, 5),
+        position(&markets[2], keeper, 0, 5),
+    ];
+
+    // Inside the dispute window
+    assert_eq!(
+        due_cranks(&markets, &keeper, &HashMap::new(), &positions, CLOSE + 99),
+        vec![]
+    );
+    assert_eq!(
+        due_cranks(&markets, &keeper, &HashMap::new(), &positions, CLOSE + 100),
+        vec![
+            Crank::SettleBond {
+                market: markets[0].address
+            },
+            Crank::Claim {
+                market: markets[0].address
+            },
+            Crank::Claim {
+                market: markets[2].address
+            },
+        ]
+    );
+}
+
+#[test]
+fn retries_transient_errors_with_rising_fees() {
+    let policy = RetryPolicy {
+        max_attempts: 4,
+        priority_fee: 1_000,
+        max_priority_fee: 5_000,
+        backoff: Duration::ZERO,
+    };
+    assert_eq!(
+        (0..4)
+            .map(|attempt| policy.fee(attempt))
+            .collect::<Vec<_>>(),
+        [1_000, 2_000, 4_000, 5_000]
+    );
+
+    let mut fees = Vec::new();
+    let submitted = with_retries(&policy, |fee| {
+        fees.push(fee);
+        match fees.len() {
+            1 => Err(ClientError::Unconfirmed("sig".to_string())),
+            2 => Err(ClientError::Rpc("Blockhash not found".to_string())),
+            _ => Ok("sig".to_string()),
+        }
+    })
+    .unwrap_or_else(|failed| panic!("{}", failed.error));
+    assert_eq!(submitted.attempts, 3);
+    assert_eq!(fees, [1_000, 2_000, 4_000]);
+
+    // Program errors fail the same way every time
+    let failed = with_retries(&policy, |_| {
+        Err(ClientError::TransactionFailed {
+            signature: "sig".to_string(),
+            reason: "custom program error".to_string(),
+        })
+    })
+    .err()
+    .unwrap();
+    assert_eq!(failed.attempts, 1);
+    assert!(!is_retryable(&ClientError::Rpc(
+        "Transaction simulation failed".to_string()
+    )));
+
+    let exhausted = with_retries(&policy, |_| {
+        Err(ClientError::Unconfirmed("sig".to_string()))
+    })
+    .err()
+    .unwrap();
+    assert_eq!(exhausted.attempts, 4);
+}
+
+#[test]
+fn renders_prometheus_metrics() {
+    let metrics = Metrics::default();
+    metrics.record_crank("settle_bond", "succeeded", 1);
+    metrics.record_crank("settle_bond", "succeeded", 3);
+    metrics.record_crank("claim", "failed", 2);
+    metrics.record_round(7, 1_700_000_000);
+
+    let text = metrics.render();
+    for line in [
+        "predection_keeper_cranks_total{kind=\"settle_bond\",outcome=\"succeeded\"} 2",
+        "predection_keeper_cranks_total{kind=\"claim\",outcome=\"failed\"} 1",
+        "predection_keeper_retries_total 3",
+        "predection_keeper_rounds_total 1",
+        "predection_keeper_markets 7",
+        "predection_keeper_last_round_timestamp 1700000000",
+    ] {
+        assert!(text.lines().any(|l| l == line), "missing {line} in\n{text}");
+    }
+    assert_eq!(text.matches("# TYPE").count(), 6);
+}
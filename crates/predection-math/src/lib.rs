@@ -0,0 +1,394 @@
+//! Fee, price and payout math for predection markets.
+//!
+//! The program calls these functions for every quote it settles, and the
+//! SDK re-exports them, so off-chain quotes match on-chain results exactly.
+//! Everything is integer math widened to u128, rounding down in the vault's
+//! favour.
+
+#![no_std]
+
+/// Denominator of every basis-point value (100 bps = 1%).
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Fixed-point scale of prices: 1_000_000 = 1.00.
+pub const PRICE_SCALE: u64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    /// The result doesn't fit in a u64.
+    Overflow,
+    /// A pro-rata share was taken of an empty supply.
+    ZeroSupply,
+    /// A fee or ratio above 100%.
+    InvalidBps,
+}
+
+pub type Result<T> = core::result::Result<T, MathError>;
+
+/// `amount * numerator / denominator`, rounded down.
+pub fn mul_div(amount: u64, numerator: u64, denominator: u64) -> Result<u64> {
+    if denominator == 0 {
+        return Err(MathError::ZeroSupply);
+    }
+    let result = amount as u128 * numerator as u128 / denominator as u128;
+    u64::try_from(result).map_err(|_| MathError::Overflow)
+}
+
+/// Fee charged on `amount` at `fee_bps`, rounded down.
+pub fn fee(amount: u64, fee_bps: u64) -> Result<u64> {
+    if fee_bps > BPS_DENOMINATOR {
+        return Err(MathError::InvalidBps);
+    }
+    mul_div(amount, fee_bps, BPS_DENOMINATOR)
+}
+
+/// A fee divided between the protocol treasury and the market creator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeSplit {
+    pub protocol: u64,
+    pub creator: u64, // Gets the odd unit
+}
+
+pub fn split_fee(fee: u64) -> FeeSplit {
+    let protocol = fee / 2;
+    FeeSplit {
+        protocol,
+        creator: fee - protocol,
+    }
+}
+
+/// What a buy of `amount` collateral pays where.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuyQuote {
+    pub amount: u64,
+    pub fee: FeeSplit,
+    /// Collateral sent to the vault. Shares are minted 1:1 for what arrives,
+    /// which is less if the collateral charges a transfer fee.
+    pub net_amount: u64,
+}
+
+pub fn quote_buy(amount: u64, fee_bps: u64) -> Result<BuyQuote> {
+    let fee = fee(amount, fee_bps)?;
+    Ok(BuyQuote {
+        amount,
+        fee: split_fee(fee),
+        net_amount: amount - fee,
+    })
+}
+
+/// Collateral paid for `shares` of a `supply` that splits `pool` pro rata.
+/// Used for claims: winners split the vault by their winning shares, or
+/// everyone splits it by all shares when a market resolves INVALID.
+pub fn pro_rata(shares: u64, pool: u64, supply: u64) -> Result<u64> {
+    mul_div(shares, pool, supply)
+}
+
+/// Implied YES probability, scaled by PRICE_SCALE. An empty market is 50/50.
+pub fn yes_price(yes_supply: u64, no_supply: u64) -> u64 {
+    let total = yes_supply as u128 + no_supply as u128;
+    if total == 0 {
+        return PRICE_SCALE / 2;
+    }
+    (yes_supply as u128 * PRICE_SCALE as u128 / total) as u64
+}
+
+/// A price difference expressed in bps of PRICE_SCALE.
+pub fn price_move_bps(from: u64, to: u64) -> u64 {
+    (from.abs_diff(to) as u128 * BPS_DENOMINATOR as u128 / PRICE_SCALE as u128) as u64
+}
+
+/// Splits `amount` into YES and NO parts so the market opens at
+/// `yes_bps` / 10_000. YES rounds down.
+pub fn split_by_odds(amount: u64, yes_bps: u64) -> Result<(u64, u64)> {
+    if yes_bps > BPS_DENOMINATOR {
+        return Err(MathError::InvalidBps);
+    }
+    let yes = mul_div(amount, yes_bps, BPS_DENOMINATOR)?;
+    Ok((yes, amount - yes))
+}
+
+/// Samples a market's price history holds, one per second traded in.
+pub const PRICE_HISTORY_CAPACITY: usize = 128;
+
+/// Where a price history ring puts its next sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RingSlot {
+    /// Index of the sample to write, which becomes the newest
+    pub head: u64,
+    pub count: u64,
+    /// The newest sample is from the same second and gets overwritten
+    pub same_second: bool,
+}
+
+/// The slot for a sample taken at `now` in a ring of
+/// PRICE_HISTORY_CAPACITY samples holding `count`, the newest at `head`
+/// taken at `newest`. Once full, the oldest sample is overwritten.
+pub fn next_slot(head: u64, count: u64, newest: Option<i64>, now: i64) -> RingSlot {
+    if count > 0 && newest == Some(now) {
+        return RingSlot {
+            head,
+            count,
+            same_second: true,
+        };
+    }
+    let capacity = PRICE_HISTORY_CAPACITY as u64;
+    RingSlot {
+        head: if count > 0 {
+            (head + 1) % capacity
+        } else {
+            head
+        },
+        count: (count + 1).min(capacity),
+        same_second: false,
+    }
+}
+
+/// Index of the `age`th newest sample of a ring whose newest is at `head`.
+pub fn ring_index(head: u64, age: u64) -> usize {
+    let capacity = PRICE_HISTORY_CAPACITY as u64;
+    ((head + capacity - age % capacity) % capacity) as usize
+}
+
+/// Largest move from `new_price` to any price seen during the last `window`
+/// seconds, in bps of PRICE_SCALE. `samples` are (timestamp, price) pairs,
+/// newest first; the one in force when the window opened counts, as does
+/// `last_price`. Zero until there is history.
+pub fn max_move_bps(
+    last_price: u64,
+    samples: impl IntoIterator<Item = (i64, u64)>,
+    now: i64,
+    window: i64,
+    new_price: u64,
+) -> u64 {
+    let mut samples = samples.into_iter().peekable();
+    if samples.peek().is_none() {
+        return 0;
+    }
+    let window_start = now.saturating_sub(window);
+
+    let mut max_move = price_move_bps(last_price, new_price);
+    for (timestamp, price) in samples {
+        max_move = max_move.max(price_move_bps(price, new_price));
+        if timestamp <= window_start {
+            break;
+        }
+    }
+    max_move
+}
+
+/// A market's circuit breaker settings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    /// Largest YES move allowed within `window`, in bps of PRICE_SCALE
+    pub max_price_move_bps: u64,
+    pub window: i64,
+    /// Seconds trading stays halted once tripped
+    pub cooldown: i64,
+    /// Shares outstanding below which trades aren't checked, since any trade
+    /// swings a thin pool
+    pub min_liquidity: u64,
+}
+
+/// A trade the circuit breaker stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trip {
+    pub price_move_bps: u64,
+    pub halted_until: i64,
+}
+
+impl CircuitBreaker {
+    /// Settings `initialize` accepts: a threshold up to 100%, and a window
+    /// and cooldown that aren't empty.
+    pub fn is_valid(&self) -> bool {
+        self.max_price_move_bps > 0
+            && self.max_price_move_bps <= BPS_DENOMINATOR
+            && self.window > 0
+            && self.cooldown > 0
+    }
+
+    /// Checks a trade that would take the outstanding YES and NO shares from
+    /// `before` to `after`, against a history as `max_move_bps` takes it.
+    /// Returns the halt if the move breaches the threshold.
+    pub fn check(
+        &self,
+        before: (u64, u64),
+        after: (u64, u64),
+        last_price: u64,
+        samples: impl IntoIterator<Item = (i64, u64)>,
+        now: i64,
+    ) -> Option<Trip> {
+        if before.0.saturating_add(before.1) < self.min_liquidity {
+            return None;
+        }
+        let new_price = yes_price(after.0, after.1);
+        let price_move_bps = max_move_bps(last_price, samples, now, self.window, new_price);
+        (price_move_bps > self.max_price_move_bps).then(|| Trip {
+            price_move_bps,
+            halted_until: now.saturating_add(self.cooldown),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One whole USDC in raw units.
+    const USDC: u64 = 1_000_000;
+
+    #[test]
+    fn mul_div_widens_before_dividing() {
+        assert_eq!(mul_div(u64::MAX, u64::MAX, u64::MAX), Ok(u64::MAX));
+        assert_eq!(mul_div(7, 3, 2), Ok(10));
+        assert_eq!(mul_div(u64::MAX, 2, 1), Err(MathError::Overflow));
+        assert_eq!(mul_div(1, 1, 0), Err(MathError::ZeroSupply));
+    }
+
+    #[test]
+    fn fee_rounds_down_and_caps_at_100_percent() {
+        assert_eq!(fee(199, 50), Ok(0));
+        assert_eq!(fee(1_000, 100), Ok(10));
+        assert_eq!(fee(1_000, BPS_DENOMINATOR), Ok(1_000));
+        assert_eq!(fee(1_000, BPS_DENOMINATOR + 1), Err(MathError::InvalidBps));
+    }
+
+    #[test]
+    fn creator_gets_the_odd_fee_unit() {
+        assert_eq!(
+            split_fee(7),
+            FeeSplit {
+                protocol: 3,
+                creator: 4
+            }
+        );
+        assert_eq!(
+            split_fee(8),
+            FeeSplit {
+                protocol: 4,
+                creator: 4
+            }
+        );
+        assert_eq!(
+            split_fee(1),
+            FeeSplit {
+                protocol: 0,
+                creator: 1
+            }
+        );
+    }
+
+    #[test]
+    fn buy_quote_sends_the_rest_to_the_vault() {
+        let quote = quote_buy(1_001, 100).unwrap();
+        assert_eq!(quote.fee.protocol + quote.fee.creator, 10);
+        assert_eq!(quote.net_amount, 991);
+        assert_eq!(quote_buy(1, 1_000).unwrap().net_amount, 1);
+    }
+
+    #[test]
+    fn split_by_odds_covers_its_bounds() {
+        assert_eq!(split_by_odds(1_000, 0), Ok((0, 1_000)));
+        assert_eq!(split_by_odds(1_000, BPS_DENOMINATOR), Ok((1_000, 0)));
+        assert_eq!(split_by_odds(999, 5_000), Ok((499, 500)));
+        assert_eq!(
+            split_by_odds(u64::MAX, 1),
+            Ok((u64::MAX / 10_000, u64::MAX - u64::MAX / 10_000))
+        );
+        assert_eq!(
+            split_by_odds(1_000, BPS_DENOMINATOR + 1),
+            Err(MathError::InvalidBps)
+        );
+    }
+
+    #[test]
+    fn yes_price_follows_the_supplies() {
+        assert_eq!(yes_price(0, 0), PRICE_SCALE / 2);
+        assert_eq!(yes_price(1, 3), PRICE_SCALE / 4);
+        assert_eq!(yes_price(5, 0), PRICE_SCALE);
+        assert_eq!(yes_price(0, 5), 0);
+        assert_eq!(yes_price(u64::MAX, u64::MAX), PRICE_SCALE / 2);
+    }
+
+    #[test]
+    fn price_move_is_symmetric() {
+        assert_eq!(price_move_bps(500_000, 600_000), 1_000);
+        assert_eq!(price_move_bps(600_000, 500_000), 1_000);
+        assert_eq!(price_move_bps(0, PRICE_SCALE), BPS_DENOMINATOR);
+        assert_eq!(price_move_bps(500_000, 500_099), 0);
+    }
+
+    #[test]
+    fn pro_rata_pays_past_the_u64_product_limit() {
+        // 5,000 USDC of shares times a 10,000 USDC vault overflows u64
+        let payout = pro_rata(5_000 * USDC, 10_000 * USDC, 10_000 * USDC).unwrap();
+        assert_eq!(payout, 5_000 * USDC);
+        assert_eq!(pro_rata(1, 10, 3), Ok(3));
+        assert_eq!(pro_rata(1, 1, 0), Err(MathError::ZeroSupply));
+    }
+
+    #[test]
+    fn ring_fills_then_overwrites_the_oldest() {
+        let first = next_slot(0, 0, None, 10);
+        assert_eq!((first.head, first.count, first.same_second), (0, 1, false));
+        assert!(next_slot(0, 1, Some(10), 10).same_second);
+
+        let capacity = PRICE_HISTORY_CAPACITY as u64;
+        let wrapped = next_slot(capacity - 1, capacity, Some(10), 11);
+        assert_eq!((wrapped.head, wrapped.count), (0, capacity));
+        assert_eq!(ring_index(0, 1), PRICE_HISTORY_CAPACITY - 1);
+        assert_eq!(ring_index(5, 0), 5);
+    }
+
+    #[test]
+    fn move_window_includes_the_price_it_opened_at() {
+        let half = PRICE_SCALE / 2;
+        assert_eq!(max_move_bps(half, [], 100, 60, PRICE_SCALE), 0);
+        // Newest first: the sample at 30 is the price in force at 40
+        let samples = [(90, half), (30, PRICE_SCALE / 10), (10, 0)];
+        assert_eq!(max_move_bps(half, samples, 100, 60, half), 4_000);
+        assert_eq!(max_move_bps(half, samples, 100, 100, half), 5_000);
+    }
+
+    #[test]
+    fn breaker_halts_past_the_threshold_above_min_liquidity() {
+        let breaker = CircuitBreaker {
+            max_price_move_bps: 1_000,
+            window: 60,
+            cooldown: 300,
+            min_liquidity: 100,
+        };
+        assert!(breaker.is_valid());
+        let history = [(0, PRICE_SCALE / 2)];
+        let last = PRICE_SCALE / 2;
+
+        assert_eq!(breaker.check((50, 50), (60, 50), last, history, 10), None);
+        assert_eq!(
+            breaker.check((50, 50), (100, 50), last, history, 10),
+            Some(Trip {
+                price_move_bps: 1_666,
+                halted_until: 310,
+            })
+        );
+        assert_eq!(breaker.check((40, 40), (100, 40), last, history, 10), None);
+
+        for invalid in [
+            CircuitBreaker {
+                max_price_move_bps: 0,
+                ..breaker
+            },
+            CircuitBreaker {
+                max_price_move_bps: BPS_DENOMINATOR + 1,
+                ..breaker
+            },
+            CircuitBreaker {
+                window: 0,
+                ..breaker
+            },
+            CircuitBreaker {
+                cooldown: 0,
+                ..breaker
+            },
+        ] {
+            assert!(!invalid.is_valid());
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::{AccountDeserialize, Discriminator, Result, ZeroCopy};
+use predection::states::{CreatorProfile, Market, MarketRegistry, Position, Resolution, Status};
+
+/// Decodes raw account data, checking the Anchor discriminator.
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut &data[..])
+}
+
+/// Decodes a zero-copy account, checking the Anchor discriminator.
+pub fn decode_zero_copy<T: ZeroCopy + Discriminator>(data: &[u8]) -> Result<T> {
+    let end = 8 + std::mem::size_of::<T>();
+    if data.len() < end {
+        return Err(ErrorCode::AccountDidNotDeserialize.into());
+    }
+    if data[..8] != *T::DISCRIMINATOR {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    Ok(bytemuck::pod_read_unaligned(&data[8..end]))
+}
+
+pub fn decode_market(data: &[u8]) -> Result<Market> {
+    decode(data)
+}
+
+pub fn decode_position(data: &[u8]) -> Result<Position> {
+    decode(data)
+}
+
+pub fn decode_creator_profile(data: &[u8]) -> Result<CreatorProfile> {
+    decode(data)
+}
+
+pub fn decode_registry(data: &[u8]) -> Result<MarketRegistry> {
+    decode(data)
+}
+
+pub fn status_label(status: &Status) -> &'static str {
+    match status {
+        Status::Open => "open",
+        Status::Resolved => "resolved",
+        Status::Settled => "settled",
+        Status::Halted => "halted",
+        Status::Disputed => "disputed",
+    }
+}
+
+pub fn resolution_label(resolution: Option<Resolution>) -> &'static str {
+    match resolution {
+        Some(Resolution::Yes) => "yes",
+        Some(Resolution::No) => "no",
+        Some(Resolution::Invalid) => "invalid",
+        None => "-",
+    }
+}
@@ -0,0 +1,319 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use predection::states::{InitialLiquidity, Market, MarketLimits, Resolution};
+use predection::{accounts, instruction, ID};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Everything `initialize` needs besides the signer.
+pub struct InitializeParams {
+    /// `next_market_id` of the creator's profile, 0 if it doesn't exist yet.
+    pub market_id: u64,
+    pub resolver: Pubkey,
+    pub collateral_mint: Pubkey,
+    /// Token program owning the collateral mint.
+    pub token_program: Pubkey,
+    /// SPL Token or Token-2022, for the YES/NO mints.
+    pub outcome_token_program: Pubkey,
+    /// The protocol config's treasury.
+    pub protocol_fee_collector: Pubkey,
+    pub question: String,
+    pub duration_time: i64,
+    pub fee: u64,
+    pub limits: MarketLimits,
+    pub allowlist_root: Option<[u8; 32]>,
+    pub metadata_uri: Option<String>,
+    pub category: String,
+    pub initial_liquidity: Option<InitialLiquidity>,
+    /// `total_markets` of the global registry, 0 if it doesn't exist yet.
+    pub registry_total_markets: u64,
+    /// `total_markets` of the category registry, 0 if it doesn't exist yet.
+    pub category_total_markets: u64,
+}
+
+/// Creates a market. The creator's collateral ATA collects the creator's
+/// fee share and funds the bond and any seeded liquidity.
+pub fn initialize(creator: &Pubkey, params: InitializeParams) -> Instruction {
+    let (market, _) = pda::market(creator, params.market_id);
+    let (yes_mint, _) = pda::yes_mint(&market);
+    let (no_mint, _) = pda::no_mint(&market);
+    let creator_collateral_ata = get_associated_token_address_with_program_id(
+        creator,
+        &params.collateral_mint,
+        &params.token_program,
+    );
+
+    let accounts = accounts::CreateMarket {
+        market_creator: *creator,
+        creator_profile: pda::creator_profile(creator).0,
+        market,
+        collateral_mint: params.collateral_mint,
+        collateral_info: pda::collateral_info(&params.collateral_mint).0,
+        config: pda::config().0,
+        fee_collector_colletral_ata: creator_collateral_ata,
+        creator_collateral_ata,
+        bond_vault: pda::bond_vault(&market).0,
+        protocol_fee_collector: params.protocol_fee_collector,
+        protocol_fee_collector_ata: get_associated_token_address_with_program_id(
+            &params.protocol_fee_collector,
+            &params.collateral_mint,
+            &params.token_program,
+        ),
+        yes_mint,
+        no_mint,
+        market_vault: pda::market_vault(&market, &params.collateral_mint, &params.token_program),
+        price_history: pda::price_history(&market).0,
+        registry: pda::registry().0,
+        registry_page: pda::registry_page(params.registry_total_markets).0,
+        category_registry: pda::category_registry(&params.category).0,
+        category_page: pda::category_page(&params.category, params.category_total_markets).0,
+        creator_position: pda::position(&market, creator).0,
+        creator_yes_ata: get_associated_token_address_with_program_id(
+            creator,
+            &yes_mint,
+            &params.outcome_token_program,
+        ),
+        creator_no_ata: get_associated_token_address_with_program_id(
+            creator,
+            &no_mint,
+            &params.outcome_token_program,
+        ),
+        token_program: params.token_program,
+        outcome_token_program: params.outcome_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+    };
+    let data = instruction::Initialize {
+        resolver: params.resolver,
+        question: params.question.into_bytes(),
+        duration_time: params.duration_time,
+        fee: params.fee,
+        limits: params.limits,
+        allowlist_root: params.allowlist_root,
+        metadata_uri: params.metadata_uri,
+        category: params.category,
+        initial_liquidity: params.initial_liquidity,
+    };
+    build(accounts, data)
+}
+
+/// Trader-side accounts shared by buy, sell and claim.
+struct TraderAccounts {
+    collateral_ata: Pubkey,
+    yes_ata: Pubkey,
+    no_ata: Pubkey,
+    position: Pubkey,
+}
+
+fn trader_accounts(
+    market_address: &Pubkey,
+    market: &Market,
+    trader: &Pubkey,
+    token_program: &Pubkey,
+) -> TraderAccounts {
+    TraderAccounts {
+        collateral_ata: get_associated_token_address_with_program_id(
+            trader,
+            &market.collateral_mint,
+            token_program,
+        ),
+        yes_ata: get_associated_token_address_with_program_id(
+            trader,
+            &market.yes_mint,
+            &market.outcome_token_program,
+        ),
+        no_ata: get_associated_token_address_with_program_id(
+            trader,
+            &market.no_mint,
+            &market.outcome_token_program,
+        ),
+        position: pda::position(market_address, trader).0,
+    }
+}
+
+/// Buys `amount` of collateral worth of YES or NO. `proof` is only needed
+/// for allowlisted markets. A buy the circuit breaker stops confirms without
+/// filling; send it with `RpcClient::send_trade` to find out.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_share(
+    market_address: &Pubkey,
+    market: &Market,
+    trader: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    is_yes: bool,
+    proof: Vec<[u8; 32]>,
+    wrap_sol: bool,
+) -> Instruction {
+    let trader_accounts = trader_accounts(market_address, market, trader, token_program);
+    let accounts = accounts::BuyShare {
+        signer: *trader,
+        fee_collector_ata: market.fee_collector_ata,
+        protocol_fee_collector_ata: market.protocol_fee_collector_ata,
+        market: *market_address,
+        creator_profile: pda::creator_profile(&market.authority).0,
+        market_vault: market.market_vault,
+        collateral_mint: market.collateral_mint,
+        user_collateral_mint_ata: trader_accounts.collateral_ata,
+        yes_mint: market.yes_mint,
+        no_mint: market.no_mint,
+        yes_mint_ata: trader_accounts.yes_ata,
+        no_mint_ata: trader_accounts.no_ata,
+        position: trader_accounts.position,
+        price_history: pda::price_history(market_address).0,
+        token_program: *token_program,
+        outcome_token_program: market.outcome_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+    };
+    let data = instruction::BuyShare {
+        amount,
+        is_yes,
+        proof,
+        wrap_sol,
+    };
+    build(accounts, data)
+}
+
+/// Sells `amount` shares back for collateral at 1:1. Like a buy, it can be
+/// halted without filling; see `RpcClient::send_trade`.
+pub fn sell_share(
+    market_address: &Pubkey,
+    market: &Market,
+    trader: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    is_yes: bool,
+    unwrap_sol: bool,
+) -> Instruction {
+    let trader_accounts = trader_accounts(market_address, market, trader, token_program);
+    let accounts = accounts::SellShare {
+        signer: *trader,
+        market: *market_address,
+        creator_profile: pda::creator_profile(&market.authority).0,
+        market_vault: market.market_vault,
+        collateral_mint: market.collateral_mint,
+        user_collateral_ata: trader_accounts.collateral_ata,
+        yes_mint: market.yes_mint,
+        no_mint: market.no_mint,
+        yes_mint_ata: trader_accounts.yes_ata,
+        no_mint_ata: trader_accounts.no_ata,
+        position: trader_accounts.position,
+        price_history: pda::price_history(market_address).0,
+        token_program: *token_program,
+        outcome_token_program: market.outcome_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+    };
+    let data = instruction::SellShare {
+        amount,
+        is_yes,
+        unwrap_sol,
+    };
+    build(accounts, data)
+}
+
+/// Resolves the market; must be signed by `market.resolver`.
+pub fn resolve_market(
+    market_address: &Pubkey,
+    market: &Market,
+    resolution: Resolution,
+) -> Instruction {
+    let accounts = accounts::ResolveMarket {
+        resolver: market.resolver,
+        market: *market_address,
+        yes_mint: market.yes_mint,
+        no_mint: market.no_mint,
+    };
+    build(accounts, instruction::ResolveMarket { resolution })
+}
+
+/// Voids a market left unresolved `RESOLUTION_GRACE_PERIOD` past its close.
+/// Permissionless.
+pub fn expire_market(market_address: &Pubkey) -> Instruction {
+    let accounts = accounts::ExpireMarket {
+        market: *market_address,
+    };
+    build(accounts, instruction::ExpireMarket {})
+}
+
+/// Redeems the trader's winning shares, or both sides if resolved INVALID.
+pub fn claim_winning(
+    market_address: &Pubkey,
+    market: &Market,
+    trader: &Pubkey,
+    token_program: &Pubkey,
+    unwrap_sol: bool,
+) -> Instruction {
+    let trader_accounts = trader_accounts(market_address, market, trader, token_program);
+    let accounts = accounts::ClaimWinning {
+        signer: *trader,
+        market: *market_address,
+        market_vault: market.market_vault,
+        collateral_mint: market.collateral_mint,
+        user_collateral_ata: trader_accounts.collateral_ata,
+        yes_mint: market.yes_mint,
+        no_mint: market.no_mint,
+        yes_mint_ata: trader_accounts.yes_ata,
+        no_mint_ata: trader_accounts.no_ata,
+        position: trader_accounts.position,
+        token_program: *token_program,
+        outcome_token_program: market.outcome_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+    };
+    build(accounts, instruction::ClaimWinning { unwrap_sol })
+}
+
+/// Releases the creator bond once the resolution is final: back to the
+/// creator, or to the treasury if the market resolved INVALID.
+/// Permissionless.
+pub fn settle_bond(
+    market_address: &Pubkey,
+    market: &Market,
+    token_program: &Pubkey,
+) -> Instruction {
+    let accounts = accounts::SettleBond {
+        market: *market_address,
+        config: pda::config().0,
+        collateral_mint: market.collateral_mint,
+        bond_vault: pda::bond_vault(market_address).0,
+        creator_collateral_ata: get_associated_token_address_with_program_id(
+            &market.authority,
+            &market.collateral_mint,
+            token_program,
+        ),
+        protocol_fee_collector_ata: market.protocol_fee_collector_ata,
+        token_program: *token_program,
+    };
+    build(accounts, instruction::SettleBond {})
+}
+
+/// Read-only: returns a `PriceView` as return data.
+pub fn get_price(market_address: &Pubkey, market: &Market) -> Instruction {
+    let accounts = accounts::GetPrice {
+        market: *market_address,
+        yes_mint: market.yes_mint,
+        no_mint: market.no_mint,
+        market_vault: market.market_vault,
+    };
+    build(accounts, instruction::GetPrice {})
+}
+
+/// Read-only: returns a `StatusView` as return data.
+pub fn get_status(market_address: &Pubkey) -> Instruction {
+    let accounts = accounts::GetStatus {
+        market: *market_address,
+    };
+    build(accounts, instruction::GetStatus {})
+}
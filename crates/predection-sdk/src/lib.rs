@@ -0,0 +1,14 @@
+//! Rust client for the predection program: PDA derivation, instruction
+//! builders and account decoding, built on the program's `cpi` types.
+
+pub mod accounts;
+pub mod instructions;
+pub mod pda;
+pub mod quote;
+#[cfg(feature = "client")]
+pub mod rpc;
+
+pub use predection::instructions::{PriceView, StatusView};
+pub use predection::math;
+pub use predection::states::{InitialLiquidity, Market, MarketLimits, Resolution, TradeOutcome};
+pub use predection::ID as PROGRAM_ID;
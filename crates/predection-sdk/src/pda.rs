@@ -0,0 +1,75 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use predection::states::REGISTRY_PAGE_SIZE;
+use predection::ID;
+
+pub fn market(creator: &Pubkey, market_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"market", creator.as_ref(), &market_id.to_le_bytes()],
+        &ID,
+    )
+}
+
+pub fn yes_mint(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"yes_mint", market.as_ref()], &ID)
+}
+
+pub fn no_mint(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"no_mint", market.as_ref()], &ID)
+}
+
+/// Collateral vault: the market PDA's ATA for the collateral mint.
+pub fn market_vault(market: &Pubkey, collateral_mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(market, collateral_mint, token_program)
+}
+
+pub fn position(market: &Pubkey, owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"position", market.as_ref(), owner.as_ref()], &ID)
+}
+
+pub fn price_history(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"price_history", market.as_ref()], &ID)
+}
+
+pub fn bond_vault(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bond_vault", market.as_ref()], &ID)
+}
+
+pub fn metadata(market: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"metadata", market.as_ref()], &ID)
+}
+
+pub fn creator_profile(creator: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"creator", creator.as_ref()], &ID)
+}
+
+pub fn config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"config"], &ID)
+}
+
+pub fn collateral_info(collateral_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"collateral", collateral_mint.as_ref()], &ID)
+}
+
+pub fn registry() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"registry"], &ID)
+}
+
+/// Page holding market number `index` of the global registry.
+pub fn registry_page(index: u64) -> (Pubkey, u8) {
+    let page = index / REGISTRY_PAGE_SIZE;
+    Pubkey::find_program_address(&[b"registry_page", &page.to_le_bytes()], &ID)
+}
+
+pub fn category_registry(category: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"category", category.as_bytes()], &ID)
+}
+
+/// Page holding market number `index` of `category`.
+pub fn category_page(category: &str, index: u64) -> (Pubkey, u8) {
+    let page = index / REGISTRY_PAGE_SIZE;
+    Pubkey::find_program_address(
+        &[b"category_page", category.as_bytes(), &page.to_le_bytes()],
+        &ID,
+    )
+}
@@ -0,0 +1,42 @@
+//! Off-chain quotes, computed with the same math module the program settles
+//! with, so they match on-chain results to the unit.
+
+use predection::math::{self, BuyQuote, MathError};
+use predection::states::{Market, Resolution};
+
+/// Fee split and vault deposit for buying `amount` of collateral.
+pub fn buy(market: &Market, amount: u64) -> Result<BuyQuote, MathError> {
+    math::quote_buy(amount, market.fee)
+}
+
+/// Current supplies and vault balance of a market, read from its mints and
+/// vault token account.
+#[derive(Clone, Copy, Debug)]
+pub struct Pool {
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub vault: u64,
+}
+
+/// What `claim_winning` pays a holder of `yes` and `no` shares, or `None`
+/// while the market is unresolved.
+pub fn claim(market: &Market, pool: &Pool, yes: u64, no: u64) -> Result<Option<u64>, MathError> {
+    let (shares, supply) = match market.resolution {
+        None => return Ok(None),
+        Some(Resolution::Yes) => (yes, pool.yes_supply),
+        Some(Resolution::No) => (no, pool.no_supply),
+        Some(Resolution::Invalid) => (
+            yes.saturating_add(no),
+            pool.yes_supply.saturating_add(pool.no_supply),
+        ),
+    };
+    if shares == 0 {
+        return Ok(Some(0));
+    }
+    math::pro_rata(shares, pool.vault, supply).map(Some)
+}
+
+/// Implied YES probability, scaled by `math::PRICE_SCALE`.
+pub fn yes_price(pool: &Pool) -> u64 {
+    math::yes_price(pool.yes_supply, pool.no_supply)
+}
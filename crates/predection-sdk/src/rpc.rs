@@ -0,0 +1,435 @@
+//! Minimal blocking JSON-RPC client: just the calls the off-chain tools need.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AnchorDeserialize, Discriminator, Space};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use predection::states::{
+    CategoryRegistry, Market, MarketRegistry, Position, RegistryPage, TradeOutcome,
+    REGISTRY_PAGE_SIZE,
+};
+use serde_json::{json, Value};
+use solana_hash::Hash;
+use solana_keypair::{read_keypair_file, Keypair};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use crate::accounts::decode;
+use crate::pda;
+
+/// Signature status is polled this many times, `CONFIRM_POLL_INTERVAL` apart.
+const CONFIRM_POLL_ATTEMPTS: u32 = 60;
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Most addresses `getMultipleAccounts` takes in one request.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("http error: {0}")]
+    Http(#[from] Box<ureq::Error>),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("rpc error: {0}")]
+    Rpc(String),
+    #[error("unexpected rpc response: {0}")]
+    Response(String),
+    #[error("account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("failed to decode account: {0}")]
+    Decode(#[from] anchor_lang::error::Error),
+    #[error("transaction {signature} failed: {reason}")]
+    TransactionFailed { signature: String, reason: String },
+    #[error("transaction {0} was not confirmed in time")]
+    Unconfirmed(String),
+    #[error("reading keypair {path}: {reason}")]
+    Keypair { path: String, reason: String },
+}
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+/// Reads a keypair file as the Solana CLI writes it. A leading `~/` is the
+/// home directory.
+pub fn load_keypair(path: &str) -> Result<Keypair> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => {
+            let home = std::env::var("HOME").map_err(|_| ClientError::Keypair {
+                path: path.to_string(),
+                reason: "HOME is not set".to_string(),
+            })?;
+            PathBuf::from(home).join(rest)
+        }
+        None => PathBuf::from(path),
+    };
+    read_keypair_file(&path).map_err(|e| ClientError::Keypair {
+        path: path.display().to_string(),
+        reason: e.to_string(),
+    })
+}
+
+pub struct RawAccount {
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub failed: bool,
+}
+
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(request)
+            .map_err(Box::new)?
+            .into_json()?;
+
+        if let Some(error) = response.get("error") {
+            return Err(ClientError::Rpc(error.to_string()));
+        }
+        Ok(response["result"].take())
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Result<Option<RawAccount>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), { "encoding": "base64", "commitment": "confirmed" }]),
+        )?;
+        match &result["value"] {
+            Value::Null => Ok(None),
+            value => parse_account(value).map(Some),
+        }
+    }
+
+    /// Decodes an Anchor account, failing if it doesn't exist.
+    pub fn get_anchor_account<T: anchor_lang::AccountDeserialize>(
+        &self,
+        address: &Pubkey,
+    ) -> Result<T> {
+        let account = self
+            .get_account(address)?
+            .ok_or(ClientError::AccountNotFound(*address))?;
+        Ok(decode(&account.data)?)
+    }
+
+    pub fn get_market(&self, address: &Pubkey) -> Result<Market> {
+        self.get_anchor_account(address)
+    }
+
+    /// Every market of the program.
+    pub fn get_markets(&self) -> Result<Vec<(Pubkey, Market)>> {
+        let mut markets = self.get_program_accounts::<Market>(Market::INIT_SPACE, &[])?;
+        markets.sort_by_key(|(_, market)| (market.authority, market.market_id));
+        Ok(markets)
+    }
+
+    /// Markets in creation order, read through the on-chain registry: the
+    /// global one, or the category's when `category` is given. Empty if no
+    /// market was registered yet.
+    pub fn get_registered_markets(&self, category: Option<&str>) -> Result<Vec<(Pubkey, Market)>> {
+        let total_markets = match category {
+            Some(category) => self
+                .get_anchor_account::<CategoryRegistry>(&pda::category_registry(category).0)
+                .map(|registry| registry.total_markets),
+            None => self
+                .get_anchor_account::<MarketRegistry>(&pda::registry().0)
+                .map(|registry| registry.total_markets),
+        };
+        let total_markets = match total_markets {
+            Err(ClientError::AccountNotFound(_)) => return Ok(Vec::new()),
+            total_markets => total_markets?,
+        };
+
+        let page_addresses: Vec<Pubkey> = (0..total_markets)
+            .step_by(REGISTRY_PAGE_SIZE as usize)
+            .map(|index| match category {
+                Some(category) => pda::category_page(category, index).0,
+                None => pda::registry_page(index).0,
+            })
+            .collect();
+        let addresses: Vec<Pubkey> = self
+            .get_anchor_accounts::<RegistryPage>(&page_addresses)?
+            .into_iter()
+            .flat_map(|page| page.markets)
+            .collect();
+        let markets = self.get_anchor_accounts::<Market>(&addresses)?;
+        Ok(addresses.into_iter().zip(markets).collect())
+    }
+
+    /// Decodes Anchor accounts in batches, failing if any doesn't exist.
+    pub fn get_anchor_accounts<T: anchor_lang::AccountDeserialize>(
+        &self,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<T>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let keys: Vec<String> = chunk.iter().map(Pubkey::to_string).collect();
+            let result = self.call(
+                "getMultipleAccounts",
+                json!([keys, { "encoding": "base64", "commitment": "confirmed" }]),
+            )?;
+            let values = result["value"]
+                .as_array()
+                .filter(|values| values.len() == chunk.len())
+                .ok_or_else(|| ClientError::Response(result.to_string()))?;
+            for (address, value) in chunk.iter().zip(values) {
+                if value.is_null() {
+                    return Err(ClientError::AccountNotFound(*address));
+                }
+                accounts.push(decode(&parse_account(value)?.data)?);
+            }
+        }
+        Ok(accounts)
+    }
+
+    /// All positions held by `owner`, across markets.
+    pub fn get_positions_by_owner(&self, owner: &Pubkey) -> Result<Vec<(Pubkey, Position)>> {
+        // discriminator (8) + market (32)
+        const OWNER_OFFSET: usize = 40;
+
+        self.get_program_accounts::<Position>(
+            Position::INIT_SPACE,
+            &[json!({ "memcmp": { "offset": OWNER_OFFSET, "bytes": owner.to_string() } })],
+        )
+    }
+
+    /// Program accounts of type `T` (`space` bytes after the discriminator)
+    /// matching the extra `memcmp` filters.
+    fn get_program_accounts<T: anchor_lang::AccountDeserialize + Discriminator>(
+        &self,
+        space: usize,
+        extra_filters: &[Value],
+    ) -> Result<Vec<(Pubkey, T)>> {
+        let mut filters = vec![
+            json!({ "dataSize": 8 + space }),
+            json!({ "memcmp": { "offset": 0, "bytes": BASE64.encode(T::DISCRIMINATOR), "encoding": "base64" } }),
+        ];
+        filters.extend_from_slice(extra_filters);
+        let result = self.call(
+            "getProgramAccounts",
+            json!([
+                predection::ID.to_string(),
+                { "encoding": "base64", "commitment": "confirmed", "filters": filters }
+            ]),
+        )?;
+
+        let entries = result
+            .as_array()
+            .ok_or_else(|| ClientError::Response(result.to_string()))?;
+        let mut accounts = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let address = parse_pubkey(&entry["pubkey"])?;
+            let account = parse_account(&entry["account"])?;
+            accounts.push((address, decode::<T>(&account.data)?));
+        }
+        Ok(accounts)
+    }
+
+    /// Signatures touching `address`, newest first. Pages backwards from
+    /// `before` and stops at `until`, both exclusive.
+    pub fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&str>,
+        until: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SignatureInfo>> {
+        let result = self.call(
+            "getSignaturesForAddress",
+            json!([
+                address.to_string(),
+                { "before": before, "until": until, "limit": limit, "commitment": "confirmed" }
+            ]),
+        )?;
+        let entries = result
+            .as_array()
+            .ok_or_else(|| ClientError::Response(result.to_string()))?;
+        entries
+            .iter()
+            .map(|entry| {
+                Ok(SignatureInfo {
+                    signature: entry["signature"]
+                        .as_str()
+                        .ok_or_else(|| ClientError::Response(entry.to_string()))?
+                        .to_string(),
+                    slot: entry["slot"].as_u64().unwrap_or_default(),
+                    failed: !entry["err"].is_null(),
+                })
+            })
+            .collect()
+    }
+
+    /// A confirmed transaction in `json` encoding, as returned by the node.
+    pub fn get_transaction(&self, signature: &str) -> Result<Option<Value>> {
+        let result = self.call(
+            "getTransaction",
+            json!([
+                signature,
+                { "encoding": "json", "commitment": "confirmed", "maxSupportedTransactionVersion": 0 }
+            ]),
+        )?;
+        Ok((!result.is_null()).then_some(result))
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        let blockhash = result["value"]["blockhash"]
+            .as_str()
+            .ok_or_else(|| ClientError::Response(result.to_string()))?;
+        Hash::from_str(blockhash).map_err(|e| ClientError::Response(e.to_string()))
+    }
+
+    /// Signs with `payer` plus any extra `signers`, sends, and waits for
+    /// confirmation. Returns the transaction signature.
+    pub fn send_and_confirm(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<String> {
+        let blockhash = self.get_latest_blockhash()?;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+
+        let wire =
+            bincode::serialize(&transaction).map_err(|e| ClientError::Response(e.to_string()))?;
+        let result = self.call(
+            "sendTransaction",
+            json!([BASE64.encode(wire), { "encoding": "base64", "preflightCommitment": "confirmed" }]),
+        )?;
+        let signature = result
+            .as_str()
+            .ok_or_else(|| ClientError::Response(result.to_string()))?
+            .to_string();
+
+        self.confirm(&signature)?;
+        Ok(signature)
+    }
+
+    /// Simulates a view instruction such as `get_price` and decodes its
+    /// return data. `payer` only has to exist; nothing is signed or sent.
+    pub fn simulate_view<T: AnchorDeserialize>(
+        &self,
+        instruction: Instruction,
+        payer: &Pubkey,
+    ) -> Result<T> {
+        let transaction = Transaction::new_with_payer(&[instruction], Some(payer));
+        let wire =
+            bincode::serialize(&transaction).map_err(|e| ClientError::Response(e.to_string()))?;
+        let result = self.call(
+            "simulateTransaction",
+            json!([BASE64.encode(wire), {
+                "encoding": "base64",
+                "sigVerify": false,
+                "replaceRecentBlockhash": true,
+                "commitment": "confirmed",
+            }]),
+        )?;
+        let value = &result["value"];
+        if !value["err"].is_null() {
+            return Err(ClientError::Rpc(format!(
+                "simulation failed: {} {}",
+                value["err"], value["logs"]
+            )));
+        }
+        parse_return_data(value)
+    }
+
+    /// Sends a `buy_share` or `sell_share` signed by `payer` and returns its
+    /// signature with what it did. A trade the circuit breaker stopped
+    /// confirms like a fill, and only its return data tells them apart.
+    pub fn send_trade(
+        &self,
+        instruction: Instruction,
+        payer: &Keypair,
+    ) -> Result<(String, TradeOutcome)> {
+        let signature = self.send_and_confirm(&[instruction], payer, &[])?;
+        let transaction = self
+            .get_transaction(&signature)?
+            .ok_or_else(|| ClientError::Response(format!("transaction {signature} not found")))?;
+        let outcome = parse_return_data(&transaction["meta"])?;
+        Ok((signature, outcome))
+    }
+
+    fn confirm(&self, signature: &str) -> Result<()> {
+        for _ in 0..CONFIRM_POLL_ATTEMPTS {
+            let result = self.call("getSignatureStatuses", json!([[signature]]))?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    return Err(ClientError::TransactionFailed {
+                        signature: signature.to_string(),
+                        reason: status["err"].to_string(),
+                    });
+                }
+                if matches!(
+                    status["confirmationStatus"].as_str(),
+                    Some("confirmed" | "finalized")
+                ) {
+                    return Ok(());
+                }
+            }
+            sleep(CONFIRM_POLL_INTERVAL);
+        }
+        Err(ClientError::Unconfirmed(signature.to_string()))
+    }
+}
+
+/// Decodes the `returnData` of a simulation result or transaction meta.
+fn parse_return_data<T: AnchorDeserialize>(value: &Value) -> Result<T> {
+    let data = value["returnData"]["data"][0]
+        .as_str()
+        .and_then(|data| BASE64.decode(data).ok())
+        .ok_or_else(|| ClientError::Response(value.to_string()))?;
+    T::try_from_slice(&data).map_err(|e| ClientError::Response(e.to_string()))
+}
+
+fn parse_pubkey(value: &Value) -> Result<Pubkey> {
+    value
+        .as_str()
+        .and_then(|s| Pubkey::from_str(s).ok())
+        .ok_or_else(|| ClientError::Response(value.to_string()))
+}
+
+fn parse_account(value: &Value) -> Result<RawAccount> {
+    let data = value["data"][0]
+        .as_str()
+        .and_then(|data| BASE64.decode(data).ok())
+        .ok_or_else(|| ClientError::Response(value.to_string()))?;
+    Ok(RawAccount {
+        owner: parse_pubkey(&value["owner"])?,
+        lamports: value["lamports"].as_u64().unwrap_or_default(),
+        data,
+    })
+}
@@ -0,0 +1,306 @@
+//! PDAs and instruction layouts the program and its integrators agree on.
+//! The seeds and account orders are spelled out here rather than derived
+//! from the SDK, so a change on either side shows up as a failure.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::{system_program, AnchorDeserialize, Discriminator};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use predection::instruction;
+use predection::states::{Market, Status, REGISTRY_PAGE_SIZE};
+use predection_sdk::{instructions, pda, PROGRAM_ID};
+
+fn derive(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &PROGRAM_ID).0
+}
+
+fn market(creator: Pubkey, market_id: u64) -> (Pubkey, Market) {
+    let address = pda::market(&creator, market_id).0;
+    let collateral_mint = Pubkey::new_unique();
+    let state = Market {
+        market_id,
+        authority: creator,
+        resolver: Pubkey::new_unique(),
+        resolution_time: 0,
+        option: None,
+        status: Status::Open,
+        yes_mint: pda::yes_mint(&address).0,
+        no_mint: pda::no_mint(&address).0,
+        market_vault: pda::market_vault(&address, &collateral_mint, &spl_token::ID),
+        collateral_mint,
+        market_close_timestamp: 0,
+        question: "Will it rain tomorrow?".to_string(),
+        fee_collector: creator,
+        fee_collector_ata: Pubkey::new_unique(),
+        protocol_fee_collector: Pubkey::new_unique(),
+        protocol_fee_collector_ata: Pubkey::new_unique(),
+        fee: 100,
+        bump: 0,
+        max_open_interest: None,
+        max_position_per_wallet: None,
+        max_price_move_bps: None,
+        price_move_window: 0,
+        halt_cooldown: 0,
+        halted_until: 0,
+        breaker_min_liquidity: 0,
+        allowlist_root: None,
+        outcome_token_program: spl_token_2022::ID,
+        min_trade_size: 0,
+        trade_count: 0,
+        category: "weather".to_string(),
+        registry_index: 0,
+        resolution: None,
+        resolved_at: 0,
+        dispute_window: 0,
+        disputer: None,
+        dispute_bond: 0,
+        bond_amount: 0,
+    };
+    (address, state)
+}
+
+/// Checks the discriminator and decodes the arguments.
+fn args<T: Discriminator + AnchorDeserialize>(ix: &Instruction) -> T {
+    assert_eq!(ix.program_id, PROGRAM_ID);
+    assert_eq!(&ix.data[..8], T::DISCRIMINATOR);
+    T::try_from_slice(&ix.data[8..]).unwrap()
+}
+
+#[test]
+fn pdas_follow_the_program_seeds() {
+    let creator = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let market = pda::market(&creator, 7).0;
+
+    assert_eq!(
+        market,
+        derive(&[b"market", creator.as_ref(), &7u64.to_le_bytes()])
+    );
+    assert_eq!(
+        pda::yes_mint(&market).0,
+        derive(&[b"yes_mint", market.as_ref()])
+    );
+    assert_eq!(
+        pda::no_mint(&market).0,
+        derive(&[b"no_mint", market.as_ref()])
+    );
+    assert_eq!(
+        pda::position(&market, &owner).0,
+        derive(&[b"position", market.as_ref(), owner.as_ref()])
+    );
+    assert_eq!(
+        pda::price_history(&market).0,
+        derive(&[b"price_history", market.as_ref()])
+    );
+    assert_eq!(
+        pda::bond_vault(&market).0,
+        derive(&[b"bond_vault", market.as_ref()])
+    );
+    assert_eq!(
+        pda::metadata(&market).0,
+        derive(&[b"metadata", market.as_ref()])
+    );
+    assert_eq!(
+        pda::creator_profile(&creator).0,
+        derive(&[b"creator", creator.as_ref()])
+    );
+    assert_eq!(pda::config().0, derive(&[b"config"]));
+    assert_eq!(
+        pda::collateral_info(&mint).0,
+        derive(&[b"collateral", mint.as_ref()])
+    );
+    assert_eq!(pda::registry().0, derive(&[b"registry"]));
+    assert_eq!(
+        pda::category_registry("crypto").0,
+        derive(&[b"category", b"crypto"])
+    );
+
+    // The vault is the market's ATA under the collateral's token program
+    assert_eq!(
+        pda::market_vault(&market, &mint, &spl_token_2022::ID),
+        get_associated_token_address_with_program_id(&market, &mint, &spl_token_2022::ID)
+    );
+
+    // Seeds are checked with the bump the SDK found
+    let (address, bump) = pda::market(&creator, 7);
+    let seeds: &[&[u8]] = &[b"market", creator.as_ref(), &7u64.to_le_bytes(), &[bump]];
+    assert_eq!(
+        Pubkey::create_program_address(seeds, &PROGRAM_ID).unwrap(),
+        address
+    );
+}
+
+#[test]
+fn registry_pages_hold_page_size_markets() {
+    let last_of_first = REGISTRY_PAGE_SIZE - 1;
+    let first_of_second = REGISTRY_PAGE_SIZE;
+
+    assert_eq!(pda::registry_page(0).0, pda::registry_page(last_of_first).0);
+    assert_eq!(
+        pda::registry_page(first_of_second).0,
+        derive(&[b"registry_page", &1u64.to_le_bytes()])
+    );
+    assert_eq!(
+        pda::category_page("crypto", last_of_first).0,
+        derive(&[b"category_page", b"crypto", &0u64.to_le_bytes()])
+    );
+    assert_eq!(
+        pda::category_page("crypto", first_of_second).0,
+        derive(&[b"category_page", b"crypto", &1u64.to_le_bytes()])
+    );
+    assert_ne!(
+        pda::category_page("crypto", 0).0,
+        pda::category_page("sports", 0).0
+    );
+}
+
+#[test]
+fn buy_share_keeps_its_account_order() {
+    let trader = Pubkey::new_unique();
+    let (address, state) = market(Pubkey::new_unique(), 3);
+    let ata = |mint: &Pubkey, program: &Pubkey| {
+        get_associated_token_address_with_program_id(&trader, mint, program)
+    };
+
+    let ix = instructions::buy_share(
+        &address,
+        &state,
+        &trader,
+        &spl_token::ID,
+        5_000_000,
+        false,
+        vec![[7; 32]],
+        true,
+    );
+
+    assert_eq!(
+        ix.accounts,
+        vec![
+            AccountMeta::new(trader, true),
+            AccountMeta::new(state.fee_collector_ata, false),
+            AccountMeta::new(state.protocol_fee_collector_ata, false),
+            AccountMeta::new(address, false),
+            AccountMeta::new(pda::creator_profile(&state.authority).0, false),
+            AccountMeta::new(state.market_vault, false),
+            AccountMeta::new(state.collateral_mint, false),
+            AccountMeta::new(ata(&state.collateral_mint, &spl_token::ID), false),
+            AccountMeta::new(state.yes_mint, false),
+            AccountMeta::new(state.no_mint, false),
+            AccountMeta::new(ata(&state.yes_mint, &spl_token_2022::ID), false),
+            AccountMeta::new(ata(&state.no_mint, &spl_token_2022::ID), false),
+            AccountMeta::new(pda::position(&address, &trader).0, false),
+            AccountMeta::new(pda::price_history(&address).0, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new_readonly(spl_token_2022::ID, false),
+            AccountMeta::new_readonly(associated_token::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ]
+    );
+    let data: instruction::BuyShare = args(&ix);
+    assert_eq!(
+        (data.amount, data.is_yes, data.proof, data.wrap_sol),
+        (5_000_000, false, vec![[7; 32]], true)
+    );
+}
+
+#[test]
+fn sell_and_claim_keep_their_account_order() {
+    let trader = Pubkey::new_unique();
+    let (address, state) = market(Pubkey::new_unique(), 0);
+    let ata = |mint: &Pubkey, program: &Pubkey| {
+        get_associated_token_address_with_program_id(&trader, mint, program)
+    };
+    let trader_accounts = [
+        AccountMeta::new(ata(&state.collateral_mint, &spl_token::ID), false),
+        AccountMeta::new(state.yes_mint, false),
+        AccountMeta::new(state.no_mint, false),
+        AccountMeta::new(ata(&state.yes_mint, &spl_token_2022::ID), false),
+        AccountMeta::new(ata(&state.no_mint, &spl_token_2022::ID), false),
+        AccountMeta::new(pda::position(&address, &trader).0, false),
+    ];
+    let programs = [
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new_readonly(spl_token_2022::ID, false),
+        AccountMeta::new_readonly(associated_token::ID, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+
+    let sell = instructions::sell_share(&address, &state, &trader, &spl_token::ID, 42, true, false);
+    let mut expected = vec![
+        AccountMeta::new(trader, true),
+        AccountMeta::new(address, false),
+        AccountMeta::new(pda::creator_profile(&state.authority).0, false),
+        AccountMeta::new(state.market_vault, false),
+        AccountMeta::new(state.collateral_mint, false),
+    ];
+    expected.extend(trader_accounts.clone());
+    expected.push(AccountMeta::new(pda::price_history(&address).0, false));
+    expected.extend(programs.clone());
+    assert_eq!(sell.accounts, expected);
+    let data: instruction::SellShare = args(&sell);
+    assert_eq!(
+        (data.amount, data.is_yes, data.unwrap_sol),
+        (42, true, false)
+    );
+
+    let claim = instructions::claim_winning(&address, &state, &trader, &spl_token::ID, true);
+    let mut expected = vec![
+        AccountMeta::new(trader, true),
+        AccountMeta::new(address, false),
+        AccountMeta::new(state.market_vault, false),
+        AccountMeta::new(state.collateral_mint, false),
+    ];
+    expected.extend(trader_accounts);
+    expected.extend(programs);
+    assert_eq!(claim.accounts, expected);
+    let data: instruction::ClaimWinning = args(&claim);
+    assert!(data.unwrap_sol);
+}
+
+#[test]
+fn views_and_cranks_need_no_signer() {
+    let (address, mut state) = market(Pubkey::new_unique(), 0);
+    state.resolver = Pubkey::new_unique();
+
+    let price = instructions::get_price(&address, &state);
+    assert_eq!(
+        price.accounts,
+        vec![
+            AccountMeta::new_readonly(address, false),
+            AccountMeta::new_readonly(state.yes_mint, false),
+            AccountMeta::new_readonly(state.no_mint, false),
+            AccountMeta::new_readonly(state.market_vault, false),
+        ]
+    );
+    args::<instruction::GetPrice>(&price);
+    let status = instructions::get_status(&address);
+    assert_eq!(
+        status.accounts,
+        vec![AccountMeta::new_readonly(address, false)]
+    );
+    args::<instruction::GetStatus>(&status);
+
+    let expire = instructions::expire_market(&address);
+    assert_eq!(expire.accounts, vec![AccountMeta::new(address, false)]);
+
+    // The bond goes back to the creator's ATA under the collateral's program
+    let settle = instructions::settle_bond(&address, &state, &spl_token_2022::ID);
+    assert_eq!(
+        settle.accounts[4].pubkey,
+        get_associated_token_address_with_program_id(
+            &state.authority,
+            &state.collateral_mint,
+            &spl_token_2022::ID
+        )
+    );
+    assert!(settle.accounts.iter().all(|meta| !meta.is_signer));
+
+    // Only the resolver signs a resolution
+    let resolve = instructions::resolve_market(&address, &state, predection_sdk::Resolution::No);
+    assert_eq!(resolve.accounts[0], AccountMeta::new(state.resolver, true));
+    let data: instruction::ResolveMarket = args(&resolve);
+    assert_eq!(data.resolution, predection_sdk::Resolution::No);
+}
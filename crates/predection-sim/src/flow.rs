@@ -0,0 +1,181 @@
+//! Order flow to replay: synthetic, from a JSON file, or from the trades
+//! `predection-indexer` recorded.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use predection_math::quote_buy;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+
+use crate::market::Side;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Order {
+    Buy {
+        trader: String,
+        side: Side,
+        amount: u64,
+        /// Seconds since the market opened
+        #[serde(default)]
+        time: i64,
+    },
+    Sell {
+        trader: String,
+        side: Side,
+        shares: u64,
+        #[serde(default)]
+        time: i64,
+    },
+}
+
+impl Order {
+    pub fn time(&self) -> i64 {
+        match self {
+            Order::Buy { time, .. } | Order::Sell { time, .. } => *time,
+        }
+    }
+}
+
+/// Noise traders pick a side at random while informed traders all buy
+/// `informed_side`, the side that will win. Sizes are uniform up to twice
+/// `mean_size`, and orders arrive every `interval` seconds.
+#[derive(Clone, Debug)]
+pub struct SyntheticFlow {
+    pub orders: usize,
+    pub traders: usize,
+    /// Fraction of orders placed by informed traders
+    pub informed_share: f64,
+    pub informed_side: Side,
+    /// Fraction of orders that sell part of a holding instead of buying
+    pub sell_share: f64,
+    pub mean_size: u64,
+    pub interval: i64,
+    /// Fee the buys are charged, to know how many shares each trader holds
+    pub fee_bps: u64,
+}
+
+impl SyntheticFlow {
+    /// The same `seed` always gives the same flow.
+    pub fn generate(&self, seed: u64) -> Vec<Order> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let traders = self.traders.max(1);
+        let mut holdings: HashMap<(usize, Side), u64> = HashMap::new();
+        let mut orders = Vec::with_capacity(self.orders);
+
+        while orders.len() < self.orders {
+            let time = orders.len() as i64 * self.interval;
+            let index = rng.gen_range(0..traders);
+            let trader = format!("trader-{index}");
+
+            if rng.gen_bool(self.sell_share.clamp(0.0, 1.0)) {
+                let side = random_side(&mut rng);
+                let held = holdings.entry((index, side)).or_default();
+                if *held > 0 {
+                    let shares = rng.gen_range(1..=*held);
+                    *held -= shares;
+                    orders.push(Order::Sell {
+                        trader,
+                        side,
+                        shares,
+                        time,
+                    });
+                }
+                continue;
+            }
+
+            let side = if rng.gen_bool(self.informed_share.clamp(0.0, 1.0)) {
+                self.informed_side
+            } else {
+                random_side(&mut rng)
+            };
+            let amount = rng.gen_range(1..=self.mean_size.max(1) * 2);
+            if let Ok(quote) = quote_buy(amount, self.fee_bps) {
+                *holdings.entry((index, side)).or_default() += quote.net_amount;
+            }
+            orders.push(Order::Buy {
+                trader,
+                side,
+                amount,
+                time,
+            });
+        }
+        orders
+    }
+}
+
+fn random_side(rng: &mut StdRng) -> Side {
+    if rng.gen_bool(0.5) {
+        Side::Yes
+    } else {
+        Side::No
+    }
+}
+
+/// A JSON array of orders, e.g.
+/// `[{"action": "buy", "trader": "a", "side": "yes", "amount": 1000000, "time": 60}]`.
+pub fn from_json(path: &Path) -> Result<Vec<Order>> {
+    let contents = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&contents).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Buys and sells the indexer recorded for `market`, oldest first. Times
+/// count from the market's creation, or from its first trade if the
+/// indexer missed that; trades without a block time keep the previous one.
+pub fn from_indexer(path: &Path, market: &str) -> Result<Vec<Order>> {
+    let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("opening {}", path.display()))?;
+    let opened: Option<i64> = db.query_row(
+        "SELECT COALESCE(
+            (SELECT created_at FROM markets WHERE address = ?1),
+            (SELECT MIN(block_time) FROM trades WHERE market = ?1)
+         )",
+        [market],
+        |row| row.get(0),
+    )?;
+    let mut statement = db.prepare(
+        "SELECT trader, kind, side, collateral, shares, block_time FROM trades
+         WHERE market = ?1 AND kind IN ('buy', 'sell')
+         ORDER BY slot, id",
+    )?;
+    let mut rows = statement.query([market])?;
+
+    let mut orders = Vec::new();
+    let mut time = 0;
+    while let Some(row) = rows.next()? {
+        let trader: String = row.get(0)?;
+        let kind: String = row.get(1)?;
+        let side = match row.get::<_, String>(2)?.as_str() {
+            "yes" => Side::Yes,
+            "no" => Side::No,
+            other => bail!("unexpected side {other}"),
+        };
+        let collateral = row.get::<_, i64>(3)? as u64;
+        let shares = row.get::<_, i64>(4)? as u64;
+        if let (Some(block_time), Some(opened)) = (row.get::<_, Option<i64>>(5)?, opened) {
+            time = block_time.saturating_sub(opened).max(time);
+        }
+        orders.push(match kind.as_str() {
+            "buy" => Order::Buy {
+                trader,
+                side,
+                amount: collateral,
+                time,
+            },
+            _ => Order::Sell {
+                trader,
+                side,
+                shares,
+                time,
+            },
+        });
+    }
+    if orders.is_empty() {
+        bail!("no trades indexed for market {market}");
+    }
+    Ok(orders)
+}
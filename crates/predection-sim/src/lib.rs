@@ -0,0 +1,17 @@
+//! Off-chain replay of predection markets. Orders go through the same
+//! `predection_math` fee, price and payout functions the program uses, so a
+//! backtest settles to the lamport what the chain would have.
+//!
+//! Markets are parimutuel: the creator's seed is the only liquidity, so
+//! "LP PnL" is what that seed claims back, and the worst case over outcomes
+//! is the subsidy a creator signs up for.
+
+pub mod flow;
+pub mod market;
+pub mod report;
+
+pub use flow::{Order, SyntheticFlow};
+pub use market::{MarketParams, Outcome, Side, SimError, SimMarket};
+pub use report::{
+    replay, simulate, slippage_curve, sweep, worst_case_subsidy, Report, SlippagePoint, Subsidy,
+};
@@ -0,0 +1,221 @@
+//! `predection-sim`: backtests market parameters against order flow and
+//! prints the results as JSON.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::{Args, Parser, Subcommand};
+use predection_sim::flow::{self, Order, SyntheticFlow};
+use predection_sim::{
+    replay, simulate, slippage_curve, sweep, worst_case_subsidy, MarketParams, Outcome, Side,
+};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(
+    name = "predection-sim",
+    about = "Replay order flow through predection's pricing and fees"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// LP PnL and fee income per outcome, and the worst-case subsidy
+    Run {
+        #[command(flatten)]
+        market: MarketArgs,
+        #[command(flatten)]
+        flow: FlowArgs,
+        /// Report a single outcome instead of all of them
+        #[arg(long, value_parser = parse_outcome)]
+        outcome: Option<Outcome>,
+    },
+    /// Price impact of single buys, after replaying the flow
+    Slippage {
+        #[command(flatten)]
+        market: MarketArgs,
+        #[command(flatten)]
+        flow: FlowArgs,
+        #[arg(long, value_parser = parse_side, default_value = "yes")]
+        side: Side,
+        /// Buy sizes to quote, in collateral base units
+        #[arg(long, value_delimiter = ',', required = true)]
+        sizes: Vec<u64>,
+    },
+    /// Worst-case subsidy over a grid of fees and seed sizes
+    Sweep {
+        #[command(flatten)]
+        market: MarketArgs,
+        #[command(flatten)]
+        flow: FlowArgs,
+        #[arg(long, value_delimiter = ',', required = true)]
+        fees_bps: Vec<u64>,
+        #[arg(long, value_delimiter = ',', required = true)]
+        liquidities: Vec<u64>,
+    },
+}
+
+#[derive(Args)]
+struct MarketArgs {
+    #[arg(long, default_value_t = 100)]
+    fee_bps: u64,
+    /// Creator's seed, in collateral base units
+    #[arg(long, default_value_t = 100_000_000)]
+    liquidity: u64,
+    /// Opening YES odds of the seed
+    #[arg(long, default_value_t = 5_000)]
+    yes_bps: u64,
+    #[arg(long, default_value_t = 0)]
+    min_trade_size: u64,
+    /// Cap on collateral in the vault
+    #[arg(long)]
+    max_open_interest: Option<u64>,
+    /// Cap on the shares one trader holds
+    #[arg(long)]
+    max_position: Option<u64>,
+    /// Halt when a trade would move YES more than this, in bps
+    #[arg(long, requires_all = ["price_move_window", "halt_cooldown"])]
+    max_price_move_bps: Option<u64>,
+    /// Seconds of price history a move is measured against
+    #[arg(long, default_value_t = 0)]
+    price_move_window: i64,
+    /// Seconds trading stays halted once the breaker trips
+    #[arg(long, default_value_t = 0)]
+    halt_cooldown: i64,
+    /// Shares outstanding below which moves aren't checked
+    #[arg(long, default_value_t = 0)]
+    breaker_min_liquidity: u64,
+}
+
+/// Where orders come from; synthetic unless a file or database is given.
+#[derive(Args)]
+struct FlowArgs {
+    /// JSON array of orders
+    #[arg(long, conflicts_with = "indexer_db")]
+    flow: Option<PathBuf>,
+    /// Replay a market's history from a predection-indexer database
+    #[arg(long, requires = "market")]
+    indexer_db: Option<PathBuf>,
+    #[arg(long)]
+    market: Option<String>,
+
+    #[arg(long, default_value_t = 500)]
+    orders: usize,
+    #[arg(long, default_value_t = 50)]
+    traders: usize,
+    /// Fraction of orders placed by traders who know the outcome
+    #[arg(long, default_value_t = 0.2)]
+    informed_share: f64,
+    #[arg(long, value_parser = parse_side, default_value = "yes")]
+    informed_side: Side,
+    #[arg(long, default_value_t = 0.1)]
+    sell_share: f64,
+    #[arg(long, default_value_t = 1_000_000)]
+    mean_size: u64,
+    /// Seconds between synthetic orders
+    #[arg(long, default_value_t = 60)]
+    interval: i64,
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+}
+
+impl MarketArgs {
+    fn params(&self) -> MarketParams {
+        MarketParams {
+            fee_bps: self.fee_bps,
+            initial_liquidity: self.liquidity,
+            initial_yes_bps: self.yes_bps,
+            min_trade_size: self.min_trade_size,
+            max_open_interest: self.max_open_interest,
+            max_position_per_wallet: self.max_position,
+            max_price_move_bps: self.max_price_move_bps,
+            price_move_window: self.price_move_window,
+            halt_cooldown: self.halt_cooldown,
+            breaker_min_liquidity: self.breaker_min_liquidity,
+        }
+    }
+}
+
+impl FlowArgs {
+    fn orders(&self, fee_bps: u64) -> Result<Vec<Order>> {
+        if let Some(path) = &self.flow {
+            return flow::from_json(path);
+        }
+        if let (Some(path), Some(market)) = (&self.indexer_db, &self.market) {
+            return flow::from_indexer(path, market);
+        }
+        Ok(SyntheticFlow {
+            orders: self.orders,
+            traders: self.traders,
+            informed_share: self.informed_share,
+            informed_side: self.informed_side,
+            sell_share: self.sell_share,
+            mean_size: self.mean_size,
+            interval: self.interval,
+            fee_bps,
+        }
+        .generate(self.seed))
+    }
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Run {
+            market,
+            flow,
+            outcome,
+        } => {
+            let params = market.params();
+            let orders = flow.orders(params.fee_bps)?;
+            match outcome {
+                Some(outcome) => print(&simulate(params, &orders, outcome)?),
+                None => print(&worst_case_subsidy(params, &orders)?),
+            }
+        }
+        Command::Slippage {
+            market,
+            flow,
+            side,
+            sizes,
+        } => {
+            let params = market.params();
+            let replayed = replay(params, &flow.orders(params.fee_bps)?)?;
+            print(&slippage_curve(&replayed.market, side, &sizes)?)
+        }
+        Command::Sweep {
+            market,
+            flow,
+            fees_bps,
+            liquidities,
+        } => {
+            let params = market.params();
+            let orders = flow.orders(params.fee_bps)?;
+            print(&sweep(params, &fees_bps, &liquidities, &orders)?)
+        }
+    }
+}
+
+fn print(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn parse_side(side: &str) -> Result<Side> {
+    match side {
+        "yes" => Ok(Side::Yes),
+        "no" => Ok(Side::No),
+        _ => bail!("side must be \"yes\" or \"no\""),
+    }
+}
+
+fn parse_outcome(outcome: &str) -> Result<Outcome> {
+    match outcome {
+        "yes" => Ok(Outcome::Yes),
+        "no" => Ok(Outcome::No),
+        "invalid" => Ok(Outcome::Invalid),
+        _ => bail!("outcome must be \"yes\", \"no\" or \"invalid\""),
+    }
+}
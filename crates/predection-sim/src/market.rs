@@ -0,0 +1,452 @@
+//! A market ledger that moves collateral and shares the way `initialize`,
+//! `buy_share`, `sell_share`, `resolve_market` and `claim_winning` do,
+//! through the same `predection_math` calls, and rejects or halts the
+//! trades the program's limits and circuit breaker would.
+
+use std::collections::BTreeMap;
+
+use predection_math::{
+    next_slot, pro_rata, quote_buy, ring_index, split_by_odds, yes_price, CircuitBreaker, FeeSplit,
+    MathError, PRICE_HISTORY_CAPACITY,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Yes,
+    No,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Yes,
+    No,
+    Invalid,
+}
+
+impl Outcome {
+    pub const ALL: [Outcome; 3] = [Outcome::Yes, Outcome::No, Outcome::Invalid];
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SimError {
+    #[error("math error: {0:?}")]
+    Math(MathError),
+    #[error("trade below the minimum size")]
+    TradeTooSmall,
+    #[error("not enough shares to sell")]
+    InsufficientShares,
+    #[error("market is resolved")]
+    MarketResolved,
+    #[error("market is not resolved")]
+    MarketNotResolved,
+    #[error("one-sided markets can only resolve INVALID")]
+    OneSided,
+    #[error("initial odds must be strictly between 0 and 10000 bps")]
+    InvalidInitialOdds,
+    #[error("invalid amount")]
+    InvalidAmount,
+    #[error("invalid circuit breaker settings")]
+    InvalidCircuitBreaker,
+    #[error("open interest cap exceeded")]
+    OpenInterestCapExceeded,
+    #[error("position limit exceeded")]
+    PositionLimitExceeded,
+    #[error("market is halted")]
+    MarketHalted,
+    #[error("trade would trip the circuit breaker; market halted")]
+    CircuitBreakerTripped,
+}
+
+impl From<MathError> for SimError {
+    fn from(error: MathError) -> Self {
+        SimError::Math(error)
+    }
+}
+
+/// The knobs a creator picks at `initialize`, and the collateral's
+/// `min_trade_size`.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct MarketParams {
+    pub fee_bps: u64,
+    /// Creator's seed; the creator is the market's only LP. Zero seeds
+    /// nothing.
+    pub initial_liquidity: u64,
+    /// Opening YES odds of the seed, in bps
+    pub initial_yes_bps: u64,
+    pub min_trade_size: u64,
+    /// Cap on collateral held in the vault
+    #[serde(default)]
+    pub max_open_interest: Option<u64>,
+    /// Cap on the shares one trader holds
+    #[serde(default)]
+    pub max_position_per_wallet: Option<u64>,
+    /// Circuit breaker threshold, in bps of PRICE_SCALE
+    #[serde(default)]
+    pub max_price_move_bps: Option<u64>,
+    /// Seconds of price history a move is measured against
+    #[serde(default)]
+    pub price_move_window: i64,
+    /// Seconds trading stays halted once the breaker trips
+    #[serde(default)]
+    pub halt_cooldown: i64,
+    /// Shares outstanding below which moves aren't checked
+    #[serde(default)]
+    pub breaker_min_liquidity: u64,
+}
+
+impl MarketParams {
+    /// The circuit breaker the program builds from these limits.
+    pub fn circuit_breaker(&self) -> Option<CircuitBreaker> {
+        self.max_price_move_bps
+            .map(|max_price_move_bps| CircuitBreaker {
+                max_price_move_bps,
+                window: self.price_move_window,
+                cooldown: self.halt_cooldown,
+                min_liquidity: self.breaker_min_liquidity,
+            })
+    }
+}
+
+/// Shares held and collateral moved by one participant.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct Holding {
+    pub yes: u64,
+    pub no: u64,
+    pub paid_in: u64,
+    pub paid_out: u64,
+}
+
+impl Holding {
+    /// Collateral out minus collateral in; only final once claimed.
+    pub fn pnl(&self) -> i64 {
+        (self.paid_out as i128 - self.paid_in as i128) as i64
+    }
+
+    fn shares(&mut self, side: Side) -> &mut u64 {
+        match side {
+            Side::Yes => &mut self.yes,
+            Side::No => &mut self.no,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fill {
+    pub amount: u64,
+    pub fee: FeeSplit,
+    pub shares: u64,
+    pub price_before: u64,
+    pub price_after: u64,
+}
+
+/// The YES price after the last trade of each second, in the same ring the
+/// program's `PriceHistory` keeps.
+#[derive(Clone, Debug)]
+struct PriceHistory {
+    samples: [(i64, u64); PRICE_HISTORY_CAPACITY],
+    head: u64,
+    count: u64,
+    last_price: u64,
+}
+
+impl Default for PriceHistory {
+    fn default() -> Self {
+        PriceHistory {
+            samples: [(0, 0); PRICE_HISTORY_CAPACITY],
+            head: 0,
+            count: 0,
+            last_price: 0,
+        }
+    }
+}
+
+impl PriceHistory {
+    fn record(&mut self, now: i64, price: u64) {
+        let newest = (self.count > 0).then(|| self.samples[self.head as usize].0);
+        let slot = next_slot(self.head, self.count, newest, now);
+        self.head = slot.head;
+        self.count = slot.count;
+        self.samples[self.head as usize] = (now, price);
+        self.last_price = price;
+    }
+
+    fn newest_first(&self) -> impl Iterator<Item = (i64, u64)> + '_ {
+        (0..self.count).map(|i| self.samples[ring_index(self.head, i)])
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SimMarket {
+    pub params: MarketParams,
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub vault: u64,
+    pub creator_fees: u64,
+    pub protocol_fees: u64,
+    pub resolution: Option<Outcome>,
+    /// Seconds since the market opened
+    pub now: i64,
+    /// When a tripped circuit breaker lets trading resume
+    pub halted_until: Option<i64>,
+    /// The creator's seeded position
+    pub lp: Holding,
+    pub traders: BTreeMap<String, Holding>,
+    history: PriceHistory,
+}
+
+impl SimMarket {
+    /// A freshly initialized market, checked and seeded the way `initialize`
+    /// splits `initial_liquidity` by the opening odds.
+    pub fn new(params: MarketParams) -> Result<Self, SimError> {
+        // Zero would block every trade, so the program treats it as a mistake
+        if params.max_open_interest == Some(0) || params.max_position_per_wallet == Some(0) {
+            return Err(SimError::InvalidAmount);
+        }
+        if params
+            .circuit_breaker()
+            .is_some_and(|breaker| !breaker.is_valid())
+        {
+            return Err(SimError::InvalidCircuitBreaker);
+        }
+
+        let mut history = PriceHistory::default();
+        let (yes, no) = if params.initial_liquidity > 0 {
+            if params.initial_yes_bps == 0 || params.initial_yes_bps >= 10_000 {
+                return Err(SimError::InvalidInitialOdds);
+            }
+            if params
+                .max_open_interest
+                .is_some_and(|cap| params.initial_liquidity > cap)
+            {
+                return Err(SimError::OpenInterestCapExceeded);
+            }
+            let (yes, no) = split_by_odds(params.initial_liquidity, params.initial_yes_bps)?;
+            if yes == 0 || no == 0 {
+                return Err(SimError::InvalidAmount);
+            }
+            history.record(0, yes_price(yes, no));
+            (yes, no)
+        } else {
+            (0, 0)
+        };
+
+        Ok(Self {
+            params,
+            yes_supply: yes,
+            no_supply: no,
+            vault: params.initial_liquidity,
+            creator_fees: 0,
+            protocol_fees: 0,
+            resolution: None,
+            now: 0,
+            halted_until: None,
+            lp: Holding {
+                yes,
+                no,
+                paid_in: params.initial_liquidity,
+                paid_out: 0,
+            },
+            traders: BTreeMap::new(),
+            history,
+        })
+    }
+
+    pub fn yes_price(&self) -> u64 {
+        yes_price(self.yes_supply, self.no_supply)
+    }
+
+    /// Moves the clock forward to `now`; it never runs backwards.
+    pub fn advance_to(&mut self, now: i64) {
+        self.now = self.now.max(now);
+    }
+
+    pub fn buy(&mut self, trader: &str, side: Side, amount: u64) -> Result<Fill, SimError> {
+        if amount < self.params.min_trade_size {
+            return Err(SimError::TradeTooSmall);
+        }
+        self.check_open()?;
+        let quote = quote_buy(amount, self.params.fee_bps)?;
+        let net_amount = quote.net_amount;
+
+        if let Some(max_open_interest) = self.params.max_open_interest {
+            let open_interest = self
+                .vault
+                .checked_add(net_amount)
+                .ok_or(MathError::Overflow)?;
+            if open_interest > max_open_interest {
+                return Err(SimError::OpenInterestCapExceeded);
+            }
+        }
+        if let Some(max_position) = self.params.max_position_per_wallet {
+            let held = self
+                .traders
+                .get(trader)
+                .map_or(0, |holding| holding.yes.saturating_add(holding.no));
+            let position = held.checked_add(net_amount).ok_or(MathError::Overflow)?;
+            if position > max_position {
+                return Err(SimError::PositionLimitExceeded);
+            }
+        }
+        let (yes_after, no_after) = match side {
+            Side::Yes => (
+                self.yes_supply.checked_add(net_amount),
+                Some(self.no_supply),
+            ),
+            Side::No => (
+                Some(self.yes_supply),
+                self.no_supply.checked_add(net_amount),
+            ),
+        };
+        let (Some(yes_after), Some(no_after)) = (yes_after, no_after) else {
+            return Err(MathError::Overflow.into());
+        };
+        self.check_circuit_breaker(yes_after, no_after)?;
+        if net_amount == 0 {
+            return Err(SimError::InvalidAmount);
+        }
+        let price_before = self.yes_price();
+
+        self.vault += quote.net_amount;
+        *self.supply(side) += quote.net_amount;
+        self.creator_fees += quote.fee.creator;
+        self.protocol_fees += quote.fee.protocol;
+        let holding = self.traders.entry(trader.to_string()).or_default();
+        *holding.shares(side) += quote.net_amount;
+        holding.paid_in += amount;
+        self.history.record(self.now, self.yes_price());
+
+        Ok(Fill {
+            amount,
+            fee: quote.fee,
+            shares: quote.net_amount,
+            price_before,
+            price_after: self.yes_price(),
+        })
+    }
+
+    /// Sells back at 1:1, as `sell_share` does, with no fee.
+    pub fn sell(&mut self, trader: &str, side: Side, shares: u64) -> Result<Fill, SimError> {
+        self.check_open()?;
+        let (yes_after, no_after) = match side {
+            Side::Yes => (self.yes_supply.saturating_sub(shares), self.no_supply),
+            Side::No => (self.yes_supply, self.no_supply.saturating_sub(shares)),
+        };
+        self.check_circuit_breaker(yes_after, no_after)?;
+        let held = self.traders.get(trader).map_or(0, |holding| match side {
+            Side::Yes => holding.yes,
+            Side::No => holding.no,
+        });
+        if held < shares {
+            return Err(SimError::InsufficientShares);
+        }
+        let price_before = self.yes_price();
+
+        *self.supply(side) -= shares;
+        self.vault -= shares;
+        let holding = self.traders.entry(trader.to_string()).or_default();
+        *holding.shares(side) -= shares;
+        holding.paid_out += shares;
+        self.history.record(self.now, self.yes_price());
+
+        Ok(Fill {
+            amount: shares,
+            fee: FeeSplit {
+                protocol: 0,
+                creator: 0,
+            },
+            shares,
+            price_before,
+            price_after: self.yes_price(),
+        })
+    }
+
+    pub fn resolve(&mut self, outcome: Outcome) -> Result<(), SimError> {
+        if self.resolution.is_some() {
+            return Err(SimError::MarketResolved);
+        }
+        if outcome != Outcome::Invalid && (self.yes_supply == 0 || self.no_supply == 0) {
+            return Err(SimError::OneSided);
+        }
+        self.resolution = Some(outcome);
+        self.halted_until = None;
+        Ok(())
+    }
+
+    /// Trades need an open market; a halt lifts once its cooldown is over.
+    fn check_open(&mut self) -> Result<(), SimError> {
+        if self.halted_until.is_some_and(|until| self.now >= until) {
+            self.halted_until = None;
+        }
+        if self.halted_until.is_some() {
+            return Err(SimError::MarketHalted);
+        }
+        if self.resolution.is_some() {
+            return Err(SimError::MarketResolved);
+        }
+        Ok(())
+    }
+
+    /// Halts the market instead of taking supplies to `yes_after` and
+    /// `no_after` if that moves the price past the threshold. Pools below
+    /// `breaker_min_liquidity` shares aren't checked.
+    fn check_circuit_breaker(&mut self, yes_after: u64, no_after: u64) -> Result<(), SimError> {
+        let Some(breaker) = self.params.circuit_breaker() else {
+            return Ok(());
+        };
+        let Some(trip) = breaker.check(
+            (self.yes_supply, self.no_supply),
+            (yes_after, no_after),
+            self.history.last_price,
+            self.history.newest_first(),
+            self.now,
+        ) else {
+            return Ok(());
+        };
+        self.halted_until = Some(trip.halted_until);
+        Err(SimError::CircuitBreakerTripped)
+    }
+
+    /// Claims for every trader in name order, then the LP. Each claim is
+    /// pro rata of what is left, as sequential `claim_winning`s are.
+    pub fn claim_all(&mut self) -> Result<(), SimError> {
+        let outcome = self.resolution.ok_or(SimError::MarketNotResolved)?;
+        let mut traders = std::mem::take(&mut self.traders);
+        for holding in traders.values_mut() {
+            self.claim(outcome, holding)?;
+        }
+        self.traders = traders;
+        let mut lp = self.lp;
+        self.claim(outcome, &mut lp)?;
+        self.lp = lp;
+        Ok(())
+    }
+
+    fn claim(&mut self, outcome: Outcome, holding: &mut Holding) -> Result<(), SimError> {
+        let (burn_yes, burn_no, supply) = match outcome {
+            Outcome::Yes => (holding.yes, 0, self.yes_supply),
+            Outcome::No => (0, holding.no, self.no_supply),
+            Outcome::Invalid => (holding.yes, holding.no, self.yes_supply + self.no_supply),
+        };
+        if burn_yes + burn_no == 0 {
+            return Ok(());
+        }
+        let payout = pro_rata(burn_yes + burn_no, self.vault, supply)?;
+        holding.yes -= burn_yes;
+        holding.no -= burn_no;
+        holding.paid_out += payout;
+        self.yes_supply -= burn_yes;
+        self.no_supply -= burn_no;
+        self.vault -= payout;
+        Ok(())
+    }
+
+    fn supply(&mut self, side: Side) -> &mut u64 {
+        match side {
+            Side::Yes => &mut self.yes_supply,
+            Side::No => &mut self.no_supply,
+        }
+    }
+}
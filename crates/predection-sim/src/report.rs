@@ -0,0 +1,264 @@
+//! What replaying a flow means for the LP, the creator and traders.
+
+use predection_math::{mul_div, price_move_bps, pro_rata, PRICE_SCALE};
+use serde::Serialize;
+
+use crate::flow::Order;
+use crate::market::{MarketParams, Outcome, Side, SimError, SimMarket};
+
+/// A market after every order has been tried against it.
+pub struct Replay {
+    pub market: SimMarket,
+    pub orders: usize,
+    /// Orders the program would have rejected, e.g. below `min_trade_size`
+    pub rejected: usize,
+    /// Orders that tripped the circuit breaker and halted trading instead
+    /// of filling; also counted in `rejected`
+    pub halts: usize,
+    /// Collateral paid into buys, fees included
+    pub volume: u64,
+}
+
+pub fn replay(params: MarketParams, orders: &[Order]) -> Result<Replay, SimError> {
+    let mut market = SimMarket::new(params)?;
+    let mut rejected = 0;
+    let mut halts = 0;
+    let mut volume = 0;
+    for order in orders {
+        market.advance_to(order.time());
+        let fill = match order {
+            Order::Buy {
+                trader,
+                side,
+                amount,
+                ..
+            } => market.buy(trader, *side, *amount),
+            Order::Sell {
+                trader,
+                side,
+                shares,
+                ..
+            } => market.sell(trader, *side, *shares),
+        };
+        match (order, fill) {
+            (Order::Buy { amount, .. }, Ok(_)) => volume += amount,
+            (Order::Sell { .. }, Ok(_)) => {}
+            (_, Err(SimError::Math(error))) => return Err(SimError::Math(error)),
+            (_, Err(SimError::CircuitBreakerTripped)) => {
+                rejected += 1;
+                halts += 1;
+            }
+            (_, Err(_)) => rejected += 1,
+        }
+    }
+    Ok(Replay {
+        market,
+        orders: orders.len(),
+        rejected,
+        halts,
+        volume,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct LpResult {
+    pub seed: u64,
+    /// What the seeded shares claimed
+    pub payout: u64,
+    pub pnl: i64,
+    /// `pnl` plus the creator's fee income, since the creator is the LP
+    pub pnl_with_fees: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub orders: usize,
+    pub rejected: usize,
+    pub halts: usize,
+    pub volume: u64,
+    /// YES price when trading stopped, scaled by PRICE_SCALE
+    pub final_yes_price: u64,
+    pub lp: LpResult,
+    pub creator_fee_income: u64,
+    pub protocol_fee_income: u64,
+    /// Net collateral traders took out of the market after claiming
+    pub trader_pnl: i64,
+    /// Rounding dust left in the vault after every claim
+    pub dust: u64,
+}
+
+/// Replays `orders`, resolves to `outcome` and claims every position.
+pub fn simulate(
+    params: MarketParams,
+    orders: &[Order],
+    outcome: Outcome,
+) -> Result<Report, SimError> {
+    let Replay {
+        mut market,
+        orders,
+        rejected,
+        halts,
+        volume,
+    } = replay(params, orders)?;
+    let final_yes_price = market.yes_price();
+    market.resolve(outcome)?;
+    market.claim_all()?;
+
+    let lp_pnl = market.lp.pnl();
+    Ok(Report {
+        outcome,
+        orders,
+        rejected,
+        halts,
+        volume,
+        final_yes_price,
+        lp: LpResult {
+            seed: market.lp.paid_in,
+            payout: market.lp.paid_out,
+            pnl: lp_pnl,
+            pnl_with_fees: lp_pnl + market.creator_fees as i64,
+        },
+        creator_fee_income: market.creator_fees,
+        protocol_fee_income: market.protocol_fees,
+        trader_pnl: market.traders.values().map(|holding| holding.pnl()).sum(),
+        dust: market.vault,
+    })
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Subsidy {
+    pub worst_outcome: Outcome,
+    /// Seed the LP loses in the worst outcome, before fee income
+    pub max_loss: u64,
+    /// The same loss net of the creator's fee income
+    pub max_net_loss: u64,
+    /// One report per outcome the market could resolve to
+    pub outcomes: Vec<Report>,
+}
+
+/// The most the creator's seed can lose to `orders` over every outcome.
+/// Outcomes a one-sided market can't resolve to are left out.
+pub fn worst_case_subsidy(params: MarketParams, orders: &[Order]) -> Result<Subsidy, SimError> {
+    let mut outcomes = Vec::new();
+    for outcome in Outcome::ALL {
+        match simulate(params, orders, outcome) {
+            Ok(report) => outcomes.push(report),
+            Err(SimError::OneSided) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    // INVALID can always resolve, so there is at least one report
+    let worst = outcomes
+        .iter()
+        .min_by_key(|report| report.lp.pnl)
+        .expect("INVALID always resolves");
+    Ok(Subsidy {
+        worst_outcome: worst.outcome,
+        max_loss: (-worst.lp.pnl).max(0) as u64,
+        max_net_loss: (-worst.lp.pnl_with_fees).max(0) as u64,
+        outcomes,
+    })
+}
+
+/// One buy of `amount` into the market as it stands. Prices are of the side
+/// bought, scaled by PRICE_SCALE.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct SlippagePoint {
+    pub amount: u64,
+    pub fee: u64,
+    pub shares: u64,
+    pub price_before: u64,
+    pub price_after: u64,
+    pub impact_bps: u64,
+    /// Collateral the shares claim if their side wins and nobody else trades
+    pub payout_if_win: u64,
+    /// `amount` per unit of `payout_if_win`, scaled by PRICE_SCALE
+    pub effective_price: u64,
+}
+
+/// Quotes each of `sizes` separately against `market`, which is left
+/// untouched.
+pub fn slippage_curve(
+    market: &SimMarket,
+    side: Side,
+    sizes: &[u64],
+) -> Result<Vec<SlippagePoint>, SimError> {
+    let side_price = |yes_price: u64| match side {
+        Side::Yes => yes_price,
+        Side::No => PRICE_SCALE - yes_price,
+    };
+    sizes
+        .iter()
+        .map(|&amount| {
+            let mut after = market.clone();
+            let fill = after.buy("slippage", side, amount)?;
+            let supply = match side {
+                Side::Yes => after.yes_supply,
+                Side::No => after.no_supply,
+            };
+            let payout_if_win = pro_rata(fill.shares, after.vault, supply)?;
+            let price_before = side_price(fill.price_before);
+            let price_after = side_price(fill.price_after);
+            Ok(SlippagePoint {
+                amount,
+                fee: fill.fee.protocol + fill.fee.creator,
+                shares: fill.shares,
+                price_before,
+                price_after,
+                impact_bps: price_move_bps(price_before, price_after),
+                payout_if_win,
+                effective_price: if payout_if_win == 0 {
+                    0
+                } else {
+                    mul_div(amount, PRICE_SCALE, payout_if_win)?
+                },
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SweepPoint {
+    pub fee_bps: u64,
+    pub initial_liquidity: u64,
+    pub rejected: usize,
+    pub halts: usize,
+    pub creator_fee_income: u64,
+    pub worst_outcome: Outcome,
+    pub max_loss: u64,
+    pub max_net_loss: u64,
+}
+
+/// Worst-case subsidy for every fee and seed combination, other params
+/// taken from `base`.
+pub fn sweep(
+    base: MarketParams,
+    fees_bps: &[u64],
+    liquidities: &[u64],
+    orders: &[Order],
+) -> Result<Vec<SweepPoint>, SimError> {
+    let mut points = Vec::new();
+    for &fee_bps in fees_bps {
+        for &initial_liquidity in liquidities {
+            let params = MarketParams {
+                fee_bps,
+                initial_liquidity,
+                ..base
+            };
+            let subsidy = worst_case_subsidy(params, orders)?;
+            let report = &subsidy.outcomes[0];
+            points.push(SweepPoint {
+                fee_bps,
+                initial_liquidity,
+                rejected: report.rejected,
+                halts: report.halts,
+                creator_fee_income: report.creator_fee_income,
+                worst_outcome: subsidy.worst_outcome,
+                max_loss: subsidy.max_loss,
+                max_net_loss: subsidy.max_net_loss,
+            });
+        }
+    }
+    Ok(points)
+}
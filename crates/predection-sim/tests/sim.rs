@@ -0,0 +1,324 @@
+//! Replays small flows whose settlement can be worked out by hand, and
+//! checks that synthetic flows conserve collateral.
+
+use predection_indexer::Store;
+use predection_math::PRICE_SCALE;
+use predection_sim::flow::from_indexer;
+use predection_sim::{
+    replay, simulate, slippage_curve, worst_case_subsidy, MarketParams, Order, Outcome, Side,
+    SimError, SimMarket, SyntheticFlow,
+};
+
+fn params(fee_bps: u64) -> MarketParams {
+    MarketParams {
+        fee_bps,
+        initial_liquidity: 1_000,
+        initial_yes_bps: 5_000,
+        min_trade_size: 10,
+        ..MarketParams::default()
+    }
+}
+
+fn buy(trader: &str, side: Side, amount: u64) -> Order {
+    Order::Buy {
+        trader: trader.to_string(),
+        side,
+        amount,
+        time: 0,
+    }
+}
+
+fn sell(trader: &str, side: Side, shares: u64) -> Order {
+    Order::Sell {
+        trader: trader.to_string(),
+        side,
+        shares,
+        time: 0,
+    }
+}
+
+fn at(time: i64, order: Order) -> Order {
+    match order {
+        Order::Buy {
+            trader,
+            side,
+            amount,
+            ..
+        } => Order::Buy {
+            trader,
+            side,
+            amount,
+            time,
+        },
+        Order::Sell {
+            trader,
+            side,
+            shares,
+            ..
+        } => Order::Sell {
+            trader,
+            side,
+            shares,
+            time,
+        },
+    }
+}
+
+fn synthetic(seed: u64) -> Vec<Order> {
+    SyntheticFlow {
+        orders: 300,
+        traders: 20,
+        informed_share: 0.3,
+        informed_side: Side::No,
+        sell_share: 0.2,
+        mean_size: 200,
+        interval: 30,
+        fee_bps: 250,
+    }
+    .generate(seed)
+}
+
+#[test]
+fn settles_a_single_buy_like_the_program() {
+    let orders = [
+        buy("alice", Side::Yes, 100),
+        // Below min_trade_size
+        buy("bob", Side::No, 9),
+        sell("bob", Side::No, 1),
+    ];
+
+    // 1% of 100 is a 1 unit fee; the protocol's half rounds down to zero
+    let yes = simulate(params(100), &orders, Outcome::Yes).unwrap();
+    assert_eq!((yes.rejected, yes.volume), (2, 100));
+    assert_eq!((yes.creator_fee_income, yes.protocol_fee_income), (1, 0));
+    assert_eq!(yes.final_yes_price, 599 * PRICE_SCALE / 1_099);
+    // 99 of 599 YES shares claim 181 of the 1_099 vault
+    assert_eq!(yes.trader_pnl, 81);
+    assert_eq!(
+        (yes.lp.payout, yes.lp.pnl, yes.lp.pnl_with_fees),
+        (918, -82, -81)
+    );
+    assert_eq!(yes.dust, 0);
+
+    let subsidy = worst_case_subsidy(params(100), &orders).unwrap();
+    assert_eq!(subsidy.worst_outcome, Outcome::Yes);
+    assert_eq!((subsidy.max_loss, subsidy.max_net_loss), (82, 81));
+    let pnls: Vec<_> = subsidy.outcomes.iter().map(|r| r.lp.pnl).collect();
+    assert_eq!(pnls, [-82, 99, 0]);
+}
+
+#[test]
+fn one_sided_markets_only_resolve_invalid() {
+    let params = MarketParams {
+        initial_liquidity: 0,
+        ..params(0)
+    };
+    let orders = [buy("alice", Side::Yes, 500)];
+    let subsidy = worst_case_subsidy(params, &orders).unwrap();
+    assert_eq!(subsidy.outcomes.len(), 1);
+    assert_eq!(subsidy.worst_outcome, Outcome::Invalid);
+    assert_eq!(subsidy.max_loss, 0);
+}
+
+#[test]
+fn synthetic_flows_conserve_collateral() {
+    let params = MarketParams {
+        min_trade_size: 0,
+        ..params(250)
+    };
+    let orders = synthetic(7);
+    assert_eq!(orders, synthetic(7));
+    assert_ne!(orders, synthetic(8));
+    assert!(orders
+        .iter()
+        .any(|order| matches!(order, Order::Sell { .. })));
+
+    for outcome in Outcome::ALL {
+        let report = simulate(params, &orders, outcome).unwrap();
+        // The generator only sells shares its traders hold
+        assert_eq!(report.rejected, 0);
+        let fees = report.creator_fee_income + report.protocol_fee_income;
+        assert_eq!(
+            -(report.lp.pnl + report.trader_pnl),
+            (fees + report.dust) as i64,
+            "{outcome:?}"
+        );
+    }
+
+    // Informed money is on NO, so that's where the seed loses most
+    let subsidy = worst_case_subsidy(params, &orders).unwrap();
+    assert_eq!(subsidy.worst_outcome, Outcome::No);
+    assert!(subsidy.max_net_loss < subsidy.max_loss);
+}
+
+#[test]
+fn slippage_grows_with_size() {
+    let market = SimMarket::new(params(100)).unwrap();
+    let curve = slippage_curve(&market, Side::No, &[10, 100, 1_000, 10_000]).unwrap();
+    assert_eq!(curve[0].price_before, PRICE_SCALE / 2);
+    for pair in curve.windows(2) {
+        assert!(pair[1].price_after > pair[0].price_after);
+        assert!(pair[1].impact_bps > pair[0].impact_bps);
+        assert!(pair[1].effective_price > pair[0].effective_price);
+    }
+    // Quoting leaves the market alone
+    assert_eq!((market.vault, market.no_supply), (1_000, 500));
+}
+
+#[test]
+fn replays_indexed_trades() {
+    let path = std::env::temp_dir().join(format!("predection-sim-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let store = Store::open(&path).unwrap();
+    // Times count from the first trade, as the market row is missing
+    for (trader, kind, side, collateral, shares, slot) in [
+        ("bob", "sell", "yes", 40, 40, 2),
+        ("bob", "buy", "yes", 100, 99, 1),
+        ("carol", "claim", "yes", 10, 10, 3),
+    ] {
+        store
+            .connection()
+            .execute(
+                "INSERT INTO trades (signature, market, trader, kind, side, collateral, shares,
+                    fee, yes_price, slot, block_time)
+                 VALUES ('sig', 'market', ?1, ?2, ?3, ?4, ?5, 0, 0, ?6, 1000 + ?6 * 10)",
+                rusqlite::params![trader, kind, side, collateral, shares, slot],
+            )
+            .unwrap();
+    }
+
+    let orders = from_indexer(&path, "market").unwrap();
+    assert_eq!(
+        orders,
+        [
+            buy("bob", Side::Yes, 100),
+            at(10, sell("bob", Side::Yes, 40)),
+        ]
+    );
+    let replayed = replay(params(100), &orders).unwrap();
+    assert_eq!(replayed.rejected, 0);
+    assert_eq!(replayed.market.traders["bob"].yes, 59);
+    assert!(from_indexer(&path, "other").is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn rejects_seeds_the_program_rejects() {
+    for initial_yes_bps in [0, 10_000, 10_001] {
+        let params = MarketParams {
+            initial_yes_bps,
+            ..params(100)
+        };
+        assert_eq!(
+            SimMarket::new(params).unwrap_err(),
+            SimError::InvalidInitialOdds
+        );
+    }
+    // 1 bps of 1_000 leaves YES without shares
+    let thin = MarketParams {
+        initial_yes_bps: 1,
+        ..params(100)
+    };
+    assert_eq!(SimMarket::new(thin).unwrap_err(), SimError::InvalidAmount);
+    let over_cap = MarketParams {
+        max_open_interest: Some(999),
+        ..params(100)
+    };
+    assert_eq!(
+        SimMarket::new(over_cap).unwrap_err(),
+        SimError::OpenInterestCapExceeded
+    );
+    let zero_cap = MarketParams {
+        max_position_per_wallet: Some(0),
+        ..params(100)
+    };
+    assert_eq!(
+        SimMarket::new(zero_cap).unwrap_err(),
+        SimError::InvalidAmount
+    );
+    let no_cooldown = MarketParams {
+        max_price_move_bps: Some(500),
+        price_move_window: 60,
+        ..params(100)
+    };
+    assert_eq!(
+        SimMarket::new(no_cooldown).unwrap_err(),
+        SimError::InvalidCircuitBreaker
+    );
+}
+
+#[test]
+fn caps_open_interest_and_positions() {
+    let mut market = SimMarket::new(MarketParams {
+        max_open_interest: Some(1_300),
+        max_position_per_wallet: Some(200),
+        ..params(0)
+    })
+    .unwrap();
+
+    market.buy("alice", Side::Yes, 150).unwrap();
+    // Shares on both sides count towards the position
+    assert_eq!(
+        market.buy("alice", Side::No, 51),
+        Err(SimError::PositionLimitExceeded)
+    );
+    market.buy("alice", Side::No, 50).unwrap();
+    market.buy("bob", Side::No, 100).unwrap();
+    assert_eq!(
+        market.buy("carol", Side::Yes, 10),
+        Err(SimError::OpenInterestCapExceeded)
+    );
+    // Selling frees room under both caps
+    market.sell("alice", Side::Yes, 100).unwrap();
+    market.buy("alice", Side::Yes, 50).unwrap();
+    market.buy("carol", Side::Yes, 50).unwrap();
+    assert_eq!(market.vault, 1_300);
+}
+
+#[test]
+fn circuit_breaker_halts_before_the_fill() {
+    let params = MarketParams {
+        max_price_move_bps: Some(1_000),
+        price_move_window: 60,
+        halt_cooldown: 300,
+        breaker_min_liquidity: 500,
+        ..params(0)
+    };
+    let orders = [
+        // YES from 50% to 58.3%
+        at(1, buy("alice", Side::Yes, 200)),
+        // 61.5% is 11.5 points above the opening price, so this halts
+        at(10, buy("bob", Side::Yes, 100)),
+        at(200, buy("bob", Side::No, 100)),
+        // The window no longer reaches the opening price
+        at(310, buy("bob", Side::No, 100)),
+    ];
+    let replayed = replay(params, &orders).unwrap();
+    assert_eq!((replayed.rejected, replayed.halts), (2, 1));
+    assert_eq!(replayed.market.vault, 1_300);
+    assert_eq!(
+        (replayed.market.yes_supply, replayed.market.no_supply),
+        (700, 600)
+    );
+
+    // A move that breaches the threshold over the window trips it even when
+    // each step stays inside
+    let steps: Vec<Order> = (0..4)
+        .map(|step| at((step + 1) * 10, buy("alice", Side::Yes, 90)))
+        .collect();
+    let replayed = replay(params, &steps).unwrap();
+    assert_eq!(replayed.halts, 1);
+    assert_eq!(replayed.market.halted_until, Some(330));
+    // Spread past the window, the same steps fill
+    let spread: Vec<Order> = (0..4)
+        .map(|step| at((step + 1) * 61, buy("alice", Side::Yes, 90)))
+        .collect();
+    assert_eq!(replay(params, &spread).unwrap().halts, 0);
+
+    // Below breaker_min_liquidity shares the pool isn't checked
+    let thin = MarketParams {
+        initial_liquidity: 400,
+        ..params
+    };
+    assert_eq!(replay(thin, &orders[..1]).unwrap().halts, 0);
+}
@@ -0,0 +1,56 @@
+//! Replaceable off-chain implementations of the CPI and return-data
+//! syscalls, in the style of `solana_sysvar::program_stubs`.
+
+use {
+    solana_account_info::AccountInfo,
+    solana_instruction::Instruction,
+    solana_program_error::ProgramResult,
+    solana_pubkey::Pubkey,
+    std::sync::{LazyLock, RwLock},
+};
+
+static SYSCALL_STUBS: LazyLock<RwLock<Box<dyn CpiStubs>>> =
+    LazyLock::new(|| RwLock::new(Box::new(DefaultCpiStubs)));
+
+/// Installs `stubs` process-wide and returns the ones they replace.
+pub fn set_cpi_stubs(stubs: Box<dyn CpiStubs>) -> Box<dyn CpiStubs> {
+    std::mem::replace(&mut SYSCALL_STUBS.write().unwrap(), stubs)
+}
+
+pub trait CpiStubs: Sync + Send {
+    fn sol_invoke_signed(
+        &self,
+        _instruction: &Instruction,
+        _account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Ok(())
+    }
+    fn sol_set_return_data(&self, _data: &[u8]) {}
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        None
+    }
+}
+
+struct DefaultCpiStubs;
+
+impl CpiStubs for DefaultCpiStubs {}
+
+pub(crate) fn sol_invoke_signed(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    SYSCALL_STUBS
+        .read()
+        .unwrap()
+        .sol_invoke_signed(instruction, account_infos, signers_seeds)
+}
+
+pub(crate) fn sol_set_return_data(data: &[u8]) {
+    SYSCALL_STUBS.read().unwrap().sol_set_return_data(data)
+}
+
+pub(crate) fn sol_get_return_data() -> Option<(Pubkey, Vec<u8>)> {
+    SYSCALL_STUBS.read().unwrap().sol_get_return_data()
+}
@@ -0,0 +1,182 @@
+//! Example integrator: buys predection shares through CPI, refusing to fill
+//! if the bought side's price afterwards would be above a caller-chosen
+//! limit. `buy_share` takes no price limit, so this is the guard a vault or
+//! router would put in front of it.
+//!
+//! The trader signs this program's instruction and that signature carries
+//! through the CPI, so the shares, position and ATAs are the trader's. A
+//! program trading from its own treasury would pass a PDA as `signer` and
+//! call through `CpiContext::new_with_signer`.
+//!
+//! The price is built from the accounts with `PriceView::new` rather than
+//! through the `get_price` CPI, which gives the same answer for fewer
+//! compute units.
+//!
+//! A buy the circuit breaker stops succeeds without filling, so the
+//! `TradeOutcome` that `buy_share` returns is checked and a halt fails the
+//! instruction. Failing also undoes the halt; a program that should let it
+//! stand would return Ok and pass the outcome on instead.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use predection::cpi::accounts::BuyShare;
+use predection::instructions::{PriceView, StatusView};
+use predection::math::{quote_buy, yes_price, PRICE_SCALE};
+use predection::program::Predection;
+use predection::states::{Market, TradeOutcome};
+
+declare_id!("6jxXNUUp1k2pKQHU9PaRtEWrotqHmfCnWYqy2jS5cpK4");
+
+#[program]
+pub mod predection_example {
+    use super::*;
+
+    /// Buys `amount` of collateral worth of YES or NO unless the side's
+    /// price after the fill would exceed `max_price`, scaled by PRICE_SCALE.
+    /// Allowlisted markets aren't supported: no proof is passed on.
+    pub fn buy_with_limit(
+        ctx: Context<BuyWithLimit>,
+        amount: u64,
+        is_yes: bool,
+        max_price: u64,
+    ) -> Result<()> {
+        ctx.accounts.buy_with_limit(amount, is_yes, max_price)
+    }
+}
+
+#[error_code]
+pub enum ExampleError {
+    #[msg("Market is not open for trading")]
+    MarketNotTrading,
+    #[msg("Price after the buy would exceed the limit")]
+    PriceAboveLimit,
+    #[msg("Buy could not be quoted")]
+    InvalidQuote,
+    #[msg("Circuit breaker halted the market instead of filling the buy")]
+    TradeHalted,
+}
+
+/// The bought side's price once `amount` has filled: the fee comes off and
+/// the rest mints shares 1:1, as in `buy_share`.
+pub fn price_after_buy(view: &PriceView, amount: u64, is_yes: bool) -> Result<u64> {
+    let shares = quote_buy(amount, view.fee_bps)
+        .map_err(|_| ExampleError::InvalidQuote)?
+        .net_amount;
+    let (yes_supply, no_supply) = if is_yes {
+        (view.yes_supply.checked_add(shares), Some(view.no_supply))
+    } else {
+        (Some(view.yes_supply), view.no_supply.checked_add(shares))
+    };
+    let (Some(yes_supply), Some(no_supply)) = (yes_supply, no_supply) else {
+        return err!(ExampleError::InvalidQuote);
+    };
+    let yes_after = yes_price(yes_supply, no_supply);
+    Ok(if is_yes {
+        yes_after
+    } else {
+        PRICE_SCALE - yes_after
+    })
+}
+
+#[derive(Accounts)]
+pub struct BuyWithLimit<'info> {
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    // Read for the price check; predection checks them again
+    #[account(mut)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(mut, address = market.yes_mint)]
+    pub yes_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, address = market.no_mint)]
+    pub no_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, address = market.market_vault)]
+    pub market_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Passed straight to `buy_share`, which validates them
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub fee_collector_ata: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub protocol_fee_collector_ata: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub creator_profile: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub collateral_mint: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub user_collateral_mint_ata: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub yes_mint_ata: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub no_mint_ata: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub position: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    #[account(mut)]
+    pub price_history: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    pub token_program: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    pub outcome_token_program: UncheckedAccount<'info>,
+    /// CHECK: checked by predection
+    pub associated_token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+
+    pub predection_program: Program<'info, Predection>,
+}
+
+impl<'info> BuyWithLimit<'info> {
+    pub fn buy_with_limit(&self, amount: u64, is_yes: bool, max_price: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            StatusView::new(&self.market, now).trading_open,
+            ExampleError::MarketNotTrading
+        );
+
+        let view = PriceView::new(
+            &self.market,
+            self.yes_mint.supply,
+            self.no_mint.supply,
+            self.market_vault.amount,
+        );
+        let price = price_after_buy(&view, amount, is_yes)?;
+        require_gte!(max_price, price, ExampleError::PriceAboveLimit);
+
+        let accounts = BuyShare {
+            signer: self.signer.to_account_info(),
+            fee_collector_ata: self.fee_collector_ata.to_account_info(),
+            protocol_fee_collector_ata: self.protocol_fee_collector_ata.to_account_info(),
+            market: self.market.to_account_info(),
+            creator_profile: self.creator_profile.to_account_info(),
+            market_vault: self.market_vault.to_account_info(),
+            collateral_mint: self.collateral_mint.to_account_info(),
+            user_collateral_mint_ata: self.user_collateral_mint_ata.to_account_info(),
+            yes_mint: self.yes_mint.to_account_info(),
+            no_mint: self.no_mint.to_account_info(),
+            yes_mint_ata: self.yes_mint_ata.to_account_info(),
+            no_mint_ata: self.no_mint_ata.to_account_info(),
+            position: self.position.to_account_info(),
+            price_history: self.price_history.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            outcome_token_program: self.outcome_token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+        };
+        let ctx = CpiContext::new(self.predection_program.to_account_info(), accounts);
+        let outcome = predection::cpi::buy_share(ctx, amount, is_yes, Vec::new(), false)?.get();
+        match outcome {
+            TradeOutcome::Filled { .. } => Ok(()),
+            TradeOutcome::Halted { .. } => err!(ExampleError::TradeHalted),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Runs the example against predection's test harness, including the
+//! `buy_share` CPI itself.
+
+#[path = "../../predection/tests/common/mod.rs"]
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use common::*;
+use predection::instructions::PriceView;
+use predection::states::{Resolution, Status};
+use predection_example::{accounts, instruction, price_after_buy, ExampleError};
+use predection_sdk::pda;
+
+/// A limit no price can exceed.
+const PRICE_LIMIT_NONE: u64 = u64::MAX;
+
+fn setup() -> (TestEnv, Pubkey, Pubkey) {
+    let mut env = TestEnv::new();
+    let admin = env.admin;
+    env.svm
+        .deploy_program(predection_example::ID, predection_example::entry, admin);
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, USDC, USDC);
+    let trader = env.trader(&market, 10 * USDC);
+    (env, market, trader)
+}
+
+fn buy_with_limit(
+    env: &TestEnv,
+    market: &Pubkey,
+    trader: &Pubkey,
+    amount: u64,
+    is_yes: bool,
+    max_price: u64,
+) -> Instruction {
+    let state = env.market(market);
+    let accounts = accounts::BuyWithLimit {
+        signer: *trader,
+        market: *market,
+        yes_mint: state.yes_mint,
+        no_mint: state.no_mint,
+        market_vault: state.market_vault,
+        fee_collector_ata: state.fee_collector_ata,
+        protocol_fee_collector_ata: state.protocol_fee_collector_ata,
+        creator_profile: pda::creator_profile(&state.authority).0,
+        collateral_mint: state.collateral_mint,
+        user_collateral_mint_ata: env.ata(trader, &state.collateral_mint),
+        yes_mint_ata: env.ata(trader, &state.yes_mint),
+        no_mint_ata: env.ata(trader, &state.no_mint),
+        position: pda::position(market, trader).0,
+        price_history: pda::price_history(market).0,
+        token_program: spl_token::ID,
+        outcome_token_program: state.outcome_token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        predection_program: predection::ID,
+    };
+    Instruction {
+        program_id: predection_example::ID,
+        accounts: accounts.to_account_metas(None),
+        data: instruction::BuyWithLimit {
+            amount,
+            is_yes,
+            max_price,
+        }
+        .data(),
+    }
+}
+
+#[test]
+fn prices_the_buy_like_predection() {
+    let (env, market, _) = setup();
+    let view = PriceView::new(&env.market(&market), USDC, USDC, 2 * USDC);
+
+    // 1% fee leaves 990_000 shares: YES goes to 1.99 / 2.99
+    assert_eq!(price_after_buy(&view, USDC, true).unwrap(), 665_551);
+    assert_eq!(price_after_buy(&view, USDC, false).unwrap(), 665_552);
+    assert_eq!(price_after_buy(&view, 0, true).unwrap(), 500_000);
+}
+
+#[test]
+fn buys_through_predection_within_the_limit() {
+    let (mut env, market, trader) = setup();
+
+    let ix = buy_with_limit(&env, &market, &trader, USDC, true, 665_551);
+    env.svm.send(&[ix], &[trader]).unwrap();
+
+    // Shares and the position belong to the wallet that signed
+    let state = env.market(&market);
+    assert_eq!(env.balance(&trader, &state.yes_mint), 990_000);
+    assert_eq!(env.balance(&trader, &state.collateral_mint), 9 * USDC);
+    assert_eq!(env.svm.mint(&state.yes_mint).supply, USDC + 990_000);
+}
+
+#[test]
+fn refuses_buys_that_would_move_past_the_limit() {
+    let (mut env, market, trader) = setup();
+
+    let ix = buy_with_limit(&env, &market, &trader, USDC, true, 665_550);
+    assert_error(
+        env.svm.send(&[ix], &[trader]),
+        ExampleError::PriceAboveLimit,
+    );
+    let ix = buy_with_limit(&env, &market, &trader, USDC, false, 500_000);
+    assert_error(
+        env.svm.send(&[ix], &[trader]),
+        ExampleError::PriceAboveLimit,
+    );
+}
+
+#[test]
+fn fails_buys_the_circuit_breaker_stops() {
+    let mut env = TestEnv::new();
+    let admin = env.admin;
+    env.svm
+        .deploy_program(predection_example::ID, predection_example::entry, admin);
+    let (_, market) = env.create_market_with(|params| {
+        params.limits.max_price_move_bps = Some(1_000);
+        params.limits.price_move_window = 60;
+        params.limits.halt_cooldown = 600;
+    });
+    env.set_supplies(&market, USDC, USDC);
+    let trader = env.trader(&market, 10 * USDC);
+    // The first trade gives the breaker a price to measure from
+    env.buy(&market, &trader, USDC, false).unwrap();
+
+    // 33% to 50% trips the breaker; failing rolls the halt back with it
+    let ix = buy_with_limit(&env, &market, &trader, USDC, true, PRICE_LIMIT_NONE);
+    assert_error(env.svm.send(&[ix], &[trader]), ExampleError::TradeHalted);
+    assert_eq!(env.market(&market).status, Status::Open);
+    assert_eq!(env.balance(&trader, &env.usdc), 9 * USDC);
+}
+
+#[test]
+fn refuses_markets_that_are_not_trading() {
+    let (mut env, market, trader) = setup();
+    env.set_resolved(&market, Resolution::Yes);
+
+    let ix = buy_with_limit(&env, &market, &trader, USDC, true, PRICE_LIMIT_NONE);
+    assert_error(
+        env.svm.send(&[ix], &[trader]),
+        ExampleError::MarketNotTrading,
+    );
+}
+
+#[test]
+fn checks_accounts_before_calling_predection() {
+    let (mut env, market, trader) = setup();
+    let state = env.market(&market);
+
+    let mut swapped = buy_with_limit(&env, &market, &trader, USDC, true, PRICE_LIMIT_NONE);
+    replace_account(&mut swapped, &state.yes_mint, &state.no_mint);
+    assert_error(
+        env.svm.send(&[swapped], &[trader]),
+        ErrorCode::ConstraintAddress,
+    );
+
+    let impostor = Pubkey::new_unique();
+    let mut wrong_program = buy_with_limit(&env, &market, &trader, USDC, true, PRICE_LIMIT_NONE);
+    replace_account(&mut wrong_program, &predection::ID, &impostor);
+    env.svm.set_account(
+        impostor,
+        Account {
+            executable: true,
+            ..Account::new(Vec::new(), Pubkey::new_unique())
+        },
+    );
+    assert_error(
+        env.svm.send(&[wrong_program], &[trader]),
+        ErrorCode::InvalidProgramId,
+    );
+}
@@ -47,8 +47,70 @@ pub enum Errors {
     DurationTooShort,
     #[msg("The Duration Too Long")]
     DurationTooLong,
-    #[msg("Collateral token not whitelisted. Only USDC is accepted.")]
+    #[msg("Collateral token is not whitelisted")]
     CollateralNotWhitelisted,
     #[msg("Protocol fee collector is invalid")]
     InvalidProtocolFeeCollector,
+    #[msg("Trade would exceed the market's open interest cap")]
+    OpenInterestCapExceeded,
+    #[msg("Trade would exceed the per-wallet position limit")]
+    PositionLimitExceeded,
+    #[msg("Trading is halted by the circuit breaker")]
+    MarketHalted,
+    #[msg("Circuit breaker settings are invalid")]
+    InvalidCircuitBreaker,
+    #[msg("Trader is not on this market's allowlist")]
+    NotOnAllowlist,
+    #[msg("Outcome token metadata requires Token-2022 outcome mints")]
+    MetadataRequiresToken2022,
+    #[msg("The metadata URI is too long")]
+    MetadataUriTooLong,
+    #[msg("Outcome token program does not match the market")]
+    InvalidOutcomeTokenProgram,
+    #[msg("Collateral mint uses an unsupported Token-2022 extension")]
+    UnsupportedCollateralExtension,
+    #[msg("Signer is not the protocol admin")]
+    InvalidProtocolAdmin,
+    #[msg("Program data account does not belong to this program")]
+    InvalidProgramData,
+    #[msg("Trade is below the collateral's minimum size")]
+    TradeTooSmall,
+    #[msg("Native SOL is only accepted when the collateral is wrapped SOL")]
+    CollateralNotNative,
+    #[msg("The Question is not valid UTF-8")]
+    InvalidQuestionEncoding,
+    #[msg("Metadata is locked once the market has traded")]
+    MetadataLocked,
+    #[msg("Metadata field is too long")]
+    MetadataFieldTooLong,
+    #[msg("Category must be 1 to 32 bytes")]
+    InvalidCategory,
+    #[msg("Dispute window must be at most 7 days, and nonzero with a creator bond")]
+    InvalidDisputeWindow,
+    #[msg("Dispute window has closed")]
+    DisputeWindowClosed,
+    #[msg("Market without a creator bond cannot be disputed")]
+    MarketNotDisputable,
+    #[msg("Market is not under dispute")]
+    MarketNotDisputed,
+    #[msg("Resolution is not final yet")]
+    ResolutionNotFinal,
+    #[msg("Creator bond already settled")]
+    BondAlreadySettled,
+    #[msg("Token account does not belong to the disputer")]
+    InvalidDisputer,
+    #[msg("Token account does not belong to the market creator")]
+    InvalidCreatorAta,
+    #[msg("Initial YES odds must be between 1 and 9999 bps")]
+    InvalidInitialOdds,
+    #[msg("wSOL account must be empty to wrap or unwrap SOL")]
+    WrappedSolAccountNotEmpty,
+    #[msg("Resolver's grace period has not ended")]
+    MarketNotExpired,
+}
+
+impl From<predection_math::MathError> for Errors {
+    fn from(_: predection_math::MathError) -> Self {
+        Errors::ErrorInCalculating
+    }
 }
@@ -1,14 +1,21 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Transfer},
+};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{self, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{
+        self, Mint, MintTo, SyncNative, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
 use crate::errors::Errors;
-use crate::states::{Market, Status};
+use crate::instructions::wsol;
+use crate::math::{quote_buy, FeeSplit};
+use crate::merkle::verify_allowlist_proof;
+use crate::states::{CreatorProfile, Market, Position, PriceHistory, Status, TradeOutcome};
 
 #[derive(Accounts)]
-#[instruction(amount: u64, _market_id: u64, is_yes: bool)]
 pub struct BuyShare<'info> {
     #[account(mut)]
     signer: Signer<'info>,
@@ -33,18 +40,26 @@ pub struct BuyShare<'info> {
 
     //predection-market
     //  OPTIMIZATION: Box reduces stack usage by heap-allocating large structs
-    #[account(seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],bump)]
+    #[account(mut, seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],bump)]
     pub market: Box<Account<'info, Market>>,
 
+    #[account(mut, seeds = [b"creator", market.authority.as_ref()], bump = creator_profile.bump)]
+    pub creator_profile: Box<Account<'info, CreatorProfile>>,
+
     #[account(mut, constraint = market_vault.key() == market.market_vault @ Errors::InvalidVault)]
     pub market_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut, constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
     pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    // User's USDC ATA (they pay from here)
-    #[account(mut,
-        constraint = user_collateral_mint_ata.mint == collateral_mint.key() @ Errors::InvalidMint
+    // User's USDC ATA (they pay from here). Created on demand so native SOL
+    // buyers can wrap into it; it must hold no wSOL of their own, since it is
+    // closed after the trade.
+    #[account(init_if_needed,
+        payer = signer,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
     )]
     pub user_collateral_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -65,7 +80,7 @@ pub struct BuyShare<'info> {
         payer= signer,
          associated_token::mint = yes_mint,
     associated_token::authority = signer,
-    associated_token::token_program = token_program
+    associated_token::token_program = outcome_token_program
     )]
     pub yes_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -74,21 +89,47 @@ pub struct BuyShare<'info> {
         payer=signer,
          associated_token::mint = no_mint,
     associated_token::authority = signer,
-    associated_token::token_program = token_program
+    associated_token::token_program = outcome_token_program
     )]
     pub no_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    #[account(init_if_needed,
+        payer = signer,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(mut, seeds = [b"price_history", market.key().as_ref()], bump = price_history.load()?.bump)]
+    pub price_history: AccountLoader<'info, PriceHistory>,
+
     pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        constraint = outcome_token_program.key() == market.outcome_token_program @ Errors::InvalidOutcomeTokenProgram
+    )]
+    pub outcome_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> BuyShare<'info> {
-    pub fn buy_share(&mut self, amount: u64, is_yes: bool) -> Result<()> {
-        let signer_bal = self.user_collateral_mint_ata.amount;
-        println!("Signer collateral_mint balance:  {}", signer_bal);
+    pub fn buy_share(
+        &mut self,
+        amount: u64,
+        is_yes: bool,
+        proof: &[[u8; 32]],
+        wrap_sol: bool,
+        position_bump: u8,
+    ) -> Result<TradeOutcome> {
+        if let Some(root) = self.market.allowlist_root {
+            require!(
+                verify_allowlist_proof(&root, proof, &self.signer.key()),
+                Errors::NotOnAllowlist
+            );
+        }
 
-        require_gte!(signer_bal, amount, Errors::ErrorInvalidAmount);
+        require_gte!(amount, self.market.min_trade_size, Errors::TradeTooSmall);
 
         // let clock = Clock::get()?;
 
@@ -97,13 +138,50 @@ impl<'info> BuyShare<'info> {
         //            Errors::MarketFinished
         //        );
 
+        let now = Clock::get()?.unix_timestamp;
+        self.market.reopen_if_cooled_down(now);
+        require!(
+            !matches!(self.market.status, Status::Halted),
+            Errors::MarketHalted
+        );
         require!(
             matches!(self.market.status, Status::Open),
             Errors::InvalidMarketStatus
         );
 
-        // Calculate fee (fee is in BPS: 100 = 1%)
-        let fee_amount = (amount * self.market.fee) / 10000;
+        let quote = quote_buy(amount, self.market.fee).map_err(Errors::from)?;
+        let net_amount = quote.net_amount;
+
+        self.check_limits(net_amount)?;
+        // The net deposit is an upper bound on the shares the trade mints
+        let before = (self.yes_mint.supply, self.no_mint.supply);
+        let after = if is_yes {
+            (before.0.checked_add(net_amount), Some(before.1))
+        } else {
+            (Some(before.0), before.1.checked_add(net_amount))
+        };
+        let (Some(yes_after), Some(no_after)) = after else {
+            return err!(Errors::ErrorInCalculating);
+        };
+        let tripped = {
+            let price_history = self.price_history.load()?;
+            self.market
+                .trip_circuit_breaker(&price_history, before, (yes_after, no_after), now)
+        };
+        if tripped {
+            return Ok(TradeOutcome::Halted {
+                halted_until: self.market.halted_until,
+            });
+        }
+
+        if wrap_sol {
+            self.wrap_sol(amount)?;
+        }
+
+        let signer_bal = self.user_collateral_mint_ata.amount;
+        println!("Signer collateral_mint balance:  {}", signer_bal);
+
+        require_gte!(signer_bal, amount, Errors::ErrorInvalidAmount);
 
         // Transfer full amount to market vault
         let ctx_acc = TransferChecked {
@@ -115,12 +193,19 @@ impl<'info> BuyShare<'info> {
 
         let ctx = CpiContext::new(self.token_program.to_account_info(), ctx_acc);
 
-        let net_amount = amount
-            .checked_sub(fee_amount)
-            .ok_or(Errors::ErrorInvalidAmount)?;
-
+        let vault_before = self.market_vault.amount;
         token_interface::transfer_checked(ctx, net_amount, self.collateral_mint.decimals)?;
 
+        // Token-2022 transfer fees are withheld from what the vault receives,
+        // so shares are minted against the measured deposit, not `net_amount`
+        self.market_vault.reload()?;
+        let received = self
+            .market_vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(Errors::ErrorInCalculating)?;
+        require!(received > 0, Errors::ErrorInvalidAmount);
+
         if is_yes {
             let mint_acc = MintTo {
                 mint: self.yes_mint.to_account_info(),
@@ -136,10 +221,10 @@ impl<'info> BuyShare<'info> {
             ];
             let signer_seeds = &[&seeds[..]];
 
-            let ctx = CpiContext::new(self.token_program.to_account_info(), mint_acc)
+            let ctx = CpiContext::new(self.outcome_token_program.to_account_info(), mint_acc)
                 .with_signer(signer_seeds);
 
-            token_interface::mint_to(ctx, net_amount)?;
+            token_interface::mint_to(ctx, received)?;
         } else {
             let mint_acc = MintTo {
                 mint: self.no_mint.to_account_info(),
@@ -155,12 +240,23 @@ impl<'info> BuyShare<'info> {
             ];
             let signer_seeds = &[&seeds[..]];
 
-            let ctx = CpiContext::new(self.token_program.to_account_info(), mint_acc)
+            let ctx = CpiContext::new(self.outcome_token_program.to_account_info(), mint_acc)
                 .with_signer(signer_seeds);
 
-            token_interface::mint_to(ctx, net_amount)?;
+            token_interface::mint_to(ctx, received)?;
         }
 
+        self.update_position(received, is_yes, position_bump)?;
+        self.yes_mint.reload()?;
+        self.no_mint.reload()?;
+        self.market.record_trade(
+            &mut self.creator_profile,
+            &mut *self.price_history.load_mut()?,
+            (self.yes_mint.supply, self.no_mint.supply),
+            received,
+            now,
+        );
+
         // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
         // 💰 FEE DISTRIBUTION: 50% Protocol + 50% Market Creator
         // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
@@ -173,10 +269,12 @@ impl<'info> BuyShare<'info> {
         //   - 50% goes to market creator (rewards quality markets)
         //   - Reduces scam incentive while still rewarding creators
         // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+        let fee_amount = quote.fee.protocol + quote.fee.creator;
         if fee_amount > 0 {
-            // Calculate 50/50 split
-            let protocol_fee = fee_amount / 2; // 50% to protocol
-            let creator_fee = fee_amount - protocol_fee; // Remaining 50% to creator (handles odd numbers)
+            let FeeSplit {
+                protocol: protocol_fee,
+                creator: creator_fee,
+            } = quote.fee;
 
             // Transfer 50% to protocol treasury
             let ctx_protocol = TransferChecked {
@@ -204,6 +302,89 @@ impl<'info> BuyShare<'info> {
             msg!("  Creator (50%): {}", creator_fee);
         }
 
+        // The account started empty and the whole amount was spent, so
+        // closing it only hands the rent back
+        if wrap_sol {
+            wsol::unwrap(
+                self.token_program.to_account_info(),
+                self.user_collateral_mint_ata.to_account_info(),
+                self.signer.to_account_info(),
+            )?;
+        }
+
+        Ok(TradeOutcome::Filled { shares: received })
+    }
+
+    /// Moves `amount` lamports into the signer's wSOL account so the trade can
+    /// spend them like any other collateral. The account is closed afterwards.
+    fn wrap_sol(&mut self, amount: u64) -> Result<()> {
+        wsol::check_unwrap(&self.collateral_mint, &self.user_collateral_mint_ata)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.signer.to_account_info(),
+                    to: self.user_collateral_mint_ata.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        token_interface::sync_native(CpiContext::new(
+            self.token_program.to_account_info(),
+            SyncNative {
+                account: self.user_collateral_mint_ata.to_account_info(),
+            },
+        ))?;
+        self.user_collateral_mint_ata.reload()?;
+        Ok(())
+    }
+
+    fn check_limits(&self, net_amount: u64) -> Result<()> {
+        if let Some(max_open_interest) = self.market.max_open_interest {
+            let open_interest = self
+                .market_vault
+                .amount
+                .checked_add(net_amount)
+                .ok_or(Errors::ErrorInCalculating)?;
+            require_gte!(
+                max_open_interest,
+                open_interest,
+                Errors::OpenInterestCapExceeded
+            );
+        }
+
+        if let Some(max_position) = self.market.max_position_per_wallet {
+            let position = self
+                .position
+                .total_shares()
+                .checked_add(net_amount)
+                .ok_or(Errors::ErrorInCalculating)?;
+            require_gte!(max_position, position, Errors::PositionLimitExceeded);
+        }
+        Ok(())
+    }
+
+    fn update_position(&mut self, shares: u64, is_yes: bool, bump: u8) -> Result<()> {
+        let market_key = self.market.key();
+        let position = &mut self.position;
+        position.ensure_initialized(market_key, self.signer.key(), bump);
+
+        if is_yes {
+            position.yes_shares = position
+                .yes_shares
+                .checked_add(shares)
+                .ok_or(Errors::ErrorInCalculating)?;
+        } else {
+            position.no_shares = position
+                .no_shares
+                .checked_add(shares)
+                .ok_or(Errors::ErrorInCalculating)?;
+        }
+        position.collateral_deposited = position
+            .collateral_deposited
+            .checked_add(shares)
+            .ok_or(Errors::ErrorInCalculating)?;
         Ok(())
     }
 }
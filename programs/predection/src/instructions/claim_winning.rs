@@ -1,5 +1,7 @@
 use crate::errors::Errors;
-use crate::states::{Market, Status};
+use crate::instructions::wsol;
+use crate::math::pro_rata;
+use crate::states::{Market, Position, Status};
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
 use anchor_spl::token_interface::{
@@ -23,10 +25,12 @@ pub struct ClaimWinning<'info> {
     #[account(mut, constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
     pub collateral_mint: InterfaceAccount<'info, Mint>,
 
-    #[account(mut,
+    #[account(init_if_needed,
+        payer = signer,
         associated_token::mint = collateral_mint,
-        associated_token::authority = signer)]
-    pub user_collateral_ata: InterfaceAccount<'info, TokenAccount>,
+        associated_token::authority = signer,
+        associated_token::token_program = token_program)]
+    pub user_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut, seeds = [b"yes_mint", market.key().as_ref()],         bump, constraint=market.yes_mint == yes_mint.key() @ Errors::InvalidMint)]
     pub yes_mint: InterfaceAccount<'info, Mint>,
@@ -36,28 +40,60 @@ pub struct ClaimWinning<'info> {
     pub no_mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut,  associated_token::mint = yes_mint,
-        associated_token::authority = signer,)]
+        associated_token::authority = signer,
+        associated_token::token_program = outcome_token_program)]
     pub yes_mint_ata: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut,  associated_token::mint = no_mint,
-        associated_token::authority = signer,)]
+        associated_token::authority = signer,
+        associated_token::token_program = outcome_token_program)]
     pub no_mint_ata: InterfaceAccount<'info, TokenAccount>,
 
+    #[account(init_if_needed,
+        payer = signer,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
     pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        constraint = outcome_token_program.key() == market.outcome_token_program @ Errors::InvalidOutcomeTokenProgram
+    )]
+    pub outcome_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> ClaimWinning<'info> {
-    pub fn claim_winning(&mut self, _yes_bump: u8, _no_bump: u8) -> Result<()> {
+    pub fn claim_winning(
+        &mut self,
+        _yes_bump: u8,
+        _no_bump: u8,
+        position_bump: u8,
+        unwrap_sol: bool,
+    ) -> Result<()> {
         require!(
             matches!(self.market.status, Status::Resolved),
             Errors::MarketIsSettled
         );
+        // Payouts wait out the dispute window so an overturned result can't be claimed
+        require!(
+            self.market.is_final(Clock::get()?.unix_timestamp),
+            Errors::ResolutionNotFinal
+        );
+        if unwrap_sol {
+            wsol::check_unwrap(&self.collateral_mint, &self.user_collateral_ata)?;
+        }
 
         let user_yes_token_amount = self.yes_mint_ata.amount;
         let user_no_token_amount = self.no_mint_ata.amount;
 
+        let market_key = self.market.key();
+        self.position
+            .ensure_initialized(market_key, self.signer.key(), position_bump);
+
         let seeds = &[
             b"market",
             self.market.authority.as_ref(),
@@ -75,7 +111,8 @@ impl<'info> ClaimWinning<'info> {
             };
             let winning_supply = self.yes_mint.supply;
 
-            let cpi_ctx = CpiContext::new(self.token_program.to_account_info(), ctx_burn_acc);
+            let cpi_ctx =
+                CpiContext::new(self.outcome_token_program.to_account_info(), ctx_burn_acc);
             token_interface::burn(cpi_ctx, user_yes_token_amount)?;
 
             let ctx_acc = TransferChecked {
@@ -89,13 +126,12 @@ impl<'info> ClaimWinning<'info> {
                 .with_signer(signer_seeds);
 
             let vault_amount = self.market_vault.amount;
-            let amount_supply = user_yes_token_amount
-                .checked_mul(vault_amount)
-                .ok_or(Errors::ErrorInCalculating)?
-                .checked_div(winning_supply)
-                .ok_or(Errors::ErrorInCalculating)?;
+            let amount_supply = pro_rata(user_yes_token_amount, vault_amount, winning_supply)
+                .map_err(Errors::from)?;
 
             token_interface::transfer_checked(ctx, amount_supply, self.collateral_mint.decimals)?;
+            self.position
+                .remove_shares(user_yes_token_amount, true, amount_supply);
         } else if self.market.option == Some(false) {
             require!(user_no_token_amount != 0, Errors::TokenAmountIsZero);
 
@@ -105,7 +141,8 @@ impl<'info> ClaimWinning<'info> {
                 authority: self.signer.to_account_info(),
             };
 
-            let ctx_burn = CpiContext::new(self.token_program.to_account_info(), ctx_burn_acc);
+            let ctx_burn =
+                CpiContext::new(self.outcome_token_program.to_account_info(), ctx_burn_acc);
 
             token_interface::burn(ctx_burn, self.no_mint_ata.amount)?;
 
@@ -122,14 +159,67 @@ impl<'info> ClaimWinning<'info> {
             let winning_supply = self.no_mint.supply;
 
             let vault_amount = self.market_vault.amount;
-            let amount_supply = user_no_token_amount
-                .checked_mul(vault_amount)
-                .ok_or(Errors::ErrorInCalculating)?
-                .checked_div(winning_supply)
+            let amount_supply = pro_rata(user_no_token_amount, vault_amount, winning_supply)
+                .map_err(Errors::from)?;
+
+            token_interface::transfer_checked(ctx, amount_supply, self.collateral_mint.decimals)?;
+            self.position
+                .remove_shares(user_no_token_amount, false, amount_supply);
+        } else {
+            // Resolved INVALID: every share redeems for the same slice of the vault
+            let total_shares = user_yes_token_amount
+                .checked_add(user_no_token_amount)
                 .ok_or(Errors::ErrorInCalculating)?;
+            require!(total_shares != 0, Errors::TokenAmountIsZero);
 
+            let total_supply = self
+                .yes_mint
+                .supply
+                .checked_add(self.no_mint.supply)
+                .ok_or(Errors::ErrorInCalculating)?;
+            let amount_supply = pro_rata(total_shares, self.market_vault.amount, total_supply)
+                .map_err(Errors::from)?;
+
+            for (mint, from, amount) in [
+                (&self.yes_mint, &self.yes_mint_ata, user_yes_token_amount),
+                (&self.no_mint, &self.no_mint_ata, user_no_token_amount),
+            ] {
+                if amount == 0 {
+                    continue;
+                }
+                let ctx_burn_acc = Burn {
+                    mint: mint.to_account_info(),
+                    from: from.to_account_info(),
+                    authority: self.signer.to_account_info(),
+                };
+                token_interface::burn(
+                    CpiContext::new(self.outcome_token_program.to_account_info(), ctx_burn_acc),
+                    amount,
+                )?;
+            }
+
+            let ctx_acc = TransferChecked {
+                mint: self.collateral_mint.to_account_info(),
+                from: self.market_vault.to_account_info(),
+                to: self.user_collateral_ata.to_account_info(),
+                authority: self.market.to_account_info(),
+            };
+            let ctx = CpiContext::new(self.token_program.to_account_info(), ctx_acc)
+                .with_signer(signer_seeds);
             token_interface::transfer_checked(ctx, amount_supply, self.collateral_mint.decimals)?;
+
+            self.position.remove_shares(user_yes_token_amount, true, 0);
+            self.position
+                .remove_shares(user_no_token_amount, false, amount_supply);
         }
+        if unwrap_sol {
+            wsol::unwrap(
+                self.token_program.to_account_info(),
+                self.user_collateral_ata.to_account_info(),
+                self.signer.to_account_info(),
+            )?;
+        }
+
         Ok(())
     }
 }
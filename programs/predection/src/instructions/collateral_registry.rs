@@ -0,0 +1,96 @@
+use crate::errors::Errors;
+use crate::states::{CollateralInfo, ProtocolConfig, MAX_DISPUTE_WINDOW};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+/// A bonded market must stay disputable for a while, or its creator's own
+/// resolver would be the only check on whether the bond is slashed.
+fn validate_dispute_window(creator_bond: u64, dispute_window: i64) -> Result<()> {
+    require!(
+        (0..=MAX_DISPUTE_WINDOW).contains(&dispute_window)
+            && (creator_bond == 0 || dispute_window > 0),
+        Errors::InvalidDisputeWindow
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AddCollateral<'info> {
+    #[account(mut, constraint = admin.key() == config.admin @ Errors::InvalidProtocolAdmin)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    pub collateral_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CollateralInfo::INIT_SPACE,
+        seeds = [b"collateral", collateral_mint.key().as_ref()],
+        bump
+    )]
+    pub collateral_info: Account<'info, CollateralInfo>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AddCollateral<'info> {
+    pub fn add_collateral(
+        &mut self,
+        min_trade_size: u64,
+        max_fee: u64,
+        creator_bond: u64,
+        dispute_window: i64,
+        bump: u8,
+    ) -> Result<()> {
+        require_gte!(1000, max_fee, Errors::FeeIsTooHigh);
+        validate_dispute_window(creator_bond, dispute_window)?;
+
+        self.collateral_info.mint = self.collateral_mint.key();
+        self.collateral_info.decimals = self.collateral_mint.decimals;
+        self.collateral_info.min_trade_size = min_trade_size;
+        self.collateral_info.max_fee = max_fee;
+        self.collateral_info.creator_bond = creator_bond;
+        self.collateral_info.dispute_window = dispute_window;
+        self.collateral_info.enabled = true;
+        self.collateral_info.bump = bump;
+
+        msg!("✅ Collateral whitelisted: {}", self.collateral_mint.key());
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateCollateral<'info> {
+    #[account(constraint = admin.key() == config.admin @ Errors::InvalidProtocolAdmin)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, ProtocolConfig>,
+
+    #[account(mut, seeds = [b"collateral", collateral_info.mint.as_ref()], bump = collateral_info.bump)]
+    pub collateral_info: Account<'info, CollateralInfo>,
+}
+
+impl<'info> UpdateCollateral<'info> {
+    pub fn update_collateral(
+        &mut self,
+        min_trade_size: u64,
+        max_fee: u64,
+        creator_bond: u64,
+        dispute_window: i64,
+        enabled: bool,
+    ) -> Result<()> {
+        require_gte!(1000, max_fee, Errors::FeeIsTooHigh);
+        validate_dispute_window(creator_bond, dispute_window)?;
+
+        self.collateral_info.min_trade_size = min_trade_size;
+        self.collateral_info.max_fee = max_fee;
+        self.collateral_info.creator_bond = creator_bond;
+        self.collateral_info.dispute_window = dispute_window;
+        self.collateral_info.enabled = enabled;
+        Ok(())
+    }
+}
@@ -1,24 +1,63 @@
 use crate::{
     errors::Errors,
-    states::{Market, Status},
+    math::split_by_odds,
+    states::{
+        yes_price, CategoryRegistry, CollateralInfo, CreatorProfile, InitialLiquidity, Market,
+        MarketLimits, MarketRegistry, Position, PriceHistory, ProtocolConfig, RegistryPage, Status,
+        MAX_CATEGORY_LEN, REGISTRY_PAGE_SIZE,
+    },
+};
+use anchor_lang::{
+    prelude::*,
+    system_program::{self, Allocate, Assign, CreateAccount, Transfer},
 };
-use anchor_lang::prelude::*;
 use anchor_spl::{
-    associated_token::AssociatedToken,
-    token_interface::{Mint, TokenAccount, TokenInterface},
+    associated_token::{self, AssociatedToken},
+    token_2022::spl_token_2022::{
+        self,
+        extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    },
+    token_interface::{
+        self, spl_pod::optional_keys::OptionalNonZeroPubkey,
+        spl_token_metadata_interface::state::TokenMetadata, InitializeMint2,
+        MetadataPointerInitialize, Mint, MintTo, TokenAccount, TokenInterface,
+        TokenMetadataInitialize, TransferChecked,
+    },
 };
 
 #[derive(Accounts)]
-#[instruction(resolver: Pubkey, market_id: u64)]
+#[instruction(
+    resolver: Pubkey,
+    question: Vec<u8>,
+    duration_time: i64,
+    fee: u64,
+    limits: MarketLimits,
+    allowlist_root: Option<[u8; 32]>,
+    metadata_uri: Option<String>,
+    category: String,
+)]
 pub struct CreateMarket<'info> {
     #[account(mut)]
     pub market_creator: Signer<'info>,
 
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + CreatorProfile::INIT_SPACE,
+        seeds = [b"creator", market_creator.key().as_ref()],
+        bump
+    )]
+    pub creator_profile: Box<Account<'info, CreatorProfile>>,
+
     #[account(
         init,
         payer = market_creator,
         space = 8 + Market::INIT_SPACE,
-        seeds = [b"market", market_creator.key().as_ref(), &market_id.to_le_bytes()],
+        seeds = [
+            b"market",
+            market_creator.key().as_ref(),
+            &creator_profile.next_market_id.to_le_bytes()
+        ],
         bump
     )]
     pub market: Box<Account<'info, Market>>,
@@ -26,49 +65,159 @@ pub struct CreateMarket<'info> {
     /// External mint (USDC or any SPL token)  
     pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
+    #[account(seeds = [b"collateral", collateral_mint.key().as_ref()], bump = collateral_info.bump)]
+    pub collateral_info: Box<Account<'info, CollateralInfo>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
     #[account(mut, 
    constraint = fee_collector_colletral_ata.mint == collateral_mint.key() @ Errors::InvalidMarketFeeCollectorAta,
     )]
     pub fee_collector_colletral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// CHECK:this is the protocol fee collector  
+    #[account(mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = market_creator,
+        associated_token::token_program = token_program
+    )]
+    pub creator_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Creator bond, returned on clean settlement and slashed otherwise
+    #[account(
+        init,
+        payer = market_creator,
+        token::mint = collateral_mint,
+        token::authority = market,
+        token::token_program = token_program,
+        seeds = [b"bond_vault", market.key().as_ref()],
+        bump
+    )]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: must be the protocol treasury; only its address is used
+    #[account(constraint = protocol_fee_collector.key() == config.treasury @ Errors::InvalidProtocolFeeCollector)]
     pub protocol_fee_collector: UncheckedAccount<'info>,
 
     #[account(mut,
         constraint = protocol_fee_collector_ata.mint == collateral_mint.key() @ Errors::InvalidProtocolFeeCollector,
+        constraint = protocol_fee_collector_ata.owner == config.treasury @ Errors::InvalidProtocolFeeCollector,
     )]
     pub protocol_fee_collector_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    /// CHECK: created in the handler so Token-2022 extensions can be optional
+    #[account(mut, seeds = [b"yes_mint", market.key().as_ref()], bump)]
+    pub yes_mint: UncheckedAccount<'info>,
+
+    /// CHECK: created in the handler so Token-2022 extensions can be optional
+    #[account(mut, seeds = [b"no_mint", market.key().as_ref()], bump)]
+    pub no_mint: UncheckedAccount<'info>,
+
     #[account(
         init,
         payer = market_creator,
-        mint::decimals = 6,
-        mint::authority = market,
-        seeds = [b"yes_mint", market.key().as_ref()],
-        bump
+        associated_token::mint = collateral_mint,
+        associated_token::authority = market,
+        associated_token::token_program = token_program
     )]
-    pub yes_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub market_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init,
         payer = market_creator,
-        mint::decimals = 6,
-        mint::authority = market,
-        seeds = [b"no_mint", market.key().as_ref()],
+        space = 8 + std::mem::size_of::<PriceHistory>(),
+        seeds = [b"price_history", market.key().as_ref()],
         bump
     )]
-    pub no_mint: Box<InterfaceAccount<'info, Mint>>,
+    pub price_history: AccountLoader<'info, PriceHistory>,
 
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  REGISTRY: global creation order + per-category index
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + MarketRegistry::INIT_SPACE,
+        seeds = [b"registry"],
+        bump
+    )]
+    pub registry: Box<Account<'info, MarketRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + RegistryPage::INIT_SPACE,
+        seeds = [
+            b"registry_page".as_ref(),
+            &(registry.total_markets / REGISTRY_PAGE_SIZE).to_le_bytes()
+        ],
+        bump
+    )]
+    pub registry_page: Box<Account<'info, RegistryPage>>,
+
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + CategoryRegistry::INIT_SPACE,
+        seeds = [b"category", category.as_bytes()],
+        bump
+    )]
+    pub category_registry: Box<Account<'info, CategoryRegistry>>,
+
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + RegistryPage::INIT_SPACE,
+        seeds = [
+            b"category_page".as_ref(),
+            category.as_bytes(),
+            &(category_registry.total_markets / REGISTRY_PAGE_SIZE).to_le_bytes()
+        ],
+        bump
+    )]
+    pub category_page: Box<Account<'info, RegistryPage>>,
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  INITIAL LIQUIDITY: creator's seeded YES/NO shares
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
     #[account(
         init,
         payer = market_creator,
-        associated_token::mint = collateral_mint,
-        associated_token::authority = market,
-        associated_token::token_program = token_program
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), market_creator.key().as_ref()],
+        bump
     )]
-    pub market_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub creator_position: Box<Account<'info, Position>>,
+
+    /// CHECK: creator's YES ATA, created in the handler once yes_mint exists
+    #[account(
+        mut,
+        seeds = [
+            market_creator.key().as_ref(),
+            outcome_token_program.key().as_ref(),
+            yes_mint.key().as_ref()
+        ],
+        seeds::program = associated_token_program.key(),
+        bump
+    )]
+    pub creator_yes_ata: UncheckedAccount<'info>,
+
+    /// CHECK: creator's NO ATA, created in the handler once no_mint exists
+    #[account(
+        mut,
+        seeds = [
+            market_creator.key().as_ref(),
+            outcome_token_program.key().as_ref(),
+            no_mint.key().as_ref()
+        ],
+        seeds::program = associated_token_program.key(),
+        bump
+    )]
+    pub creator_no_ata: UncheckedAccount<'info>,
 
     pub token_program: Interface<'info, TokenInterface>,
+    // Token program for yes_mint / no_mint; may differ from the collateral's
+    pub outcome_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
@@ -77,31 +226,45 @@ impl<'info> CreateMarket<'info> {
     pub fn create_market(
         &mut self,
         resolver: Pubkey,
-
-        market_id: u64,
         question: String,
         duration_time: i64,
         fee: u64,
-        bump: u8,
+        limits: MarketLimits,
+        allowlist_root: Option<[u8; 32]>,
+        metadata_uri: Option<String>,
+        category: String,
+        initial_liquidity: Option<InitialLiquidity>,
+        bumps: &CreateMarketBumps,
     ) -> Result<()> {
-        self.market.market_id = market_id;
-        self.market.bump = bump;
-
-        // USDC on Mainnet-Beta
-        const USDC_MAINNET: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
-        // USDC on Devnet (from Solana token faucet)
-        const USDC_DEVNET: &str = "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU";
+        let profile = &mut self.creator_profile;
+        profile.creator = self.market_creator.key();
+        profile.bump = bumps.creator_profile;
+        self.market.market_id = profile.next_market_id;
+        self.market.bump = bumps.market;
+        profile.next_market_id = profile
+            .next_market_id
+            .checked_add(1)
+            .ok_or(Errors::ErrorInCalculating)?;
+        profile.markets_created = profile
+            .markets_created
+            .checked_add(1)
+            .ok_or(Errors::ErrorInCalculating)?;
 
         let collateral_key = self.collateral_mint.key();
 
-        // TODO: Re-enable for production deployment
-        // Check if collateral_mint is in the whitelist
-        // let usdc_mainnet = USDC_MAINNET.parse::<Pubkey>().unwrap();
-        // let usdc_devnet = USDC_DEVNET.parse::<Pubkey>().unwrap();
-        // require!(
-        //     collateral_key == usdc_mainnet || collateral_key == usdc_devnet,
-        //     Errors::CollateralNotWhitelisted
-        // );
+        // Collateral must be approved by the protocol admin (USDC, USDT, PYUSD, wSOL, ...)
+        require!(
+            self.collateral_info.enabled,
+            Errors::CollateralNotWhitelisted
+        );
+        require!(
+            self.collateral_info.decimals == self.collateral_mint.decimals,
+            Errors::InvalidMint
+        );
+        require_gte!(self.collateral_info.max_fee, fee, Errors::FeeIsTooHigh);
+        self.market.min_trade_size = self.collateral_info.min_trade_size;
+
+        self.validate_collateral_extensions()?;
 
         msg!("✅ Collateral mint: {}", collateral_key);
 
@@ -141,6 +304,409 @@ impl<'info> CreateMarket<'info> {
         require!(question.len() <= 64, Errors::QuestionTooLong);
 
         self.market.question = question;
+
+        // Zero would block every trade, so treat it as a mistake
+        require!(
+            limits.max_open_interest != Some(0) && limits.max_position_per_wallet != Some(0),
+            Errors::ErrorInvalidAmount
+        );
+        self.market.max_open_interest = limits.max_open_interest;
+        self.market.max_position_per_wallet = limits.max_position_per_wallet;
+
+        self.market.max_price_move_bps = limits.max_price_move_bps;
+        self.market.price_move_window = limits.price_move_window;
+        self.market.halt_cooldown = limits.halt_cooldown;
+        self.market.breaker_min_liquidity = limits.breaker_min_liquidity;
+        require!(
+            self.market
+                .circuit_breaker()
+                .is_none_or(|breaker| breaker.is_valid()),
+            Errors::InvalidCircuitBreaker
+        );
+
+        // Private markets: only allowlisted wallets can buy, anyone can exit
+        self.market.allowlist_root = allowlist_root;
+
+        self.market.dispute_window = self.collateral_info.dispute_window;
+        self.lock_creator_bond()?;
+
+        // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+        // OUTCOME MINTS: plain SPL, or Token-2022 with on-chain metadata
+        // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+        let outcome_token_program = self.outcome_token_program.key();
+        require!(
+            metadata_uri.is_none() || outcome_token_program == spl_token_2022::ID,
+            Errors::MetadataRequiresToken2022
+        );
+        if let Some(uri) = &metadata_uri {
+            require!(uri.len() <= 200, Errors::MetadataUriTooLong);
+        }
+        self.market.outcome_token_program = outcome_token_program;
+
+        let question = self.market.question.clone();
+        let yes_metadata = metadata_uri.as_ref().map(|uri| {
+            (
+                format!("YES – {}", question),
+                "YES".to_string(),
+                uri.clone(),
+            )
+        });
+        let no_metadata =
+            metadata_uri.map(|uri| (format!("NO – {}", question), "NO".to_string(), uri));
+
+        require!(
+            !category.is_empty() && category.len() <= MAX_CATEGORY_LEN,
+            Errors::InvalidCategory
+        );
+        self.register_market(category, bumps)?;
+
+        let yes_mint = self.yes_mint.to_account_info();
+        self.create_outcome_mint(&yes_mint, b"yes_mint", bumps.yes_mint, yes_metadata)?;
+        let no_mint = self.no_mint.to_account_info();
+        self.create_outcome_mint(&no_mint, b"no_mint", bumps.no_mint, no_metadata)?;
+
+        let market_key = self.market.key();
+        self.creator_position.ensure_initialized(
+            market_key,
+            self.market_creator.key(),
+            bumps.creator_position,
+        );
+        let opening_sample = match initial_liquidity {
+            Some(seed) => Some(self.seed_initial_liquidity(seed)?),
+            None => None,
+        };
+
+        let mut price_history = self.price_history.load_init()?;
+        price_history.market = market_key;
+        price_history.bump = bumps.price_history;
+        if let Some((price, volume)) = opening_sample {
+            price_history.record(clock.unix_timestamp, price, volume);
+        }
+        Ok(())
+    }
+
+    /// Deposits the creator's liquidity and mints it as YES/NO shares in the
+    /// requested ratio, so the parimutuel price opens at `yes_bps`. The shares
+    /// sit in the creator's position and exit through sell or claim like any
+    /// other. Returns the opening price and volume for the price history.
+    fn seed_initial_liquidity(&mut self, seed: InitialLiquidity) -> Result<(u64, u64)> {
+        require!(
+            seed.yes_bps > 0 && seed.yes_bps < 10_000,
+            Errors::InvalidInitialOdds
+        );
+        require!(seed.amount > 0, Errors::ErrorInvalidAmount);
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.creator_collateral_ata.to_account_info(),
+                    mint: self.collateral_mint.to_account_info(),
+                    to: self.market_vault.to_account_info(),
+                    authority: self.market_creator.to_account_info(),
+                },
+            ),
+            seed.amount,
+            self.collateral_mint.decimals,
+        )?;
+        // The vault is new, so its whole balance is what arrived after transfer fees
+        self.market_vault.reload()?;
+        let received = self.market_vault.amount;
+        if let Some(max_open_interest) = self.market.max_open_interest {
+            require_gte!(max_open_interest, received, Errors::OpenInterestCapExceeded);
+        }
+
+        let (yes_shares, no_shares) =
+            split_by_odds(received, seed.yes_bps).map_err(Errors::from)?;
+        require!(yes_shares > 0 && no_shares > 0, Errors::ErrorInvalidAmount);
+
+        let seeds = &[
+            b"market",
+            self.market.authority.as_ref(),
+            &self.market.market_id.to_le_bytes(),
+            &[self.market.bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        for (mint, ata, shares) in [
+            (&self.yes_mint, &self.creator_yes_ata, yes_shares),
+            (&self.no_mint, &self.creator_no_ata, no_shares),
+        ] {
+            associated_token::create(CpiContext::new(
+                self.associated_token_program.to_account_info(),
+                associated_token::Create {
+                    payer: self.market_creator.to_account_info(),
+                    associated_token: ata.to_account_info(),
+                    authority: self.market_creator.to_account_info(),
+                    mint: mint.to_account_info(),
+                    system_program: self.system_program.to_account_info(),
+                    token_program: self.outcome_token_program.to_account_info(),
+                },
+            ))?;
+            token_interface::mint_to(
+                CpiContext::new(
+                    self.outcome_token_program.to_account_info(),
+                    MintTo {
+                        mint: mint.to_account_info(),
+                        to: ata.to_account_info(),
+                        authority: self.market.to_account_info(),
+                    },
+                )
+                .with_signer(signer_seeds),
+                shares,
+            )?;
+        }
+
+        let position = &mut self.creator_position;
+        position.yes_shares = yes_shares;
+        position.no_shares = no_shares;
+        position.collateral_deposited = received;
+
+        msg!(
+            "🌱 Seeded {} liquidity: {} YES / {} NO",
+            received,
+            yes_shares,
+            no_shares
+        );
+        Ok((yes_price(yes_shares, no_shares), received))
+    }
+
+    /// Moves the collateral's creator bond into the market's bond vault.
+    fn lock_creator_bond(&mut self) -> Result<()> {
+        let bond = self.collateral_info.creator_bond;
+        if bond == 0 {
+            return Ok(());
+        }
+
+        token_interface::transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.creator_collateral_ata.to_account_info(),
+                    mint: self.collateral_mint.to_account_info(),
+                    to: self.bond_vault.to_account_info(),
+                    authority: self.market_creator.to_account_info(),
+                },
+            ),
+            bond,
+            self.collateral_mint.decimals,
+        )?;
+
+        // Record what actually arrived, in case the collateral charges transfer fees
+        self.bond_vault.reload()?;
+        self.market.bond_amount = self.bond_vault.amount;
+        msg!("🔒 Creator bond locked: {}", self.market.bond_amount);
+        Ok(())
+    }
+
+    /// Appends the market to the global and category registries.
+    fn register_market(&mut self, category: String, bumps: &CreateMarketBumps) -> Result<()> {
+        let market_key = self.market.key();
+
+        let registry = &mut self.registry;
+        registry.bump = bumps.registry;
+        self.market.registry_index = registry.total_markets;
+        self.registry_page.page_index = registry.total_markets / REGISTRY_PAGE_SIZE;
+        self.registry_page.bump = bumps.registry_page;
+        self.registry_page.markets.push(market_key);
+        registry.total_markets = registry
+            .total_markets
+            .checked_add(1)
+            .ok_or(Errors::ErrorInCalculating)?;
+
+        let category_registry = &mut self.category_registry;
+        category_registry.category = category.clone();
+        category_registry.bump = bumps.category_registry;
+        self.category_page.page_index = category_registry.total_markets / REGISTRY_PAGE_SIZE;
+        self.category_page.bump = bumps.category_page;
+        self.category_page.markets.push(market_key);
+        category_registry.total_markets = category_registry
+            .total_markets
+            .checked_add(1)
+            .ok_or(Errors::ErrorInCalculating)?;
+
+        self.market.category = category;
+        Ok(())
+    }
+
+    /// Token-2022 collateral may only carry extensions the vault accounting can
+    /// handle. Transfer fees are fine because trades measure what the vault
+    /// actually received; confidential balances, permanent delegates, hooks,
+    /// pausing and frozen-by-default accounts could drain or lock the vault.
+    fn validate_collateral_extensions(&self) -> Result<()> {
+        let mint_info = self.collateral_mint.to_account_info();
+        if *mint_info.owner != spl_token_2022::ID {
+            return Ok(());
+        }
+
+        let data = mint_info.try_borrow_data()?;
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+        for extension in mint.get_extension_types()? {
+            let supported = matches!(
+                extension,
+                ExtensionType::TransferFeeConfig
+                    | ExtensionType::MintCloseAuthority
+                    | ExtensionType::InterestBearingConfig
+                    | ExtensionType::MetadataPointer
+                    | ExtensionType::TokenMetadata
+                    | ExtensionType::GroupPointer
+                    | ExtensionType::TokenGroup
+                    | ExtensionType::GroupMemberPointer
+                    | ExtensionType::TokenGroupMember
+                    | ExtensionType::ScaledUiAmount
+            );
+            if !supported {
+                msg!("Unsupported collateral extension: {:?}", extension);
+                return err!(Errors::UnsupportedCollateralExtension);
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates an outcome mint PDA with the market as mint authority. With
+    /// `metadata` (name, symbol, uri) the mint carries the metadata-pointer and
+    /// token-metadata extensions, pointing at itself.
+    ///
+    /// Outcome mints copy the collateral's decimals: buy, sell and claim move
+    /// shares and collateral 1:1 in raw units, so one UI share is always
+    /// backed by one UI unit of collateral.
+    fn create_outcome_mint(
+        &self,
+        mint: &AccountInfo<'info>,
+        seed: &[u8],
+        bump: u8,
+        metadata: Option<(String, String, String)>,
+    ) -> Result<()> {
+        let market_key = self.market.key();
+        let token_program = self.outcome_token_program.to_account_info();
+
+        let market_seeds = &[
+            b"market",
+            self.market.authority.as_ref(),
+            &self.market.market_id.to_le_bytes(),
+            &[self.market.bump],
+        ];
+        let market_signer = &[&market_seeds[..]];
+        let mint_seeds = &[seed, market_key.as_ref(), &[bump]];
+        let mint_signer = &[&mint_seeds[..]];
+
+        let extensions: &[ExtensionType] = if metadata.is_some() {
+            &[ExtensionType::MetadataPointer]
+        } else {
+            &[]
+        };
+        let space =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)?;
+
+        // Token metadata is appended by a realloc, so fund its rent up front
+        let token_metadata = metadata.as_ref().map(|(name, symbol, uri)| TokenMetadata {
+            update_authority: OptionalNonZeroPubkey(market_key),
+            mint: mint.key(),
+            name: name.clone(),
+            symbol: symbol.clone(),
+            uri: uri.clone(),
+            additional_metadata: vec![],
+        });
+        let rent_space = match &token_metadata {
+            Some(token_metadata) => space + token_metadata.tlv_size_of()?,
+            None => space,
+        };
+        let lamports = Rent::get()?.minimum_balance(rent_space);
+
+        // Same approach as Anchor's `init`: tolerate lamports sent to the PDA early
+        let current_lamports = mint.lamports();
+        if current_lamports == 0 {
+            system_program::create_account(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    CreateAccount {
+                        from: self.market_creator.to_account_info(),
+                        to: mint.clone(),
+                    },
+                    mint_signer,
+                ),
+                lamports,
+                space as u64,
+                &token_program.key(),
+            )?;
+        } else {
+            let top_up = lamports.saturating_sub(current_lamports);
+            if top_up > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        self.system_program.to_account_info(),
+                        Transfer {
+                            from: self.market_creator.to_account_info(),
+                            to: mint.clone(),
+                        },
+                    ),
+                    top_up,
+                )?;
+            }
+            system_program::allocate(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Allocate {
+                        account_to_allocate: mint.clone(),
+                    },
+                    mint_signer,
+                ),
+                space as u64,
+            )?;
+            system_program::assign(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Assign {
+                        account_to_assign: mint.clone(),
+                    },
+                    mint_signer,
+                ),
+                &token_program.key(),
+            )?;
+        }
+
+        if metadata.is_some() {
+            token_interface::metadata_pointer_initialize(
+                CpiContext::new(
+                    token_program.clone(),
+                    MetadataPointerInitialize {
+                        token_program_id: token_program.clone(),
+                        mint: mint.clone(),
+                    },
+                ),
+                Some(market_key),
+                Some(mint.key()),
+            )?;
+        }
+
+        token_interface::initialize_mint2(
+            CpiContext::new(
+                token_program.clone(),
+                InitializeMint2 { mint: mint.clone() },
+            ),
+            self.collateral_mint.decimals,
+            &market_key,
+            None,
+        )?;
+
+        if let Some((name, symbol, uri)) = metadata {
+            token_interface::token_metadata_initialize(
+                CpiContext::new_with_signer(
+                    token_program.clone(),
+                    TokenMetadataInitialize {
+                        program_id: token_program.clone(),
+                        metadata: mint.clone(),
+                        update_authority: self.market.to_account_info(),
+                        mint_authority: self.market.to_account_info(),
+                        mint: mint.clone(),
+                    },
+                    market_signer,
+                ),
+                name,
+                symbol,
+                uri,
+            )?;
+        }
         Ok(())
     }
 }
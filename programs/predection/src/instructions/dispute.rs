@@ -0,0 +1,265 @@
+use crate::errors::Errors;
+use crate::states::{CreatorProfile, Market, ProtocolConfig, Resolution, Status};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  DISPUTES + CREATOR BOND
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Inside the dispute window anyone can challenge a resolution by matching
+// the creator bond. The protocol admin rules: an overturned result pays both
+// bonds to the disputer, an upheld one sends the disputer's bond to the
+// treasury. Once final, the creator bond goes back to the creator, or to the
+// treasury if the market was resolved INVALID.
+
+/// Pays `amount` out of the bond vault, signed by the market PDA.
+fn pay_from_bond_vault<'info>(
+    market: &Account<'info, Market>,
+    bond_vault: &InterfaceAccount<'info, TokenAccount>,
+    collateral_mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    let seeds = &[
+        b"market",
+        market.authority.as_ref(),
+        &market.market_id.to_le_bytes(),
+        &[market.bump],
+    ];
+    let signer_seeds = &[&seeds[..]];
+
+    let ctx_acc = TransferChecked {
+        mint: collateral_mint.to_account_info(),
+        from: bond_vault.to_account_info(),
+        to: to.to_account_info(),
+        authority: market.to_account_info(),
+    };
+    let ctx = CpiContext::new(token_program.to_account_info(), ctx_acc).with_signer(signer_seeds);
+    token_interface::transfer_checked(ctx, amount, collateral_mint.decimals)
+}
+
+#[derive(Accounts)]
+pub struct DisputeMarket<'info> {
+    #[account(mut)]
+    pub disputer: Signer<'info>,
+
+    #[account(mut, seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut,
+        associated_token::mint = collateral_mint,
+        associated_token::authority = disputer,
+        associated_token::token_program = token_program
+    )]
+    pub disputer_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut, seeds = [b"bond_vault", market.key().as_ref()], bump)]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> DisputeMarket<'info> {
+    pub fn dispute_market(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            matches!(self.market.status, Status::Resolved),
+            Errors::InvalidMarketStatus
+        );
+        require!(
+            now < self
+                .market
+                .resolved_at
+                .saturating_add(self.market.dispute_window),
+            Errors::DisputeWindowClosed
+        );
+        // The disputer stakes as much as the creator did
+        require!(self.market.bond_amount > 0, Errors::MarketNotDisputable);
+
+        let vault_before = self.bond_vault.amount;
+        let ctx_acc = TransferChecked {
+            mint: self.collateral_mint.to_account_info(),
+            from: self.disputer_collateral_ata.to_account_info(),
+            to: self.bond_vault.to_account_info(),
+            authority: self.disputer.to_account_info(),
+        };
+        token_interface::transfer_checked(
+            CpiContext::new(self.token_program.to_account_info(), ctx_acc),
+            self.market.bond_amount,
+            self.collateral_mint.decimals,
+        )?;
+        self.bond_vault.reload()?;
+
+        self.market.dispute_bond = self
+            .bond_vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(Errors::ErrorInCalculating)?;
+        self.market.disputer = Some(self.disputer.key());
+        self.market.status = Status::Disputed;
+
+        msg!("⚖️ Resolution disputed by {}", self.disputer.key());
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct RuleDispute<'info> {
+    #[account(constraint = admin.key() == config.admin @ Errors::InvalidProtocolAdmin)]
+    pub admin: Signer<'info>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(mut, seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(mut, seeds = [b"creator", market.authority.as_ref()], bump = creator_profile.bump)]
+    pub creator_profile: Box<Account<'info, CreatorProfile>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"bond_vault", market.key().as_ref()], bump)]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        constraint = Some(disputer_collateral_ata.owner) == market.disputer @ Errors::InvalidDisputer,
+        constraint = disputer_collateral_ata.mint == market.collateral_mint @ Errors::InvalidMint
+    )]
+    pub disputer_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Slashed and forfeited bonds only ever go to the protocol treasury
+    #[account(mut,
+        constraint = protocol_fee_collector_ata.owner == config.treasury @ Errors::InvalidProtocolFeeCollector,
+        constraint = protocol_fee_collector_ata.mint == market.collateral_mint @ Errors::InvalidMint
+    )]
+    pub protocol_fee_collector_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> RuleDispute<'info> {
+    pub fn rule_dispute(&mut self, ruling: Resolution) -> Result<()> {
+        require!(
+            matches!(self.market.status, Status::Disputed),
+            Errors::MarketNotDisputed
+        );
+
+        let dispute_bond = self.market.dispute_bond;
+        if self.market.resolution != Some(ruling) {
+            // Creator's market lost the dispute: both bonds go to the disputer
+            let payout = self
+                .market
+                .bond_amount
+                .checked_add(dispute_bond)
+                .ok_or(Errors::ErrorInCalculating)?;
+            pay_from_bond_vault(
+                &self.market,
+                &self.bond_vault,
+                &self.collateral_mint,
+                &self.disputer_collateral_ata,
+                &self.token_program,
+                payout,
+            )?;
+            self.market.bond_amount = 0;
+            self.creator_profile.disputes_lost =
+                self.creator_profile.disputes_lost.saturating_add(1);
+            msg!("Resolution overturned, bonds paid to disputer");
+        } else {
+            pay_from_bond_vault(
+                &self.market,
+                &self.bond_vault,
+                &self.collateral_mint,
+                &self.protocol_fee_collector_ata,
+                &self.token_program,
+                dispute_bond,
+            )?;
+            msg!("Resolution upheld, dispute bond sent to treasury");
+        }
+
+        self.market.resolution = Some(ruling);
+        self.market.option = match ruling {
+            Resolution::Yes => Some(true),
+            Resolution::No => Some(false),
+            Resolution::Invalid => None,
+        };
+        self.market.dispute_bond = 0;
+        self.market.status = Status::Resolved;
+        // The admin ruling is final, so claims open immediately
+        self.market.dispute_window = 0;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SettleBond<'info> {
+    #[account(mut, seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, ProtocolConfig>>,
+
+    #[account(constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
+    pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut, seeds = [b"bond_vault", market.key().as_ref()], bump)]
+    pub bond_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        constraint = creator_collateral_ata.owner == market.authority @ Errors::InvalidCreatorAta,
+        constraint = creator_collateral_ata.mint == market.collateral_mint @ Errors::InvalidMint
+    )]
+    pub creator_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Slashed and forfeited bonds only ever go to the protocol treasury
+    #[account(mut,
+        constraint = protocol_fee_collector_ata.owner == config.treasury @ Errors::InvalidProtocolFeeCollector,
+        constraint = protocol_fee_collector_ata.mint == market.collateral_mint @ Errors::InvalidMint
+    )]
+    pub protocol_fee_collector_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> SettleBond<'info> {
+    /// Permissionless: anyone can release the bond once the result is final.
+    pub fn settle_bond(&mut self) -> Result<()> {
+        require!(
+            self.market.is_final(Clock::get()?.unix_timestamp),
+            Errors::ResolutionNotFinal
+        );
+        let bond = self.market.bond_amount;
+        require!(bond > 0, Errors::BondAlreadySettled);
+
+        let to = if self.market.resolution == Some(Resolution::Invalid) {
+            msg!("Market resolved INVALID, creator bond slashed to treasury");
+            &self.protocol_fee_collector_ata
+        } else {
+            msg!("Clean settlement, creator bond returned");
+            &self.creator_collateral_ata
+        };
+        pay_from_bond_vault(
+            &self.market,
+            &self.bond_vault,
+            &self.collateral_mint,
+            to,
+            &self.token_program,
+            bond,
+        )?;
+
+        self.market.bond_amount = 0;
+        Ok(())
+    }
+}
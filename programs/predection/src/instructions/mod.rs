@@ -1,11 +1,22 @@
 pub mod buy_share;
 pub mod claim_winning;
+pub mod collateral_registry;
 pub mod create_market;
+pub mod dispute;
+pub mod protocol_config;
 pub mod resolve_market;
 pub mod sell_share;
+pub mod update_market_metadata;
+pub mod views;
+mod wsol;
 
 pub use buy_share::*;
 pub use claim_winning::*;
+pub use collateral_registry::*;
 pub use create_market::*;
+pub use dispute::*;
+pub use protocol_config::*;
 pub use resolve_market::*;
 pub use sell_share::*;
+pub use update_market_metadata::*;
+pub use views::*;
@@ -0,0 +1,37 @@
+use crate::errors::Errors;
+use crate::program::Predection;
+use crate::states::ProtocolConfig;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitProtocolConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, ProtocolConfig>,
+
+    // Only the upgrade authority can claim the admin role
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ Errors::InvalidProgramData)]
+    pub program: Program<'info, Predection>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ Errors::InvalidProtocolAdmin)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitProtocolConfig<'info> {
+    pub fn init_protocol_config(&mut self, treasury: Pubkey, bump: u8) -> Result<()> {
+        self.config.admin = self.admin.key();
+        self.config.treasury = treasury;
+        self.config.bump = bump;
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
 use crate::errors::Errors;
-use crate::states::{Market, Status};
+use crate::states::{Market, Resolution, Status};
 use anchor_lang::prelude::*;
 use anchor_spl::token_interface::Mint;
 
 #[derive(Accounts)]
-#[instruction(outcome: bool)]
+#[instruction(resolution: Resolution)]
 pub struct ResolveMarket<'info> {
     #[account(mut,
 constraint = *resolver.key == market.resolver @ Errors::InvalidMarketResolver
@@ -28,17 +28,19 @@ constraint = *resolver.key == market.resolver @ Errors::InvalidMarketResolver
 }
 
 impl<'info> ResolveMarket<'info> {
-    pub fn resolve_market(&mut self, outcome: bool) -> Result<()> {
+    pub fn resolve_market(&mut self, resolution: Resolution) -> Result<()> {
         let clock = Clock::get()?;
 
-        // Check market is still Open
+        // Check market is still Open (a halted market can still be resolved)
         require!(
-            matches!(self.market.status, Status::Open),
+            matches!(self.market.status, Status::Open | Status::Halted),
             Errors::InvalidMarketStatus
         );
 
+        // A one-sided market has no losers to pay winners, but can still be voided
         require!(
-            self.yes_mint.supply > 0 && self.no_mint.supply > 0,
+            resolution == Resolution::Invalid
+                || (self.yes_mint.supply > 0 && self.no_mint.supply > 0),
             Errors::CannotResolveOnesideMarket
         );
 
@@ -55,13 +57,58 @@ impl<'info> ResolveMarket<'info> {
         );
 
         // Set the outcome
-        self.market.option = Some(outcome);
+        self.market.resolution = Some(resolution);
+        self.market.option = match resolution {
+            Resolution::Yes => Some(true),
+            Resolution::No => Some(false),
+            Resolution::Invalid => None,
+        };
         self.market.status = Status::Resolved;
+        self.market.resolved_at = clock.unix_timestamp;
 
-        msg!("Market resolved! Outcome: {}", outcome);
-        msg!("YES tokens: {}", if outcome { "WINNERS" } else { "LOSERS" });
-        msg!("NO tokens: {}", if outcome { "LOSERS" } else { "WINNERS" });
+        match self.market.option {
+            Some(outcome) => {
+                msg!("Market resolved! Outcome: {}", outcome);
+                msg!("YES tokens: {}", if outcome { "WINNERS" } else { "LOSERS" });
+                msg!("NO tokens: {}", if outcome { "LOSERS" } else { "WINNERS" });
+            }
+            None => msg!("Market resolved INVALID, both sides are refunded"),
+        }
 
         Ok(())
     }
 }
+
+#[derive(Accounts)]
+pub struct ExpireMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()],
+        bump = market.bump
+    )]
+    pub market: Account<'info, Market>,
+}
+
+impl<'info> ExpireMarket<'info> {
+    /// Permissionless: voids a market its resolver left unresolved past the
+    /// grace period, so traders are refunded and the creator bond, slashed
+    /// as for any INVALID market, is no longer locked.
+    pub fn expire_market(&mut self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            matches!(self.market.status, Status::Open | Status::Halted),
+            Errors::InvalidMarketStatus
+        );
+        require!(self.market.is_expired(now), Errors::MarketNotExpired);
+
+        self.market.resolution = Some(Resolution::Invalid);
+        self.market.option = None;
+        self.market.status = Status::Resolved;
+        self.market.resolved_at = now;
+        // No resolver decision to dispute, so claims open immediately
+        self.market.dispute_window = 0;
+
+        msg!("Market expired unresolved, voided as INVALID");
+        Ok(())
+    }
+}
@@ -5,7 +5,8 @@ use anchor_spl::{
 };
 
 use crate::errors::Errors;
-use crate::states::{Market, Status};
+use crate::instructions::wsol;
+use crate::states::{CreatorProfile, Market, Position, PriceHistory, Status, TradeOutcome};
 
 #[derive(Accounts)]
 #[instruction(amount: u64, is_yes: bool)]
@@ -13,9 +14,12 @@ pub struct SellShare<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
 
-    #[account(seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump)]
+    #[account(mut, seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump)]
     pub market: Box<Account<'info, Market>>,
 
+    #[account(mut, seeds = [b"creator", market.authority.as_ref()], bump = creator_profile.bump)]
+    pub creator_profile: Box<Account<'info, CreatorProfile>>,
+
     #[account(mut, associated_token::mint = collateral_mint,
         associated_token::authority = market,
         associated_token::token_program = token_program,
@@ -26,9 +30,11 @@ pub struct SellShare<'info> {
     #[account(mut, constraint = collateral_mint.key() == market.collateral_mint @ Errors::InvalidMint)]
     pub collateral_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    #[account(mut,
+    #[account(init_if_needed,
+        payer = signer,
         associated_token::mint = collateral_mint,
-        associated_token::authority = signer
+        associated_token::authority = signer,
+        associated_token::token_program = token_program
     )]
     pub user_collateral_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -47,28 +53,75 @@ pub struct SellShare<'info> {
     #[account(mut,
         associated_token::mint = yes_mint,
         associated_token::authority = signer,
-        associated_token::token_program = token_program
+        associated_token::token_program = outcome_token_program
     )]
     pub yes_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         associated_token::mint = no_mint,
         associated_token::authority = signer,
-        associated_token::token_program = token_program
+        associated_token::token_program = outcome_token_program
     )]
     pub no_mint_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
+    #[account(init_if_needed,
+        payer = signer,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [b"position", market.key().as_ref(), signer.key().as_ref()],
+        bump
+    )]
+    pub position: Box<Account<'info, Position>>,
+
+    #[account(mut, seeds = [b"price_history", market.key().as_ref()], bump = price_history.load()?.bump)]
+    pub price_history: AccountLoader<'info, PriceHistory>,
+
     pub token_program: Interface<'info, TokenInterface>,
+    #[account(
+        constraint = outcome_token_program.key() == market.outcome_token_program @ Errors::InvalidOutcomeTokenProgram
+    )]
+    pub outcome_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> SellShare<'info> {
-    pub fn sell_share(&mut self, amount: u64, is_yes: bool) -> Result<()> {
+    pub fn sell_share(
+        &mut self,
+        amount: u64,
+        is_yes: bool,
+        unwrap_sol: bool,
+        position_bump: u8,
+    ) -> Result<TradeOutcome> {
+        let now = Clock::get()?.unix_timestamp;
+        self.market.reopen_if_cooled_down(now);
+        require!(
+            !matches!(self.market.status, Status::Halted),
+            Errors::MarketHalted
+        );
         require!(
             matches!(self.market.status, Status::Open),
             Errors::InvalidMarketStatus
         );
+        if unwrap_sol {
+            wsol::check_unwrap(&self.collateral_mint, &self.user_collateral_ata)?;
+        }
+        // Burning more than the supply fails in the token program anyway
+        let before = (self.yes_mint.supply, self.no_mint.supply);
+        let after = if is_yes {
+            (before.0.saturating_sub(amount), before.1)
+        } else {
+            (before.0, before.1.saturating_sub(amount))
+        };
+        let tripped = {
+            let price_history = self.price_history.load()?;
+            self.market
+                .trip_circuit_breaker(&price_history, before, after, now)
+        };
+        if tripped {
+            return Ok(TradeOutcome::Halted {
+                halted_until: self.market.halted_until,
+            });
+        }
 
         let seeds = &[
             b"market",
@@ -81,7 +134,7 @@ impl<'info> SellShare<'info> {
         if is_yes {
             // Burn YES tokens
             let burn_ctx = CpiContext::new(
-                self.token_program.to_account_info(),
+                self.outcome_token_program.to_account_info(),
                 Burn {
                     mint: self.yes_mint.to_account_info(),
                     from: self.yes_mint_ata.to_account_info(),
@@ -92,7 +145,7 @@ impl<'info> SellShare<'info> {
         } else {
             // Burn NO tokens
             let burn_ctx = CpiContext::new(
-                self.token_program.to_account_info(),
+                self.outcome_token_program.to_account_info(),
                 Burn {
                     mint: self.no_mint.to_account_info(),
                     from: self.no_mint_ata.to_account_info(),
@@ -116,6 +169,29 @@ impl<'info> SellShare<'info> {
 
         token_interface::transfer_checked(transfer_ctx, amount, self.collateral_mint.decimals)?;
 
-        Ok(())
+        let market_key = self.market.key();
+        self.position
+            .ensure_initialized(market_key, self.signer.key(), position_bump);
+        self.position.remove_shares(amount, is_yes, amount);
+
+        self.yes_mint.reload()?;
+        self.no_mint.reload()?;
+        self.market.record_trade(
+            &mut self.creator_profile,
+            &mut *self.price_history.load_mut()?,
+            (self.yes_mint.supply, self.no_mint.supply),
+            amount,
+            now,
+        );
+
+        if unwrap_sol {
+            wsol::unwrap(
+                self.token_program.to_account_info(),
+                self.user_collateral_ata.to_account_info(),
+                self.signer.to_account_info(),
+            )?;
+        }
+
+        Ok(TradeOutcome::Filled { shares: amount })
     }
 }
@@ -0,0 +1,71 @@
+use crate::errors::Errors;
+use crate::states::{
+    Market, MarketMetadata, MetadataField, MAX_DESCRIPTION_LEN, MAX_RULES_LEN, MAX_SOURCES,
+    MAX_SOURCE_LEN, MAX_TAGS, MAX_TAG_LEN,
+};
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdateMarketMetadata<'info> {
+    #[account(mut,
+        constraint = market_creator.key() == market.authority @ Errors::InvalidMarketAuthority
+    )]
+    pub market_creator: Signer<'info>,
+
+    #[account(seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(
+        init_if_needed,
+        payer = market_creator,
+        space = 8 + MarketMetadata::INIT_SPACE,
+        seeds = [b"metadata", market.key().as_ref()],
+        bump
+    )]
+    pub market_metadata: Box<Account<'info, MarketMetadata>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> UpdateMarketMetadata<'info> {
+    pub fn update_market_metadata(&mut self, field: MetadataField, bump: u8) -> Result<()> {
+        require!(self.market.trade_count == 0, Errors::MetadataLocked);
+
+        let metadata = &mut self.market_metadata;
+        metadata.market = self.market.key();
+        metadata.bump = bump;
+
+        match field {
+            MetadataField::Description(description) => {
+                require!(
+                    description.len() <= MAX_DESCRIPTION_LEN,
+                    Errors::MetadataFieldTooLong
+                );
+                metadata.description = description;
+            }
+            MetadataField::Rules(rules) => {
+                require!(rules.len() <= MAX_RULES_LEN, Errors::MetadataFieldTooLong);
+                metadata.rules = rules;
+            }
+            MetadataField::Tags(tags) => {
+                require!(
+                    tags.len() <= MAX_TAGS && tags.iter().all(|tag| tag.len() <= MAX_TAG_LEN),
+                    Errors::MetadataFieldTooLong
+                );
+                metadata.tags = tags;
+            }
+            MetadataField::Sources(sources) => {
+                require!(
+                    sources.len() <= MAX_SOURCES
+                        && sources.iter().all(|source| source.len() <= MAX_SOURCE_LEN),
+                    Errors::MetadataFieldTooLong
+                );
+                metadata.sources = sources;
+            }
+            MetadataField::ContentHash(content_hash) => {
+                metadata.content_hash = content_hash;
+            }
+        }
+        Ok(())
+    }
+}
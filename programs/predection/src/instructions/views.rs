@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+use crate::errors::Errors;
+use crate::states::{yes_price, Market, Resolution, Status, PRICE_SCALE};
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  VIEWS: read-only instructions that answer through return data
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Callers read the answer with `predection::cpi::get_price(..)?.get()`, or
+// off-chain from `returnData` of a simulated transaction. Programs linking
+// this crate can skip the CPI and build the same views from the accounts.
+
+/// Prices and pool sizes, as `buy_share` would see them right now.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceView {
+    pub yes_price: u64, // Scaled by PRICE_SCALE
+    pub no_price: u64,
+    pub yes_supply: u64,
+    pub no_supply: u64,
+    pub vault_balance: u64, // Collateral winning shares split at claim time
+    pub fee_bps: u64,
+    pub min_trade_size: u64,
+}
+
+impl PriceView {
+    pub fn new(market: &Market, yes_supply: u64, no_supply: u64, vault_balance: u64) -> Self {
+        let yes_price = yes_price(yes_supply, no_supply);
+        Self {
+            yes_price,
+            no_price: PRICE_SCALE - yes_price,
+            yes_supply,
+            no_supply,
+            vault_balance,
+            fee_bps: market.fee,
+            min_trade_size: market.min_trade_size,
+        }
+    }
+}
+
+/// Lifecycle state, with the time checks the trading and claim paths make
+/// already applied.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StatusView {
+    pub status: Status,
+    pub resolution: Option<Resolution>,
+    pub trading_open: bool, // A buy or sell would pass the status check
+    pub claimable: bool,    // Resolved and past the dispute window
+    pub close_timestamp: i64,
+    pub halted_until: i64,
+    pub dispute_deadline: Option<i64>, // Set once resolved
+}
+
+impl StatusView {
+    pub fn new(market: &Market, now: i64) -> Self {
+        let trading_open = match market.status {
+            Status::Open => true,
+            // Trades lift an expired halt before checking the status
+            Status::Halted => now >= market.halted_until,
+            _ => false,
+        };
+        Self {
+            status: market.status.clone(),
+            resolution: market.resolution,
+            trading_open,
+            claimable: market.is_final(now),
+            close_timestamp: market.market_close_timestamp,
+            halted_until: market.halted_until,
+            dispute_deadline: market
+                .resolution
+                .map(|_| market.resolved_at.saturating_add(market.dispute_window)),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct GetPrice<'info> {
+    #[account(seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+
+    #[account(constraint = yes_mint.key() == market.yes_mint @ Errors::InvalidMint)]
+    pub yes_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(constraint = no_mint.key() == market.no_mint @ Errors::InvalidMint)]
+    pub no_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(constraint = market_vault.key() == market.market_vault @ Errors::InvalidVault)]
+    pub market_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+}
+
+impl<'info> GetPrice<'info> {
+    pub fn get_price(&self) -> PriceView {
+        PriceView::new(
+            &self.market,
+            self.yes_mint.supply,
+            self.no_mint.supply,
+            self.market_vault.amount,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct GetStatus<'info> {
+    #[account(seeds = [b"market", market.authority.as_ref(), &market.market_id.to_le_bytes()], bump = market.bump)]
+    pub market: Box<Account<'info, Market>>,
+}
+
+impl<'info> GetStatus<'info> {
+    pub fn get_status(&self) -> Result<StatusView> {
+        let now = Clock::get()?.unix_timestamp;
+        Ok(StatusView::new(&self.market, now))
+    }
+}
@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::spl_token::native_mint,
+    token_interface::{self, CloseAccount, Mint, TokenAccount},
+};
+
+use crate::errors::Errors;
+
+/// Closing the wSOL account unwraps all of it, so only an account that
+/// starts empty is closed, and the user gets back just what the instruction
+/// moved through it.
+pub(super) fn check_unwrap(
+    collateral_mint: &InterfaceAccount<Mint>,
+    account: &InterfaceAccount<TokenAccount>,
+) -> Result<()> {
+    require_keys_eq!(
+        collateral_mint.key(),
+        native_mint::ID,
+        Errors::CollateralNotNative
+    );
+    require!(account.amount == 0, Errors::WrappedSolAccountNotEmpty);
+    Ok(())
+}
+
+/// Closes the owner's wSOL account into their wallet, so they hold lamports
+/// rather than wSOL.
+pub(super) fn unwrap<'info>(
+    token_program: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    owner: AccountInfo<'info>,
+) -> Result<()> {
+    token_interface::close_account(CpiContext::new(
+        token_program,
+        CloseAccount {
+            account,
+            destination: owner.clone(),
+            authority: owner,
+        },
+    ))
+}
@@ -1,11 +1,53 @@
+//! Parimutuel YES/NO prediction markets.
+//!
+//! # Calling from other programs
+//!
+//! Build with the `cpi` feature and call through `predection::cpi`. These
+//! instructions are the stable surface for integrators: their arguments and
+//! account order only change with a new major version.
+//!
+//! | Instruction     | Signer | Effect                                        | Returns            |
+//! |-----------------|--------|-----------------------------------------------|--------------------|
+//! | `buy_share`     | trader | pays collateral plus fee, mints YES or NO[^1] | [`TradeOutcome`]   |
+//! | `sell_share`    | trader | burns shares for collateral at 1:1[^1]        | [`TradeOutcome`]   |
+//! | `claim_winning` | trader | burns winning shares for their vault share    |                    |
+//! | `get_price`     | none   | reads only                                    | [`PriceView`]      |
+//! | `get_status`    | none   | reads only                                    | [`StatusView`]     |
+//!
+//! [^1]: Unless the trade would move the price past the market's circuit
+//!     breaker. It then halts the market and succeeds without filling, so
+//!     the halt is kept, and returns `TradeOutcome::Halted`.
+//!
+//! The trader's signer privilege carries through the CPI, so a program can
+//! trade for the wallet that signed its own instruction, or for a PDA it
+//! signs for. Outcome tokens, the trader's position and their ATAs belong to
+//! that signer; `init_if_needed` accounts are paid for by it.
+//!
+//! Results come back as return data: `predection::cpi::get_price(ctx)?.get()`,
+//! or off-chain from the transaction's `returnData`. A halted trade does not
+//! fail, so callers must check what `buy_share` and `sell_share` return.
+//! Programs that link this
+//! crate can instead build the views straight from the accounts with
+//! [`PriceView::new`] and [`StatusView::new`], saving the CPI. Fee, price
+//! and payout math is in [`math`] for quoting ahead of a trade.
+//!
+//! `programs/predection-example` is a worked integration.
+
+// Anchor's generated CPI client mirrors each handler's argument list
+#![allow(clippy::too_many_arguments)]
+
 use anchor_lang::prelude::*;
 
 pub mod errors;
 pub mod instructions;
+pub mod merkle;
 pub mod states;
 
+pub use predection_math as math;
+
 use errors::Errors;
 use instructions::*;
+use states::{InitialLiquidity, MarketLimits, MetadataField, Resolution, TradeOutcome};
 
 declare_id!("xU2NJKXuSyyrGC8ntDjUG6ESH1YB6CNtivV8MTG9YGV");
 
@@ -16,43 +58,134 @@ pub mod predection {
     pub fn initialize(
         ctx: Context<CreateMarket>,
         resolver: Pubkey,
-        market_id: u64,
         question: Vec<u8>,
         duration_time: i64,
         fee: u64,
+        limits: MarketLimits,
+        allowlist_root: Option<[u8; 32]>,
+        metadata_uri: Option<String>,
+        category: String,
+        initial_liquidity: Option<InitialLiquidity>,
     ) -> Result<()> {
         msg!("Greetings from: {:?}", ctx.program_id);
-        let bump = ctx.bumps.market;
 
         // Convert Vec<u8> to String
         let question_str =
-            String::from_utf8(question).map_err(|_| error!(Errors::QuestionTooLong))?;
+            String::from_utf8(question).map_err(|_| error!(Errors::InvalidQuestionEncoding))?;
 
-        ctx.accounts
-            .create_market(resolver, market_id, question_str, duration_time, fee, bump)
+        ctx.accounts.create_market(
+            resolver,
+            question_str,
+            duration_time,
+            fee,
+            limits,
+            allowlist_root,
+            metadata_uri,
+            category,
+            initial_liquidity,
+            &ctx.bumps,
+        )
     }
 
     pub fn buy_share(
         ctx: Context<BuyShare>,
         amount: u64,
-        _market_id: u64,
         is_yes: bool,
-    ) -> Result<()> {
-        ctx.accounts.buy_share(amount, is_yes)
+        proof: Vec<[u8; 32]>,
+        wrap_sol: bool,
+    ) -> Result<TradeOutcome> {
+        let position_bump = ctx.bumps.position;
+        ctx.accounts
+            .buy_share(amount, is_yes, &proof, wrap_sol, position_bump)
+    }
+
+    pub fn resolve_market(ctx: Context<ResolveMarket>, resolution: Resolution) -> Result<()> {
+        ctx.accounts.resolve_market(resolution)
     }
 
-    pub fn resolve_market(ctx: Context<ResolveMarket>, outcome: bool) -> Result<()> {
-        ctx.accounts.resolve_market(outcome)
+    pub fn expire_market(ctx: Context<ExpireMarket>) -> Result<()> {
+        ctx.accounts.expire_market()
     }
 
-    pub fn claim_winning(ctx: Context<ClaimWinning>) -> Result<()> {
+    pub fn claim_winning(ctx: Context<ClaimWinning>, unwrap_sol: bool) -> Result<()> {
         let yes_bump = ctx.bumps.yes_mint;
         let no_bump = ctx.bumps.no_mint;
-        ctx.accounts.claim_winning(yes_bump, no_bump)?;
+        let position_bump = ctx.bumps.position;
+        ctx.accounts
+            .claim_winning(yes_bump, no_bump, position_bump, unwrap_sol)?;
         Ok(())
     }
 
-    pub fn sell_share(ctx: Context<SellShare>, amount: u64, is_yes: bool) -> Result<()> {
-        ctx.accounts.sell_share(amount, is_yes)
+    pub fn sell_share(
+        ctx: Context<SellShare>,
+        amount: u64,
+        is_yes: bool,
+        unwrap_sol: bool,
+    ) -> Result<TradeOutcome> {
+        let position_bump = ctx.bumps.position;
+        ctx.accounts
+            .sell_share(amount, is_yes, unwrap_sol, position_bump)
+    }
+
+    pub fn init_protocol_config(ctx: Context<InitProtocolConfig>, treasury: Pubkey) -> Result<()> {
+        let bump = ctx.bumps.config;
+        ctx.accounts.init_protocol_config(treasury, bump)
+    }
+
+    pub fn add_collateral(
+        ctx: Context<AddCollateral>,
+        min_trade_size: u64,
+        max_fee: u64,
+        creator_bond: u64,
+        dispute_window: i64,
+    ) -> Result<()> {
+        let bump = ctx.bumps.collateral_info;
+        ctx.accounts
+            .add_collateral(min_trade_size, max_fee, creator_bond, dispute_window, bump)
+    }
+
+    pub fn update_collateral(
+        ctx: Context<UpdateCollateral>,
+        min_trade_size: u64,
+        max_fee: u64,
+        creator_bond: u64,
+        dispute_window: i64,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.update_collateral(
+            min_trade_size,
+            max_fee,
+            creator_bond,
+            dispute_window,
+            enabled,
+        )
+    }
+
+    pub fn update_market_metadata(
+        ctx: Context<UpdateMarketMetadata>,
+        field: MetadataField,
+    ) -> Result<()> {
+        let bump = ctx.bumps.market_metadata;
+        ctx.accounts.update_market_metadata(field, bump)
+    }
+
+    pub fn dispute_market(ctx: Context<DisputeMarket>) -> Result<()> {
+        ctx.accounts.dispute_market()
+    }
+
+    pub fn rule_dispute(ctx: Context<RuleDispute>, ruling: Resolution) -> Result<()> {
+        ctx.accounts.rule_dispute(ruling)
+    }
+
+    pub fn settle_bond(ctx: Context<SettleBond>) -> Result<()> {
+        ctx.accounts.settle_bond()
+    }
+
+    pub fn get_price(ctx: Context<GetPrice>) -> Result<PriceView> {
+        Ok(ctx.accounts.get_price())
+    }
+
+    pub fn get_status(ctx: Context<GetStatus>) -> Result<StatusView> {
+        ctx.accounts.get_status()
     }
 }
@@ -0,0 +1,24 @@
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+
+// Allowlist trees hash leaves and nodes with different prefixes so an inner
+// node can never be passed off as a leaf. Sibling pairs are sorted before
+// hashing, so proofs do not need left/right flags.
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn allowlist_leaf(trader: &Pubkey) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, trader.as_ref()]).to_bytes()
+}
+
+pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+pub fn verify_allowlist_proof(root: &[u8; 32], proof: &[[u8; 32]], trader: &Pubkey) -> bool {
+    let computed = proof.iter().fold(allowlist_leaf(trader), |node, sibling| {
+        hash_pair(&node, sibling)
+    });
+    computed == *root
+}
@@ -1,10 +1,29 @@
 use anchor_lang::prelude::*;
+use predection_math::{next_slot, ring_index, CircuitBreaker};
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq, InitSpace)]
 pub enum Status {
     Open,
     Resolved,
     Settled,
+    Halted,   // Circuit breaker tripped; reopens at `halted_until`
+    Disputed, // Resolution challenged; claims wait for the admin ruling
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum Resolution {
+    Yes,
+    No,
+    Invalid, // Question was ambiguous or unresolvable; both sides are refunded
+}
+
+/// What `buy_share` and `sell_share` did, set as their return data. A trade
+/// the circuit breaker stops still succeeds, so the halt is saved, but fills
+/// nothing: callers must check for `Halted`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeOutcome {
+    Filled { shares: u64 }, // Minted by a buy, burned by a sell
+    Halted { halted_until: i64 },
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
@@ -36,4 +55,381 @@ pub struct Market {
     pub protocol_fee_collector_ata: Pubkey, // Protocol treasury's USDC ATA
     pub fee: u64,              // Fee in BPS (100 = 1%)
     pub bump: u8,
+
+    pub max_open_interest: Option<u64>, // Cap on collateral held in market_vault
+    pub max_position_per_wallet: Option<u64>, // Cap on shares bought by one wallet
+
+    pub max_price_move_bps: Option<u64>, // Circuit breaker threshold, in bps of PRICE_SCALE
+    pub price_move_window: i64,          // Seconds of price history the move is measured over
+    pub halt_cooldown: i64,              // Seconds trading stays halted once tripped
+    pub halted_until: i64,
+    pub breaker_min_liquidity: u64, // Shares outstanding below which moves aren't checked
+
+    pub allowlist_root: Option<[u8; 32]>, // Merkle root of traders allowed to buy
+
+    pub outcome_token_program: Pubkey, // Owner of yes_mint / no_mint (SPL Token or Token-2022)
+
+    pub min_trade_size: u64, // Copied from the collateral's CollateralInfo at creation
+
+    pub trade_count: u64, // Buys and sells so far; metadata locks after the first
+
+    #[max_len(MAX_CATEGORY_LEN)]
+    pub category: String, // Fixed at creation because the category index uses it
+    pub registry_index: u64, // Position in the global creation-order registry
+
+    pub resolution: Option<Resolution>, // `option` mirrors Yes/No for older clients
+    pub resolved_at: i64,
+    pub dispute_window: i64, // Copied from the collateral's CollateralInfo; 0 once ruled or expired
+    pub disputer: Option<Pubkey>,
+    pub dispute_bond: u64, // Disputer's stake, equal to the creator bond
+    pub bond_amount: u64,  // Creator bond still held in bond_vault
+}
+
+/// Seconds after close a resolver has before anyone can void the market.
+pub const RESOLUTION_GRACE_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+impl Market {
+    /// Claims and bond settlement wait until the dispute window has passed.
+    pub fn is_final(&self, now: i64) -> bool {
+        matches!(self.status, Status::Resolved)
+            && now >= self.resolved_at.saturating_add(self.dispute_window)
+    }
+
+    /// Unresolved past the grace period: `expire_market` can void it.
+    pub fn is_expired(&self, now: i64) -> bool {
+        matches!(self.status, Status::Open | Status::Halted)
+            && now
+                >= self
+                    .market_close_timestamp
+                    .saturating_add(RESOLUTION_GRACE_PERIOD)
+    }
+
+    /// Lifts an expired circuit-breaker halt.
+    pub fn reopen_if_cooled_down(&mut self, now: i64) {
+        if matches!(self.status, Status::Halted) && now >= self.halted_until {
+            self.status = Status::Open;
+            msg!("Circuit breaker cooldown over, market reopened");
+        }
+    }
+
+    /// The market's circuit breaker, if it has one.
+    pub fn circuit_breaker(&self) -> Option<CircuitBreaker> {
+        self.max_price_move_bps
+            .map(|max_price_move_bps| CircuitBreaker {
+                max_price_move_bps,
+                window: self.price_move_window,
+                cooldown: self.halt_cooldown,
+                min_liquidity: self.breaker_min_liquidity,
+            })
+    }
+
+    /// Checks a trade that would take the outstanding YES and NO shares from
+    /// `before` to `after`, before it fills. If the move breaches the
+    /// threshold the market halts and this returns true: the trade must not
+    /// fill, but the halt still has to be saved, so the caller returns
+    /// `TradeOutcome::Halted` rather than an error. Pools holding fewer than `breaker_min_liquidity`
+    /// shares before the trade are not checked, since any trade swings a thin
+    /// pool.
+    pub fn trip_circuit_breaker(
+        &mut self,
+        history: &PriceHistory,
+        before: (u64, u64),
+        after: (u64, u64),
+        now: i64,
+    ) -> bool {
+        let Some(breaker) = self.circuit_breaker() else {
+            return false;
+        };
+        let Some(trip) = breaker.check(
+            before,
+            after,
+            history.last_price,
+            history.samples_newest(),
+            now,
+        ) else {
+            return false;
+        };
+        self.status = Status::Halted;
+        self.halted_until = trip.halted_until;
+        msg!(
+            "Circuit breaker tripped: trade would move YES {} bps in {}s, rejected and halted until {}",
+            trip.price_move_bps,
+            self.price_move_window,
+            self.halted_until
+        );
+        true
+    }
+
+    /// Counts a filled trade of `volume` collateral and records the YES
+    /// price of the shares outstanding after it.
+    pub fn record_trade(
+        &mut self,
+        profile: &mut CreatorProfile,
+        history: &mut PriceHistory,
+        after: (u64, u64),
+        volume: u64,
+        now: i64,
+    ) {
+        self.trade_count = self.trade_count.saturating_add(1);
+        profile.total_volume = profile.total_volume.saturating_add(volume);
+        history.record(now, yes_price(after.0, after.1), volume);
+    }
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  MARKET METADATA: long-form details kept out of `Market`
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Written one field per transaction (fields are too large to fit together)
+// and frozen once the market has traded, so bettors know what they bet on.
+
+pub const MAX_DESCRIPTION_LEN: usize = 800;
+pub const MAX_RULES_LEN: usize = 800;
+pub const MAX_CATEGORY_LEN: usize = 32;
+pub const MAX_TAGS: usize = 8;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_SOURCES: usize = 4;
+pub const MAX_SOURCE_LEN: usize = 200;
+
+#[derive(InitSpace)]
+#[account]
+pub struct MarketMetadata {
+    pub market: Pubkey,
+    #[max_len(MAX_DESCRIPTION_LEN)]
+    pub description: String,
+    #[max_len(MAX_RULES_LEN)]
+    pub rules: String, // How the resolver decides the outcome
+    #[max_len(MAX_TAGS, MAX_TAG_LEN)]
+    pub tags: Vec<String>,
+    #[max_len(MAX_SOURCES, MAX_SOURCE_LEN)]
+    pub sources: Vec<String>, // Resolution source URLs
+    pub content_hash: [u8; 32], // Hash of the off-chain market document
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum MetadataField {
+    Description(String),
+    Rules(String),
+    Tags(Vec<String>),
+    Sources(Vec<String>),
+    ContentHash([u8; 32]),
+}
+
+/// One per creator at [b"creator", creator]. `next_market_id` seeds the
+/// creator's next market, so ids never collide.
+#[derive(InitSpace)]
+#[account]
+pub struct CreatorProfile {
+    pub creator: Pubkey,
+    pub next_market_id: u64,
+    pub markets_created: u64,
+    pub total_volume: u64, // Collateral traded across all of the creator's markets
+    pub disputes_lost: u64,
+    pub bump: u8,
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  MARKET REGISTRY: deterministic discovery without getProgramAccounts
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Market number n lives in page n / REGISTRY_PAGE_SIZE at slot
+// n % REGISTRY_PAGE_SIZE, both globally and per category. Clients read the
+// counter, then fetch pages [b"registry_page", page] or
+// [b"category_page", category, page].
+
+pub const REGISTRY_PAGE_SIZE: u64 = 64;
+
+#[derive(InitSpace)]
+#[account]
+pub struct MarketRegistry {
+    pub total_markets: u64,
+    pub bump: u8,
+}
+
+#[derive(InitSpace)]
+#[account]
+pub struct CategoryRegistry {
+    #[max_len(MAX_CATEGORY_LEN)]
+    pub category: String,
+    pub total_markets: u64,
+    pub bump: u8,
+}
+
+#[derive(InitSpace)]
+#[account]
+pub struct RegistryPage {
+    pub page_index: u64,
+    #[max_len(REGISTRY_PAGE_SIZE)]
+    pub markets: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+#[derive(InitSpace)]
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,    // Manages the collateral whitelist
+    pub treasury: Pubkey, // Collects protocol fees and slashed or forfeited bonds
+    pub bump: u8,
+}
+
+/// Longest dispute window the admin can set, so claims can't be held back
+/// indefinitely.
+pub const MAX_DISPUTE_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+/// Whitelist entry for a collateral mint, one PDA per mint.
+#[derive(InitSpace)]
+#[account]
+pub struct CollateralInfo {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub min_trade_size: u64, // Smallest buy, in raw collateral units
+    pub max_fee: u64,        // Highest market fee allowed, in BPS
+    pub enabled: bool,       // Disabling only blocks new markets
+    pub creator_bond: u64,   // Locked by the creator per market, in raw collateral units
+    pub dispute_window: i64, // Seconds a resolution stays disputable; required with a bond
+    pub bump: u8,
+}
+
+/// Optional exposure limits chosen by the market creator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct MarketLimits {
+    pub max_open_interest: Option<u64>,
+    pub max_position_per_wallet: Option<u64>,
+    pub max_price_move_bps: Option<u64>,
+    pub price_move_window: i64,
+    pub halt_cooldown: i64,
+    /// Pool size, in outstanding shares, below which the breaker is skipped
+    pub breaker_min_liquidity: u64,
+}
+
+/// Liquidity the creator deposits at creation, split so the market opens at
+/// `yes_bps` / 10_000 instead of 50/50.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitialLiquidity {
+    pub amount: u64,
+    pub yes_bps: u64,
+}
+
+#[derive(InitSpace)]
+#[account]
+pub struct Position {
+    pub market: Pubkey,
+    pub owner: Pubkey,
+    pub yes_shares: u64,
+    pub no_shares: u64,
+    pub collateral_deposited: u64, // Net collateral paid into the vault
+    pub collateral_withdrawn: u64, // Collateral paid out by sells and claims
+    pub bump: u8,
+}
+
+impl Position {
+    /// Fills in identity fields the first time an `init_if_needed` position is used.
+    pub fn ensure_initialized(&mut self, market: Pubkey, owner: Pubkey, bump: u8) {
+        if self.owner == Pubkey::default() {
+            self.market = market;
+            self.owner = owner;
+            self.bump = bump;
+        }
+    }
+
+    /// Shares leave through sells and claims, including ones received by transfer,
+    /// so the tracked balance saturates at zero instead of failing.
+    pub fn remove_shares(&mut self, shares: u64, is_yes: bool, collateral_out: u64) {
+        if is_yes {
+            self.yes_shares = self.yes_shares.saturating_sub(shares);
+        } else {
+            self.no_shares = self.no_shares.saturating_sub(shares);
+        }
+        self.collateral_withdrawn = self.collateral_withdrawn.saturating_add(collateral_out);
+    }
+
+    pub fn total_shares(&self) -> u64 {
+        self.yes_shares.saturating_add(self.no_shares)
+    }
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  PRICE HISTORY: ring buffer of samples + TWAP accumulator
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// YES price is the implied probability yes_supply / (yes_supply + no_supply),
+// scaled by PRICE_SCALE. TWAP between two samples a and b is
+//   (b.cumulative_price - a.cumulative_price) / (b.timestamp - a.timestamp)
+
+pub use predection_math::{yes_price, PRICE_HISTORY_CAPACITY, PRICE_SCALE};
+
+#[zero_copy]
+pub struct PriceSample {
+    pub timestamp: i64,
+    pub yes_price: u64,        // Price after the trades in this second
+    pub volume: u64,           // Collateral traded in this second
+    pub cumulative_price: u64, // Accumulator value at `timestamp`
+}
+
+#[account(zero_copy)]
+pub struct PriceHistory {
+    pub market: Pubkey,
+    pub head: u64,  // Index of the newest sample
+    pub count: u64, // Number of valid samples (<= capacity)
+    pub last_update_timestamp: i64,
+    pub last_price: u64,
+    pub cumulative_price: u64, // Sum of price * seconds, wraps on overflow
+    pub bump: u8,
+    pub _padding: [u8; 7],
+    pub samples: [PriceSample; PRICE_HISTORY_CAPACITY],
+}
+
+impl PriceHistory {
+    pub fn latest(&self) -> Option<&PriceSample> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(&self.samples[self.head as usize])
+    }
+
+    /// Samples from newest to oldest.
+    pub fn iter_newest(&self) -> impl Iterator<Item = &PriceSample> {
+        (0..self.count).map(move |i| &self.samples[ring_index(self.head, i)])
+    }
+
+    /// (timestamp, price) pairs from newest to oldest, as the breaker reads them.
+    pub fn samples_newest(&self) -> impl Iterator<Item = (i64, u64)> + '_ {
+        self.iter_newest().map(|s| (s.timestamp, s.yes_price))
+    }
+
+    /// Accumulator extrapolated to `now` using the last recorded price.
+    pub fn cumulative_price_at(&self, now: i64) -> u64 {
+        let elapsed = now.saturating_sub(self.last_update_timestamp).max(0) as u64;
+        self.cumulative_price
+            .wrapping_add(self.last_price.wrapping_mul(elapsed))
+    }
+
+    /// Records a trade. Trades in the same second share one sample.
+    pub fn record(&mut self, now: i64, yes_price: u64, volume: u64) {
+        if self.count > 0 {
+            self.cumulative_price = self.cumulative_price_at(now);
+        }
+
+        let slot = next_slot(
+            self.head,
+            self.count,
+            self.latest().map(|s| s.timestamp),
+            now,
+        );
+        if slot.same_second {
+            let sample = &mut self.samples[self.head as usize];
+            sample.yes_price = yes_price;
+            sample.volume = sample.volume.saturating_add(volume);
+        } else {
+            self.head = slot.head;
+            self.count = slot.count;
+            self.samples[self.head as usize] = PriceSample {
+                timestamp: now,
+                yes_price,
+                volume,
+                cumulative_price: self.cumulative_price,
+            };
+        }
+
+        self.last_update_timestamp = now;
+        self.last_price = yes_price;
+    }
 }
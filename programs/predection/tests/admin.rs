@@ -0,0 +1,179 @@
+mod common;
+
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token;
+use common::*;
+use predection::errors::Errors;
+use predection::states::{CollateralInfo, ProtocolConfig, MAX_DISPUTE_WINDOW};
+use predection_sdk::pda;
+
+#[test]
+fn admin_updates_collateral_settings() {
+    let mut env = TestEnv::new();
+    let settings = CollateralSettings {
+        min_trade_size: USDC,
+        max_fee: 500,
+        creator_bond: 10 * USDC,
+        dispute_window: 600,
+    };
+
+    env.update_collateral_as(&env.admin.clone(), settings, false)
+        .unwrap();
+
+    let info = env.collateral_info();
+    assert_eq!(info.min_trade_size, USDC);
+    assert_eq!(info.max_fee, 500);
+    assert_eq!(info.creator_bond, 10 * USDC);
+    assert_eq!(info.dispute_window, 600);
+    assert!(!info.enabled);
+}
+
+#[test]
+fn bonded_collateral_needs_a_bounded_dispute_window() {
+    let mut env = TestEnv::new();
+    let admin = env.admin;
+    let bonded = |dispute_window| CollateralSettings {
+        creator_bond: 10 * USDC,
+        dispute_window,
+        ..CollateralSettings::default()
+    };
+
+    for dispute_window in [0, -1, MAX_DISPUTE_WINDOW + 1] {
+        let result = env.update_collateral_as(&admin, bonded(dispute_window), true);
+        assert_error(result, Errors::InvalidDisputeWindow);
+    }
+    env.update_collateral_as(&admin, bonded(MAX_DISPUTE_WINDOW), true)
+        .unwrap();
+    // Without a bond there is nothing to dispute
+    env.update_collateral_as(&admin, CollateralSettings::default(), true)
+        .unwrap();
+}
+
+#[test]
+fn collateral_max_fee_is_capped_at_ten_percent() {
+    let mut env = TestEnv::new();
+    let settings = CollateralSettings {
+        max_fee: 1001,
+        ..CollateralSettings::default()
+    };
+
+    let result = env.update_collateral_as(&env.admin.clone(), settings, true);
+    assert_error(result, Errors::FeeIsTooHigh);
+}
+
+#[test]
+fn only_the_admin_updates_collateral() {
+    let mut env = TestEnv::new();
+    let outsider = env.user();
+
+    let result = env.update_collateral_as(&outsider, CollateralSettings::default(), false);
+    assert_error(result, Errors::InvalidProtocolAdmin);
+    assert!(env.collateral_info().enabled);
+}
+
+fn init_protocol_config(
+    svm: &mut Svm,
+    admin: &Pubkey,
+    program_data: Pubkey,
+    treasury: Pubkey,
+) -> TxResult {
+    let ix = build(
+        predection::accounts::InitProtocolConfig {
+            admin: *admin,
+            config: pda::config().0,
+            program: predection::ID,
+            program_data,
+            system_program: system_program::ID,
+        },
+        predection::instruction::InitProtocolConfig { treasury },
+    );
+    svm.send(&[ix], &[*admin])
+}
+
+#[test]
+fn upgrade_authority_claims_the_admin_role_once() {
+    let mut svm = Svm::new();
+    let admin = Pubkey::new_unique();
+    let outsider = Pubkey::new_unique();
+    let treasury = Pubkey::new_unique();
+    svm.airdrop(&admin, 10);
+    svm.airdrop(&outsider, 10);
+    svm.deploy(admin);
+    let program_data = programdata_address(&predection::ID);
+
+    assert_error(
+        init_protocol_config(&mut svm, &outsider, program_data, treasury),
+        Errors::InvalidProtocolAdmin,
+    );
+
+    // Another program's deployment can't vouch for the admin
+    let other = Pubkey::new_unique();
+    svm.deploy_program(other, predection::entry, admin);
+    assert_error(
+        init_protocol_config(&mut svm, &admin, programdata_address(&other), treasury),
+        Errors::InvalidProgramData,
+    );
+
+    init_protocol_config(&mut svm, &admin, program_data, treasury).unwrap();
+    let config: ProtocolConfig = svm.anchor_account(&pda::config().0);
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.treasury, treasury);
+
+    // `init` finds the config already allocated
+    assert_eq!(
+        init_protocol_config(&mut svm, &admin, program_data, treasury),
+        Err(TxError::Instruction {
+            index: 0,
+            error: ProgramError::Custom(0),
+        })
+    );
+}
+
+fn add_collateral(env: &mut TestEnv, admin: &Pubkey, mint: &Pubkey, max_fee: u64) -> TxResult {
+    let ix = build(
+        predection::accounts::AddCollateral {
+            admin: *admin,
+            config: pda::config().0,
+            collateral_mint: *mint,
+            collateral_info: pda::collateral_info(mint).0,
+            system_program: system_program::ID,
+        },
+        predection::instruction::AddCollateral {
+            min_trade_size: USDC,
+            max_fee,
+            creator_bond: 5 * USDC,
+            dispute_window: 600,
+        },
+    );
+    env.svm.send(&[ix], &[*admin])
+}
+
+#[test]
+fn admin_whitelists_new_collateral() {
+    let mut env = TestEnv::new();
+    let admin = env.admin;
+    let outsider = env.user();
+    let pyusd = Pubkey::new_unique();
+    env.svm
+        .set_mint(pyusd, spl_token::ID, Pubkey::new_unique(), 0, 6);
+
+    assert_error(
+        add_collateral(&mut env, &outsider, &pyusd, 500),
+        Errors::InvalidProtocolAdmin,
+    );
+    assert_error(
+        add_collateral(&mut env, &admin, &pyusd, 1001),
+        Errors::FeeIsTooHigh,
+    );
+
+    add_collateral(&mut env, &admin, &pyusd, 500).unwrap();
+    let info: CollateralInfo = env.svm.anchor_account(&pda::collateral_info(&pyusd).0);
+    assert_eq!(info.mint, pyusd);
+    assert_eq!(info.decimals, 6);
+    assert_eq!(info.min_trade_size, USDC);
+    assert_eq!(info.max_fee, 500);
+    assert_eq!(info.creator_bond, 5 * USDC);
+    assert_eq!(info.dispute_window, 600);
+    assert!(info.enabled);
+}
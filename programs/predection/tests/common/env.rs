@@ -0,0 +1,471 @@
+//! A deployed program with a whitelisted USDC-like collateral. Markets are
+//! created through `initialize`; helpers for later states (supplies,
+//! resolutions, traders' accounts) write them directly to keep tests short.
+
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AnchorDeserialize, InstructionData, Space, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token::spl_token;
+use predection::errors::Errors;
+use predection::states::{
+    CategoryRegistry, CollateralInfo, CreatorProfile, Market, MarketLimits, MarketMetadata,
+    MarketRegistry, Position, ProtocolConfig, Resolution, Status, TradeOutcome,
+};
+use predection_sdk::instructions::{self, InitializeParams};
+use predection_sdk::pda;
+
+use super::svm::{Svm, TxError, TxResult};
+
+pub const USDC_DECIMALS: u8 = 6;
+/// One whole USDC in raw units.
+pub const USDC: u64 = 1_000_000;
+/// Seconds between market creation and close.
+pub const MARKET_DURATION: i64 = 3600;
+
+/// Whitelist settings for a collateral mint.
+#[derive(Clone, Copy)]
+pub struct CollateralSettings {
+    pub min_trade_size: u64,
+    pub max_fee: u64,
+    pub creator_bond: u64,
+    pub dispute_window: i64,
+}
+
+impl Default for CollateralSettings {
+    fn default() -> Self {
+        Self {
+            min_trade_size: 0,
+            max_fee: 1000,
+            creator_bond: 0,
+            dispute_window: 0,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TestEnv {
+    pub svm: Svm,
+    /// Upgrade authority, and so protocol admin.
+    pub admin: Pubkey,
+    /// Protocol fee collector, as set in the protocol config.
+    pub treasury: Pubkey,
+    /// The whitelisted collateral mint.
+    pub usdc: Pubkey,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        Self::with_collateral(CollateralSettings::default())
+    }
+
+    /// Deploys the program with a claimed protocol config and a 6-decimal
+    /// SPL Token collateral whitelisted with `settings`.
+    pub fn with_collateral(settings: CollateralSettings) -> Self {
+        Self::with_collateral_mint(Pubkey::new_unique(), USDC_DECIMALS, settings)
+    }
+
+    /// Same with `usdc` as the collateral, e.g. the native mint for wSOL
+    /// markets.
+    pub fn with_collateral_mint(usdc: Pubkey, decimals: u8, settings: CollateralSettings) -> Self {
+        let mut svm = Svm::new();
+        let admin = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        svm.airdrop(&admin, 100);
+        svm.deploy(admin);
+
+        let (config, bump) = pda::config();
+        svm.set_anchor_account(
+            config,
+            &ProtocolConfig {
+                admin,
+                treasury,
+                bump,
+            },
+            8 + ProtocolConfig::INIT_SPACE,
+        );
+
+        svm.set_mint(usdc, spl_token::ID, Pubkey::new_unique(), 0, decimals);
+        let (collateral_info, bump) = pda::collateral_info(&usdc);
+        svm.set_anchor_account(
+            collateral_info,
+            &CollateralInfo {
+                mint: usdc,
+                decimals,
+                min_trade_size: settings.min_trade_size,
+                max_fee: settings.max_fee,
+                enabled: true,
+                creator_bond: settings.creator_bond,
+                dispute_window: settings.dispute_window,
+                bump,
+            },
+            8 + CollateralInfo::INIT_SPACE,
+        );
+
+        let mut env = Self {
+            svm,
+            admin,
+            treasury,
+            usdc,
+        };
+        env.svm.airdrop(&env.treasury, 1);
+        env.fund(&env.treasury.clone(), 0);
+        env
+    }
+
+    /// A wallet with 100 SOL.
+    pub fn user(&mut self) -> Pubkey {
+        let user = Pubkey::new_unique();
+        self.svm.airdrop(&user, 100);
+        user
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  TOKENS
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    pub fn token_program_of(&self, mint: &Pubkey) -> Pubkey {
+        self.svm.account(mint).expect("mint exists").owner
+    }
+
+    pub fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program_of(mint))
+    }
+
+    /// Sets `owner`'s USDC ATA to hold `amount`.
+    pub fn fund(&mut self, owner: &Pubkey, amount: u64) -> Pubkey {
+        let ata = self.ata(owner, &self.usdc);
+        self.svm
+            .set_token_account(ata, spl_token::ID, self.usdc, *owner, amount);
+        ata
+    }
+
+    pub fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.svm.token_account(&self.ata(owner, mint)).amount
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  PROTOCOL ADMIN
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    pub fn update_collateral_as(
+        &mut self,
+        admin: &Pubkey,
+        settings: CollateralSettings,
+        enabled: bool,
+    ) -> TxResult {
+        let ix = build(
+            predection::accounts::UpdateCollateral {
+                admin: *admin,
+                config: pda::config().0,
+                collateral_info: pda::collateral_info(&self.usdc).0,
+            },
+            predection::instruction::UpdateCollateral {
+                min_trade_size: settings.min_trade_size,
+                max_fee: settings.max_fee,
+                creator_bond: settings.creator_bond,
+                dispute_window: settings.dispute_window,
+                enabled,
+            },
+        );
+        self.svm.send(&[ix], &[*admin])
+    }
+
+    pub fn collateral_info(&self) -> CollateralInfo {
+        self.svm.anchor_account(&pda::collateral_info(&self.usdc).0)
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  MARKETS
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    /// `initialize` arguments for a one-hour, 1% fee USDC market with no
+    /// limits and no initial liquidity, as `creator`'s next market.
+    pub fn market_params(&self, creator: &Pubkey) -> InitializeParams {
+        let category = "crypto".to_string();
+        let profile = pda::creator_profile(creator).0;
+        let category_registry = pda::category_registry(&category).0;
+        InitializeParams {
+            market_id: self
+                .try_anchor_account::<CreatorProfile>(&profile)
+                .map_or(0, |profile| profile.next_market_id),
+            resolver: *creator,
+            collateral_mint: self.usdc,
+            token_program: spl_token::ID,
+            outcome_token_program: spl_token::ID,
+            protocol_fee_collector: self.treasury,
+            question: "Will SOL close above $200 this week?".to_string(),
+            duration_time: MARKET_DURATION,
+            fee: 100,
+            limits: MarketLimits {
+                max_open_interest: None,
+                max_position_per_wallet: None,
+                max_price_move_bps: None,
+                price_move_window: 0,
+                halt_cooldown: 0,
+                breaker_min_liquidity: 0,
+            },
+            allowlist_root: None,
+            metadata_uri: None,
+            category,
+            initial_liquidity: None,
+            registry_total_markets: self
+                .try_anchor_account::<MarketRegistry>(&pda::registry().0)
+                .map_or(0, |registry| registry.total_markets),
+            category_total_markets: self
+                .try_anchor_account::<CategoryRegistry>(&category_registry)
+                .map_or(0, |registry| registry.total_markets),
+        }
+    }
+
+    fn try_anchor_account<T: anchor_lang::AccountDeserialize>(
+        &self,
+        address: &Pubkey,
+    ) -> Option<T> {
+        self.svm
+            .account(address)
+            .map(|_| self.svm.anchor_account(address))
+    }
+
+    pub fn initialize(&mut self, creator: &Pubkey, params: InitializeParams) -> TxResult {
+        let ix = instructions::initialize(creator, params);
+        self.svm.send(&[ix], &[*creator])
+    }
+
+    /// A fresh creator holding 1,000 USDC and a market of theirs closing in
+    /// one hour.
+    pub fn create_market(&mut self) -> (Pubkey, Pubkey) {
+        self.create_market_with(|_| {})
+    }
+
+    /// Like `create_market`, with `customize` applied to the arguments.
+    pub fn create_market_with(
+        &mut self,
+        customize: impl FnOnce(&mut InitializeParams),
+    ) -> (Pubkey, Pubkey) {
+        let creator = self.user();
+        self.fund(&creator, 1_000 * USDC);
+        let mut params = self.market_params(&creator);
+        customize(&mut params);
+        let market = pda::market(&creator, params.market_id).0;
+        self.initialize(&creator, params).unwrap();
+        (creator, market)
+    }
+
+    pub fn market(&self, market: &Pubkey) -> Market {
+        self.svm.anchor_account(market)
+    }
+
+    pub fn set_market(&mut self, market: &Pubkey, state: &Market) {
+        self.svm
+            .set_anchor_account(*market, state, 8 + Market::INIT_SPACE);
+    }
+
+    /// Applies `update` to the stored market.
+    pub fn update_market(&mut self, market: &Pubkey, update: impl FnOnce(&mut Market)) {
+        let mut state = self.market(market);
+        update(&mut state);
+        self.set_market(market, &state);
+    }
+
+    /// Sets the outcome mint supplies, as trades on both sides would.
+    pub fn set_supplies(&mut self, market: &Pubkey, yes: u64, no: u64) {
+        let state = self.market(market);
+        self.svm
+            .set_mint(state.yes_mint, spl_token::ID, *market, yes, USDC_DECIMALS);
+        self.svm
+            .set_mint(state.no_mint, spl_token::ID, *market, no, USDC_DECIMALS);
+    }
+
+    /// Marks the market resolved at the current time.
+    pub fn set_resolved(&mut self, market: &Pubkey, resolution: Resolution) {
+        let now = self.svm.now();
+        self.update_market(market, |state| {
+            state.status = Status::Resolved;
+            state.resolution = Some(resolution);
+            state.option = match resolution {
+                Resolution::Yes => Some(true),
+                Resolution::No => Some(false),
+                Resolution::Invalid => None,
+            };
+            state.resolved_at = now;
+        });
+    }
+
+    /// A trader holding `amount` USDC, with the outcome ATAs and position a
+    /// first buy would have created.
+    pub fn trader(&mut self, market: &Pubkey, amount: u64) -> Pubkey {
+        let trader = self.user();
+        self.fund(&trader, amount);
+
+        let state = self.market(market);
+        for mint in [state.yes_mint, state.no_mint] {
+            let ata = self.ata(&trader, &mint);
+            self.svm
+                .set_token_account(ata, spl_token::ID, mint, trader, 0);
+        }
+        let (position, bump) = pda::position(market, &trader);
+        self.svm.set_anchor_account(
+            position,
+            &Position {
+                market: *market,
+                owner: trader,
+                yes_shares: 0,
+                no_shares: 0,
+                collateral_deposited: 0,
+                collateral_withdrawn: 0,
+                bump,
+            },
+            8 + Position::INIT_SPACE,
+        );
+        trader
+    }
+
+    /// The metadata account, as the creator's first update would create it.
+    pub fn init_metadata(&mut self, market: &Pubkey) -> Pubkey {
+        let (metadata, bump) = pda::metadata(market);
+        self.svm.set_anchor_account(
+            metadata,
+            &MarketMetadata {
+                market: *market,
+                description: String::new(),
+                rules: String::new(),
+                tags: Vec::new(),
+                sources: Vec::new(),
+                content_hash: [0; 32],
+                bump,
+            },
+            8 + MarketMetadata::INIT_SPACE,
+        );
+        metadata
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  INSTRUCTIONS
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    pub fn buy_ix(
+        &self,
+        market: &Pubkey,
+        trader: &Pubkey,
+        amount: u64,
+        is_yes: bool,
+        proof: Vec<[u8; 32]>,
+    ) -> Instruction {
+        let state = self.market(market);
+        instructions::buy_share(
+            market,
+            &state,
+            trader,
+            &spl_token::ID,
+            amount,
+            is_yes,
+            proof,
+            false,
+        )
+    }
+
+    pub fn buy(&mut self, market: &Pubkey, trader: &Pubkey, amount: u64, is_yes: bool) -> TxResult {
+        let ix = self.buy_ix(market, trader, amount, is_yes, Vec::new());
+        self.svm.send(&[ix], &[*trader])
+    }
+
+    pub fn sell(
+        &mut self,
+        market: &Pubkey,
+        trader: &Pubkey,
+        amount: u64,
+        is_yes: bool,
+    ) -> TxResult {
+        let state = self.market(market);
+        let ix = instructions::sell_share(
+            market,
+            &state,
+            trader,
+            &spl_token::ID,
+            amount,
+            is_yes,
+            false,
+        );
+        self.svm.send(&[ix], &[*trader])
+    }
+
+    /// What the last successful `buy_share` or `sell_share` returned.
+    pub fn trade_outcome(&self) -> TradeOutcome {
+        let (program_id, data) = self.svm.return_data().expect("trades return their outcome");
+        assert_eq!(program_id, predection::ID);
+        TradeOutcome::try_from_slice(&data).unwrap()
+    }
+
+    /// Resolves as `resolver`, which need not be the market's.
+    pub fn resolve_as(
+        &mut self,
+        resolver: &Pubkey,
+        market: &Pubkey,
+        resolution: Resolution,
+    ) -> TxResult {
+        let mut state = self.market(market);
+        state.resolver = *resolver;
+        let ix = instructions::resolve_market(market, &state, resolution);
+        self.svm.send(&[ix], &[*resolver])
+    }
+
+    pub fn resolve(&mut self, market: &Pubkey, resolution: Resolution) -> TxResult {
+        let resolver = self.market(market).resolver;
+        self.resolve_as(&resolver, market, resolution)
+    }
+
+    pub fn claim(&mut self, market: &Pubkey, trader: &Pubkey) -> TxResult {
+        let state = self.market(market);
+        let ix = instructions::claim_winning(market, &state, trader, &spl_token::ID, false);
+        self.svm.send(&[ix], &[*trader])
+    }
+}
+
+impl Default for TestEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An instruction to the predection program from its generated client types.
+pub fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: predection::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// Swaps one account of `ix` for another.
+pub fn replace_account(ix: &mut Instruction, from: &Pubkey, to: &Pubkey) {
+    let meta = ix
+        .accounts
+        .iter_mut()
+        .find(|meta| meta.pubkey == *from)
+        .unwrap_or_else(|| panic!("{from} is not in the instruction"));
+    meta.pubkey = *to;
+}
+
+/// The custom error code a failed transaction returned, if any.
+pub fn error_code(result: &TxResult) -> Option<u32> {
+    match result {
+        Err(TxError::Instruction {
+            error: ProgramError::Custom(code),
+            ..
+        }) => Some(*code),
+        _ => None,
+    }
+}
+
+/// `expected` is any Anchor error enum: `Errors`, Anchor's own `ErrorCode`,
+/// or an integrating program's errors.
+#[track_caller]
+pub fn assert_error<E: Into<u32> + std::fmt::Debug + Copy>(result: TxResult, expected: E) {
+    let code = expected.into();
+    assert_eq!(
+        error_code(&result),
+        Some(code),
+        "expected {expected:?} ({code}), got {result:?}"
+    );
+}
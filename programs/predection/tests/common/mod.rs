@@ -0,0 +1,9 @@
+//! Shared harness for the integration tests. Each test builds its own
+//! `TestEnv`, so tests are independent and run in parallel.
+#![allow(dead_code, unused_imports)]
+
+pub mod env;
+pub mod svm;
+
+pub use env::*;
+pub use svm::*;
@@ -0,0 +1,948 @@
+//! Minimal in-process runtime: runs deployed programs' entrypoints natively
+//! over an in-memory account store.
+//!
+//! Accounts are laid out exactly as the BPF loader serializes them and read
+//! back through `entrypoint::deserialize`, so duplicate accounts, signer and
+//! writable flags behave as on chain. Clock and rent come from syscall stubs,
+//! which is what lets tests warp time.
+//!
+//! Cross-program invocations run too: the harness workspace (harness/) patches
+//! `solana-cpi` so that off-chain invokes and return data reach the stubs here, which
+//! serialize the callee's accounts the same way and write its changes back
+//! into the caller's. The System, SPL Token, Token-2022 and Associated Token
+//! programs are built in. As on chain, signer and writable privileges must
+//! be held or signed for with the caller's seeds, only an account's owner
+//! may change its data or debit it, and a failed invocation fails the whole
+//! transaction whatever the caller does with the error.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::{AccountInfo, Clock, ProgramError, Pubkey, Rent};
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::solana_program::entrypoint::{
+    deserialize, ProgramResult, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{system_program, AccountDeserialize, AccountSerialize, Discriminator, ZeroCopy};
+use anchor_spl::associated_token::{self, spl_associated_token_account};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{self, extension::StateWithExtensions};
+use solana_cpi::stubs::{set_cpi_stubs, CpiStubs};
+use solana_sysvar::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+/// Clock at genesis; tests only ever move it forward.
+pub const GENESIS_TIMESTAMP: i64 = 1_700_000_000;
+const SLOTS_PER_SECOND: u64 = 2;
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl Account {
+    /// Rent-exempt, non-executable account holding `data`.
+    pub fn new(data: Vec<u8>, owner: Pubkey) -> Self {
+        Self {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner,
+            executable: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxError {
+    /// A top-level instruction returned an error.
+    Instruction {
+        index: usize,
+        error: ProgramError,
+    },
+    MissingSigner(Pubkey),
+    ReadonlyModified(Pubkey),
+    UnbalancedLamports {
+        index: usize,
+    },
+    UnknownProgram(Pubkey),
+    /// A program changed the data or owner of, or debited, an account it
+    /// does not own.
+    ExternalAccountModified(Pubkey),
+    /// An invocation asked for a signer or writable account the caller
+    /// could not grant.
+    PrivilegeEscalation(Pubkey),
+    /// An invocation named an account the caller did not pass.
+    MissingAccount(Pubkey),
+    /// A program other than the caller was invoked while already running.
+    Reentrancy(Pubkey),
+    CallDepthExceeded,
+    /// A writable account was left with lamports but not rent exempt.
+    InsufficientFundsForRent(Pubkey),
+}
+
+pub type TxResult = Result<(), TxError>;
+
+/// The `entry` function Anchor generates for a program.
+pub type Entrypoint = for<'a> fn(&Pubkey, &'a [AccountInfo<'a>], &[u8]) -> ProgramResult;
+
+#[derive(Clone)]
+pub struct Svm {
+    accounts: HashMap<Pubkey, Account>,
+    programs: HashMap<Pubkey, Entrypoint>,
+    clock: Clock,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+            set_cpi_stubs(Box::new(Stubs));
+        });
+
+        let mut svm = Self {
+            accounts: HashMap::new(),
+            programs: HashMap::new(),
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: GENESIS_TIMESTAMP,
+                ..Clock::default()
+            },
+            return_data: None,
+        };
+        let builtins: [(Pubkey, Entrypoint); 4] = [
+            (system_program::ID, process_system_instruction),
+            (spl_token::ID, spl_token::processor::Processor::process),
+            (
+                spl_token_2022::ID,
+                spl_token_2022::processor::Processor::process,
+            ),
+            (
+                associated_token::ID,
+                spl_associated_token_account::processor::process_instruction,
+            ),
+        ];
+        for (program, entrypoint) in builtins {
+            svm.programs.insert(program, entrypoint);
+            svm.set_account(
+                program,
+                Account {
+                    executable: true,
+                    ..Account::new(Vec::new(), NATIVE_LOADER_ID)
+                },
+            );
+        }
+        svm
+    }
+
+    /// Adds predection as an upgradeable deployment owned by
+    /// `upgrade_authority`.
+    pub fn deploy(&mut self, upgrade_authority: Pubkey) {
+        self.deploy_program(predection::ID, predection::entry, upgrade_authority);
+    }
+
+    /// Adds any Anchor program, such as one that integrates with predection.
+    pub fn deploy_program(
+        &mut self,
+        program_id: Pubkey,
+        entrypoint: Entrypoint,
+        upgrade_authority: Pubkey,
+    ) {
+        self.programs.insert(program_id, entrypoint);
+        let program_data = programdata_address(&program_id);
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend_from_slice(program_data.as_ref());
+        self.set_account(
+            program_id,
+            Account {
+                executable: true,
+                ..Account::new(program, bpf_loader_upgradeable::ID)
+            },
+        );
+
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0u64.to_le_bytes()); // slot
+        data.push(1);
+        data.extend_from_slice(upgrade_authority.as_ref());
+        self.set_account(program_data, Account::new(data, bpf_loader_upgradeable::ID));
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  ACCOUNTS
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    pub fn airdrop(&mut self, address: &Pubkey, sol: u64) {
+        let account = self
+            .accounts
+            .entry(*address)
+            .or_insert_with(|| Account::new(Vec::new(), system_program::ID));
+        account.lamports += sol * LAMPORTS_PER_SOL;
+    }
+
+    /// Writes a program-owned Anchor account padded to `space` bytes, as
+    /// `init` would have allocated it.
+    pub fn set_anchor_account<T: AccountSerialize>(
+        &mut self,
+        address: Pubkey,
+        value: &T,
+        space: usize,
+    ) {
+        let mut data = Vec::with_capacity(space);
+        value.try_serialize(&mut data).unwrap();
+        assert!(
+            data.len() <= space,
+            "{address} does not fit in {space} bytes"
+        );
+        data.resize(space, 0);
+        self.set_account(address, Account::new(data, predection::ID));
+    }
+
+    /// Decodes an Anchor account, panicking if it is missing or malformed.
+    pub fn anchor_account<T: AccountDeserialize>(&self, address: &Pubkey) -> T {
+        let account = self
+            .account(address)
+            .unwrap_or_else(|| panic!("account {address} does not exist"));
+        T::try_deserialize(&mut &account.data[..])
+            .unwrap_or_else(|e| panic!("account {address} failed to decode: {e}"))
+    }
+
+    pub fn set_zero_copy<T: ZeroCopy + Discriminator>(&mut self, address: Pubkey, value: &T) {
+        let mut data = T::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(value));
+        self.set_account(address, Account::new(data, predection::ID));
+    }
+
+    pub fn zero_copy<T: ZeroCopy>(&self, address: &Pubkey) -> T {
+        let account = self
+            .account(address)
+            .unwrap_or_else(|| panic!("account {address} does not exist"));
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<T>()])
+    }
+
+    pub fn set_mint(
+        &mut self,
+        address: Pubkey,
+        token_program: Pubkey,
+        authority: Pubkey,
+        supply: u64,
+        decimals: u8,
+    ) {
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            mint_authority: Some(authority).into(),
+            supply,
+            decimals,
+            is_initialized: true,
+            freeze_authority: None.into(),
+        }
+        .pack_into_slice(&mut data);
+        self.set_account(address, Account::new(data, token_program));
+    }
+
+    pub fn set_token_account(
+        &mut self,
+        address: Pubkey,
+        token_program: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+    ) {
+        // wSOL accounts hold their balance as lamports above the reserve
+        let rent_exempt_reserve = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+        let is_native = mint == spl_token::native_mint::ID;
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint,
+            owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: is_native.then_some(rent_exempt_reserve).into(),
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        let mut account = Account::new(data, token_program);
+        if is_native {
+            account.lamports += amount;
+        }
+        self.set_account(address, account);
+    }
+
+    /// Base state of an SPL Token or Token-2022 account.
+    pub fn token_account(&self, address: &Pubkey) -> spl_token_2022::state::Account {
+        let account = self
+            .account(address)
+            .unwrap_or_else(|| panic!("token account {address} does not exist"));
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .unwrap()
+            .base
+    }
+
+    pub fn mint(&self, address: &Pubkey) -> spl_token_2022::state::Mint {
+        let account = self
+            .account(address)
+            .unwrap_or_else(|| panic!("mint {address} does not exist"));
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+            .unwrap()
+            .base
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  CLOCK
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    pub fn now(&self) -> i64 {
+        self.clock.unix_timestamp
+    }
+
+    /// Moves the clock to `unix_timestamp`, advancing the slot with it.
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        assert!(
+            unix_timestamp >= self.clock.unix_timestamp,
+            "the clock only moves forward"
+        );
+        let elapsed = (unix_timestamp - self.clock.unix_timestamp) as u64;
+        self.clock.slot += elapsed * SLOTS_PER_SECOND;
+        self.clock.unix_timestamp = unix_timestamp;
+    }
+
+    pub fn warp_by(&mut self, seconds: i64) {
+        self.warp_to(self.clock.unix_timestamp + seconds);
+    }
+
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+    //  TRANSACTIONS
+    // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+    /// Runs `instructions` atomically. Every `is_signer` account must be in
+    /// `signers`; on failure no account changes are kept.
+    pub fn send(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> TxResult {
+        RUNTIME.with(|runtime| {
+            let mut runtime = runtime.borrow_mut();
+            runtime.clock = self.clock.clone();
+            runtime.programs = self.programs.clone();
+            runtime.return_data = None;
+        });
+
+        let snapshot = self.accounts.clone();
+        let result = instructions
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, instruction)| self.process(index, instruction, signers))
+            .and_then(|()| self.check_rent(&snapshot));
+        self.return_data = RUNTIME.with(|runtime| runtime.borrow_mut().return_data.take());
+        if result.is_err() {
+            self.accounts = snapshot;
+            self.return_data = None;
+        }
+        self.accounts.retain(|_, account| account.lamports > 0);
+        result
+    }
+
+    /// Return data left by the last successful transaction, with the
+    /// program that set it.
+    pub fn return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.return_data.clone()
+    }
+
+    fn process(&mut self, index: usize, instruction: &Instruction, signers: &[Pubkey]) -> TxResult {
+        let Some(entrypoint) = self.programs.get(&instruction.program_id).copied() else {
+            return Err(TxError::UnknownProgram(instruction.program_id));
+        };
+        for meta in &instruction.accounts {
+            if meta.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(TxError::MissingSigner(meta.pubkey));
+            }
+        }
+
+        let accounts = Accounts::of(instruction);
+        let before: Vec<Account> = accounts
+            .keys
+            .iter()
+            .map(|key| {
+                self.account(key)
+                    .cloned()
+                    .unwrap_or_else(Account::new_empty)
+            })
+            .collect();
+
+        RUNTIME.with(|runtime| runtime.borrow_mut().instruction_index = index);
+        let result = execute(entrypoint, instruction, &accounts, before);
+        // A failed invocation fails the transaction even if its caller
+        // carried on, and it is the innermost error that gets reported.
+        if let Some(failure) = RUNTIME.with(|runtime| runtime.borrow_mut().failure.take()) {
+            return Err(failure);
+        }
+        for (key, account) in accounts.keys.into_iter().zip(result?) {
+            self.accounts.insert(key, account);
+        }
+        Ok(())
+    }
+
+    /// Accounts a transaction changed must end rent exempt or empty.
+    fn check_rent(&self, snapshot: &HashMap<Pubkey, Account>) -> TxResult {
+        for (key, account) in &self.accounts {
+            if snapshot.get(key) != Some(account)
+                && account.lamports > 0
+                && account.lamports < Rent::default().minimum_balance(account.data.len())
+            {
+                return Err(TxError::InsufficientFundsForRent(*key));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Account {
+    /// What an address with no account reads as.
+    fn new_empty() -> Self {
+        Self {
+            lamports: 0,
+            data: Vec::new(),
+            owner: system_program::ID,
+            executable: false,
+        }
+    }
+}
+
+const NATIVE_LOADER_ID: Pubkey =
+    Pubkey::from_str_const("NativeLoader1111111111111111111111111111111");
+
+/// Top-level instructions are at height 1; each invocation adds one.
+const MAX_INVOKE_STACK_HEIGHT: usize = 5;
+
+pub fn programdata_address(program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  EXECUTION
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+/// An instruction's unique accounts in first-seen order, with their
+/// privileges merged across duplicate metas.
+struct Accounts {
+    keys: Vec<Pubkey>,
+    signer: Vec<bool>,
+    writable: Vec<bool>,
+    /// For each meta, the index of its account in `keys`.
+    positions: Vec<usize>,
+}
+
+impl Accounts {
+    fn of(instruction: &Instruction) -> Self {
+        let mut accounts = Self {
+            keys: Vec::new(),
+            signer: Vec::new(),
+            writable: Vec::new(),
+            positions: Vec::with_capacity(instruction.accounts.len()),
+        };
+        for meta in &instruction.accounts {
+            match accounts.keys.iter().position(|key| *key == meta.pubkey) {
+                Some(position) => {
+                    accounts.signer[position] |= meta.is_signer;
+                    accounts.writable[position] |= meta.is_writable;
+                    accounts.positions.push(position);
+                }
+                None => {
+                    accounts.keys.push(meta.pubkey);
+                    accounts.signer.push(meta.is_signer);
+                    accounts.writable.push(meta.is_writable);
+                    accounts.positions.push(accounts.keys.len() - 1);
+                }
+            }
+        }
+        accounts
+    }
+}
+
+/// A running program and the state of its accounts as of its last
+/// invocation, against which its own changes are checked.
+struct Frame {
+    program_id: Pubkey,
+    keys: Vec<Pubkey>,
+    writable: Vec<bool>,
+    state: Vec<Account>,
+}
+
+impl Frame {
+    fn position(&self, key: &Pubkey) -> Result<usize, TxError> {
+        self.keys
+            .iter()
+            .position(|other| other == key)
+            .ok_or(TxError::MissingAccount(*key))
+    }
+}
+
+/// Per-thread state of the transaction being run: stubs are process-wide
+/// but tests run on parallel threads.
+#[derive(Default)]
+struct Runtime {
+    clock: Clock,
+    programs: HashMap<Pubkey, Entrypoint>,
+    instruction_index: usize,
+    stack: Vec<Frame>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    failure: Option<TxError>,
+}
+
+thread_local! {
+    static RUNTIME: RefCell<Runtime> = RefCell::new(Runtime::default());
+}
+
+fn instruction_error(error: ProgramError) -> TxError {
+    let index = RUNTIME.with(|runtime| runtime.borrow().instruction_index);
+    TxError::Instruction { index, error }
+}
+
+/// Runs `entrypoint` over `before`, the state of `accounts`, and returns
+/// their state afterwards once the runtime's checks pass.
+fn execute(
+    entrypoint: Entrypoint,
+    instruction: &Instruction,
+    accounts: &Accounts,
+    before: Vec<Account>,
+) -> Result<Vec<Account>, TxError> {
+    let mut buffer = serialize(instruction, accounts, &before);
+    // SAFETY: `serialize` wrote the loader's input layout, and the buffer
+    // outlives every `AccountInfo` read below.
+    let (program_id, infos, data) = unsafe { deserialize(buffer.as_mut_ptr() as *mut u8) };
+    // Anchor ties the slice and account lifetimes together; leaking the
+    // handful of `Rc` clones is the simplest way to satisfy it in tests.
+    let leaked = Vec::leak(infos.clone());
+
+    RUNTIME.with(|runtime| {
+        runtime.borrow_mut().stack.push(Frame {
+            program_id: instruction.program_id,
+            keys: accounts.keys.clone(),
+            writable: accounts.writable.clone(),
+            state: before.clone(),
+        })
+    });
+    let result = entrypoint(program_id, leaked, data);
+    let frame = RUNTIME
+        .with(|runtime| runtime.borrow_mut().stack.pop())
+        .expect("frame pushed above");
+    result.map_err(instruction_error)?;
+
+    let after: Vec<Account> = (0..accounts.keys.len())
+        .map(|position| {
+            let info = &infos[accounts
+                .positions
+                .iter()
+                .position(|p| *p == position)
+                .unwrap()];
+            Account {
+                lamports: info.lamports(),
+                data: info.data.borrow().to_vec(),
+                owner: *info.owner,
+                executable: info.executable,
+            }
+        })
+        .collect();
+
+    for (position, key) in accounts.keys.iter().enumerate() {
+        check_change(
+            &frame.program_id,
+            key,
+            accounts.writable[position],
+            &frame.state[position],
+            &after[position],
+        )?;
+    }
+    let total = |accounts: &[Account]| -> u128 {
+        accounts
+            .iter()
+            .map(|account| account.lamports as u128)
+            .sum()
+    };
+    if total(&before) != total(&after) {
+        let index = RUNTIME.with(|runtime| runtime.borrow().instruction_index);
+        return Err(TxError::UnbalancedLamports { index });
+    }
+    Ok(after)
+}
+
+/// Whether `program_id` may have taken `key` from `before` to `after`.
+fn check_change(
+    program_id: &Pubkey,
+    key: &Pubkey,
+    writable: bool,
+    before: &Account,
+    after: &Account,
+) -> TxResult {
+    if before == after {
+        return Ok(());
+    }
+    if !writable || before.executable != after.executable {
+        return Err(TxError::ReadonlyModified(*key));
+    }
+    let owned = before.owner == *program_id;
+    let reassigned = before.owner != after.owner;
+    if !owned && (reassigned || before.data != after.data || after.lamports < before.lamports) {
+        return Err(TxError::ExternalAccountModified(*key));
+    }
+    if reassigned && after.data.iter().any(|byte| *byte != 0) {
+        return Err(TxError::ExternalAccountModified(*key));
+    }
+    Ok(())
+}
+
+/// Runs a cross-program invocation from the program on top of the stack,
+/// writing the callee's changes back into `account_infos`.
+fn invoke(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> Result<(), TxError> {
+    let callee = instruction.program_id;
+    let (caller, entrypoint) = RUNTIME.with(|runtime| {
+        let runtime = runtime.borrow();
+        let caller = runtime
+            .stack
+            .last()
+            .expect("invoked by a program")
+            .program_id;
+        if runtime.stack.len() >= MAX_INVOKE_STACK_HEIGHT {
+            return Err(TxError::CallDepthExceeded);
+        }
+        if callee != caller && runtime.stack.iter().any(|frame| frame.program_id == callee) {
+            return Err(TxError::Reentrancy(callee));
+        }
+        let entrypoint = runtime
+            .programs
+            .get(&callee)
+            .copied()
+            .ok_or(TxError::UnknownProgram(callee))?;
+        Ok((caller, entrypoint))
+    })?;
+
+    let signed_for = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| instruction_error(ProgramError::InvalidSeeds))?;
+
+    let accounts = Accounts::of(instruction);
+    let mut infos = Vec::with_capacity(accounts.keys.len());
+    for (position, key) in accounts.keys.iter().enumerate() {
+        let info = account_infos
+            .iter()
+            .find(|info| info.key == key)
+            .ok_or(TxError::MissingAccount(*key))?;
+        if (accounts.signer[position] && !info.is_signer && !signed_for.contains(key))
+            || (accounts.writable[position] && !info.is_writable)
+        {
+            return Err(TxError::PrivilegeEscalation(*key));
+        }
+        infos.push(info);
+    }
+    let before: Vec<Account> = infos
+        .iter()
+        .map(|info| {
+            Ok(Account {
+                lamports: info.lamports(),
+                data: info.try_borrow_data().map_err(instruction_error)?.to_vec(),
+                owner: *info.owner,
+                executable: info.executable,
+            })
+        })
+        .collect::<Result<_, TxError>>()?;
+
+    // What the caller did to these accounts so far must have been allowed,
+    // and becomes the state its later changes are checked against.
+    RUNTIME.with(|runtime| {
+        let mut runtime = runtime.borrow_mut();
+        let frame = runtime.stack.last_mut().expect("invoked by a program");
+        for (key, account) in accounts.keys.iter().zip(&before) {
+            let position = frame.position(key)?;
+            check_change(
+                &caller,
+                key,
+                frame.writable[position],
+                &frame.state[position],
+                account,
+            )?;
+            frame.state[position] = account.clone();
+        }
+        runtime.return_data = None;
+        Ok::<_, TxError>(())
+    })?;
+
+    let after = execute(entrypoint, instruction, &accounts, before.clone())?;
+
+    for ((info, before), after) in infos.iter().zip(&before).zip(&after) {
+        if before == after {
+            continue;
+        }
+        **info.try_borrow_mut_lamports().map_err(instruction_error)? = after.lamports;
+        if before.data.len() != after.data.len() {
+            info.resize(after.data.len()).map_err(instruction_error)?;
+        }
+        info.try_borrow_mut_data()
+            .map_err(instruction_error)?
+            .copy_from_slice(&after.data);
+        if before.owner != after.owner {
+            info.assign(&after.owner);
+        }
+    }
+    RUNTIME.with(|runtime| {
+        let mut runtime = runtime.borrow_mut();
+        let frame = runtime.stack.last_mut().expect("invoked by a program");
+        for (key, account) in accounts.keys.iter().zip(after) {
+            let position = frame.position(key)?;
+            frame.state[position] = account;
+        }
+        Ok(())
+    })
+}
+
+/// Lays out the instruction input the way the BPF loader does.
+fn serialize(instruction: &Instruction, accounts: &Accounts, state: &[Account]) -> Vec<u64> {
+    let mut input: Vec<u8> = Vec::new();
+    input.extend_from_slice(&(accounts.positions.len() as u64).to_le_bytes());
+
+    for (slot, position) in accounts.positions.iter().enumerate() {
+        let first_slot = accounts
+            .positions
+            .iter()
+            .position(|p| p == position)
+            .unwrap();
+        if first_slot != slot {
+            input.push(first_slot as u8);
+            input.extend_from_slice(&[0; 7]);
+            continue;
+        }
+
+        let account = &state[*position];
+        input.push(NON_DUP_MARKER);
+        input.push(accounts.signer[*position] as u8);
+        input.push(accounts.writable[*position] as u8);
+        input.push(account.executable as u8);
+        input.extend_from_slice(&[0; 4]); // original data length, set by `deserialize`
+        input.extend_from_slice(accounts.keys[*position].as_ref());
+        input.extend_from_slice(account.owner.as_ref());
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        input.resize(input.len().next_multiple_of(8), 0);
+        input.extend_from_slice(&u64::MAX.to_le_bytes()); // rent epoch
+    }
+
+    input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    input.extend_from_slice(&instruction.data);
+    input.extend_from_slice(instruction.program_id.as_ref());
+
+    // u64 words keep the lamport and length fields 8-byte aligned
+    let mut words = vec![0u64; input.len().div_ceil(8)];
+    bytemuck::cast_slice_mut::<u64, u8>(&mut words)[..input.len()].copy_from_slice(&input);
+    words
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  SYSTEM PROGRAM
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// The instructions programs invoke to create and fund accounts. Custom
+// error codes are the System program's.
+
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+const MAX_PERMITTED_DATA_LENGTH: u64 = 10 * 1024 * 1024;
+
+fn process_system_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let u64_at = |offset: usize| -> Result<u64, ProgramError> {
+        data.get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .ok_or(ProgramError::InvalidInstructionData)
+    };
+    let pubkey_at = |offset: usize| -> Result<Pubkey, ProgramError> {
+        data.get(offset..offset + 32)
+            .map(|bytes| Pubkey::new_from_array(bytes.try_into().unwrap()))
+            .ok_or(ProgramError::InvalidInstructionData)
+    };
+    let tag = data
+        .get(..4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    match (tag, accounts) {
+        // CreateAccount { lamports, space, owner }
+        (0, [from, to, ..]) => {
+            if to.lamports() > 0 {
+                return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+            }
+            system_allocate(to, u64_at(12)?)?;
+            system_assign(to, &pubkey_at(20)?)?;
+            system_transfer(from, to, u64_at(4)?)
+        }
+        // Assign { owner }
+        (1, [account, ..]) => system_assign(account, &pubkey_at(4)?),
+        // Transfer { lamports }
+        (2, [from, to, ..]) => system_transfer(from, to, u64_at(4)?),
+        // Allocate { space }
+        (8, [account, ..]) => system_allocate(account, u64_at(4)?),
+        (0 | 1 | 2 | 8, _) => Err(ProgramError::NotEnoughAccountKeys),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn system_allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !account.data_is_empty() || *account.owner != system_program::ID {
+        return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(ProgramError::InvalidArgument);
+    }
+    account.resize(space as usize)
+}
+
+fn system_assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    account.assign(owner);
+    Ok(())
+}
+
+fn system_transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !from.data_is_empty() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let remaining = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS))?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+//  SYSCALLS
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+
+struct Stubs;
+
+impl Stubs {
+    fn invoke_signed(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke(instruction, account_infos, signers_seeds).map_err(|failure| {
+            let error = match &failure {
+                TxError::Instruction { error, .. } => error.clone(),
+                _ => ProgramError::InvalidArgument,
+            };
+            RUNTIME.with(|runtime| {
+                runtime.borrow_mut().failure.get_or_insert(failure);
+            });
+            error
+        })
+    }
+
+    fn set_return_data(data: &[u8]) {
+        RUNTIME.with(|runtime| {
+            let mut runtime = runtime.borrow_mut();
+            let program_id = runtime.stack.last().expect("set by a program").program_id;
+            runtime.return_data = (!data.is_empty()).then(|| (program_id, data.to_vec()));
+        });
+    }
+
+    fn return_data() -> Option<(Pubkey, Vec<u8>)> {
+        RUNTIME.with(|runtime| runtime.borrow().return_data.clone())
+    }
+}
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = RUNTIME.with(|runtime| runtime.borrow().clock.clone());
+        // SAFETY: the caller passes a pointer to a `Clock`
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // SAFETY: the caller passes a pointer to a `Rent`
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Self::invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        Self::set_return_data(data)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Self::return_data()
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        RUNTIME.with(|runtime| runtime.borrow().stack.len() as u64)
+    }
+}
+
+impl CpiStubs for Stubs {
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Self::invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        Self::set_return_data(data)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Self::return_data()
+    }
+}
@@ -0,0 +1,338 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_spl::associated_token;
+use anchor_spl::token::spl_token;
+use common::*;
+use predection::errors::Errors;
+use predection::states::{CreatorProfile, Resolution, Status, RESOLUTION_GRACE_PERIOD};
+use predection_sdk::{instructions, pda};
+
+const DISPUTE_WINDOW: i64 = 600;
+
+fn bonded_env() -> TestEnv {
+    TestEnv::with_collateral(CollateralSettings {
+        creator_bond: 10 * USDC,
+        dispute_window: DISPUTE_WINDOW,
+        ..CollateralSettings::default()
+    })
+}
+
+fn dispute(env: &mut TestEnv, market: &Pubkey, disputer: &Pubkey) -> TxResult {
+    let ix = build(
+        predection::accounts::DisputeMarket {
+            disputer: *disputer,
+            market: *market,
+            collateral_mint: env.usdc,
+            disputer_collateral_ata: env.ata(disputer, &env.usdc),
+            bond_vault: pda::bond_vault(market).0,
+            token_program: spl_token::ID,
+            associated_token_program: associated_token::ID,
+        },
+        predection::instruction::DisputeMarket {},
+    );
+    env.svm.send(&[ix], &[*disputer])
+}
+
+#[test]
+fn open_market_cannot_be_disputed() {
+    let mut env = bonded_env();
+    let (_, market) = env.create_market();
+    let disputer = env.trader(&market, 100 * USDC);
+
+    assert_error(
+        dispute(&mut env, &market, &disputer),
+        Errors::InvalidMarketStatus,
+    );
+}
+
+#[test]
+fn disputes_close_with_the_window() {
+    let mut env = bonded_env();
+    let (_, market) = env.create_market();
+    let disputer = env.trader(&market, 100 * USDC);
+    env.set_resolved(&market, Resolution::Yes);
+
+    env.svm.warp_by(DISPUTE_WINDOW);
+    assert_error(
+        dispute(&mut env, &market, &disputer),
+        Errors::DisputeWindowClosed,
+    );
+}
+
+#[test]
+fn unbonded_market_is_not_disputable() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let disputer = env.trader(&market, 100 * USDC);
+    env.update_market(&market, |state| state.dispute_window = DISPUTE_WINDOW);
+    env.set_resolved(&market, Resolution::Yes);
+
+    assert_error(
+        dispute(&mut env, &market, &disputer),
+        Errors::MarketNotDisputable,
+    );
+}
+
+/// A bonded market with trades on both sides, resolved YES by its creator.
+fn resolved_market(env: &mut TestEnv) -> (Pubkey, Pubkey) {
+    let (creator, market) = env.create_market();
+    assert_eq!(env.market(&market).dispute_window, DISPUTE_WINDOW);
+    let yes = env.trader(&market, 10 * USDC);
+    let no = env.trader(&market, 10 * USDC);
+    env.buy(&market, &yes, 5 * USDC, true).unwrap();
+    env.buy(&market, &no, 5 * USDC, false).unwrap();
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Yes).unwrap();
+    (creator, market)
+}
+
+fn rule_ix(env: &TestEnv, admin: &Pubkey, market: &Pubkey, ruling: Resolution) -> Instruction {
+    let state = env.market(market);
+    let disputer = state.disputer.expect("market was disputed");
+    build(
+        predection::accounts::RuleDispute {
+            admin: *admin,
+            config: pda::config().0,
+            market: *market,
+            creator_profile: pda::creator_profile(&state.authority).0,
+            collateral_mint: env.usdc,
+            bond_vault: pda::bond_vault(market).0,
+            disputer_collateral_ata: env.ata(&disputer, &env.usdc),
+            protocol_fee_collector_ata: state.protocol_fee_collector_ata,
+            token_program: spl_token::ID,
+        },
+        predection::instruction::RuleDispute { ruling },
+    )
+}
+
+fn rule_as(env: &mut TestEnv, admin: &Pubkey, market: &Pubkey, ruling: Resolution) -> TxResult {
+    let ix = rule_ix(env, admin, market, ruling);
+    env.svm.send(&[ix], &[*admin])
+}
+
+fn rule(env: &mut TestEnv, market: &Pubkey, ruling: Resolution) -> TxResult {
+    let admin = env.admin;
+    rule_as(env, &admin, market, ruling)
+}
+
+fn settle_bond(env: &mut TestEnv, market: &Pubkey) -> TxResult {
+    let state = env.market(market);
+    let ix = instructions::settle_bond(market, &state, &spl_token::ID);
+    let payer = env.user();
+    env.svm.send(&[ix], &[payer])
+}
+
+fn bond_vault_balance(env: &TestEnv, market: &Pubkey) -> u64 {
+    env.svm.token_account(&pda::bond_vault(market).0).amount
+}
+
+#[test]
+fn dispute_stakes_a_matching_bond() {
+    let mut env = bonded_env();
+    let (_, market) = resolved_market(&mut env);
+    let disputer = env.trader(&market, 100 * USDC);
+
+    dispute(&mut env, &market, &disputer).unwrap();
+
+    let state = env.market(&market);
+    assert!(matches!(state.status, Status::Disputed));
+    assert_eq!(state.disputer, Some(disputer));
+    assert_eq!(state.dispute_bond, 10 * USDC);
+    assert_eq!(env.balance(&disputer, &env.usdc), 90 * USDC);
+    assert_eq!(bond_vault_balance(&env, &market), 20 * USDC);
+
+    // Trading, claims and a second dispute all wait for the ruling
+    assert_error(
+        dispute(&mut env, &market, &disputer),
+        Errors::InvalidMarketStatus,
+    );
+    assert_error(settle_bond(&mut env, &market), Errors::ResolutionNotFinal);
+}
+
+#[test]
+fn overturned_resolution_pays_both_bonds_to_the_disputer() {
+    let mut env = bonded_env();
+    let (creator, market) = resolved_market(&mut env);
+    let disputer = env.trader(&market, 100 * USDC);
+    dispute(&mut env, &market, &disputer).unwrap();
+
+    rule(&mut env, &market, Resolution::No).unwrap();
+
+    let state = env.market(&market);
+    assert!(matches!(state.status, Status::Resolved));
+    assert!(matches!(state.resolution, Some(Resolution::No)));
+    assert_eq!(state.option, Some(false));
+    assert_eq!((state.bond_amount, state.dispute_bond), (0, 0));
+    assert_eq!(env.balance(&disputer, &env.usdc), 110 * USDC);
+    assert_eq!(bond_vault_balance(&env, &market), 0);
+    let profile: CreatorProfile = env.svm.anchor_account(&pda::creator_profile(&creator).0);
+    assert_eq!(profile.disputes_lost, 1);
+
+    // The ruling is final at once, and there is no bond left to settle
+    assert!(state.is_final(env.svm.now()));
+    assert_error(settle_bond(&mut env, &market), Errors::BondAlreadySettled);
+}
+
+#[test]
+fn upheld_resolution_sends_the_dispute_bond_to_the_treasury() {
+    let mut env = bonded_env();
+    let (creator, market) = resolved_market(&mut env);
+    let disputer = env.trader(&market, 100 * USDC);
+    dispute(&mut env, &market, &disputer).unwrap();
+
+    rule(&mut env, &market, Resolution::Yes).unwrap();
+
+    let state = env.market(&market);
+    assert!(matches!(state.resolution, Some(Resolution::Yes)));
+    assert_eq!(state.bond_amount, 10 * USDC);
+    assert_eq!(env.balance(&disputer, &env.usdc), 90 * USDC);
+    let treasury_fees = env.balance(&env.treasury, &env.usdc) - 10 * USDC;
+
+    settle_bond(&mut env, &market).unwrap();
+    assert_eq!(
+        env.balance(&env.treasury, &env.usdc),
+        treasury_fees + 10 * USDC
+    );
+    let creator_fees = env.balance(&creator, &env.usdc) - 1_000 * USDC;
+    assert_eq!(creator_fees, treasury_fees);
+}
+
+#[test]
+fn only_the_admin_rules_once_on_a_disputed_market() {
+    let mut env = bonded_env();
+    let (_, market) = resolved_market(&mut env);
+    let disputer = env.trader(&market, 100 * USDC);
+    dispute(&mut env, &market, &disputer).unwrap();
+
+    let outsider = env.user();
+    assert_error(
+        rule_as(&mut env, &outsider, &market, Resolution::No),
+        Errors::InvalidProtocolAdmin,
+    );
+
+    // Bonds can only go to the disputer
+    let outsider_ata = env.fund(&outsider, 0);
+    let mut ix = rule_ix(&env, &env.admin.clone(), &market, Resolution::No);
+    replace_account(&mut ix, &env.ata(&disputer, &env.usdc), &outsider_ata);
+    assert_error(env.svm.send(&[ix], &[env.admin]), Errors::InvalidDisputer);
+
+    rule(&mut env, &market, Resolution::Yes).unwrap();
+    assert_error(
+        rule(&mut env, &market, Resolution::No),
+        Errors::MarketNotDisputed,
+    );
+}
+
+#[test]
+fn bond_returns_to_the_creator_once_final() {
+    let mut env = bonded_env();
+    let (creator, market) = resolved_market(&mut env);
+    let before = env.balance(&creator, &env.usdc);
+
+    assert_error(settle_bond(&mut env, &market), Errors::ResolutionNotFinal);
+    env.svm.warp_by(DISPUTE_WINDOW);
+    settle_bond(&mut env, &market).unwrap();
+
+    assert_eq!(env.balance(&creator, &env.usdc), before + 10 * USDC);
+    assert_eq!(env.market(&market).bond_amount, 0);
+    assert_error(settle_bond(&mut env, &market), Errors::BondAlreadySettled);
+}
+
+#[test]
+fn invalid_resolution_slashes_the_bond() {
+    let mut env = bonded_env();
+    let (creator, market) = env.create_market();
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Invalid).unwrap();
+    env.svm.warp_by(DISPUTE_WINDOW);
+
+    settle_bond(&mut env, &market).unwrap();
+
+    assert_eq!(env.balance(&env.treasury, &env.usdc), 10 * USDC);
+    assert_eq!(env.balance(&creator, &env.usdc), 990 * USDC);
+}
+
+#[test]
+fn forfeited_bonds_only_go_to_the_protocol_treasury() {
+    let mut env = bonded_env();
+    let (creator, market) = resolved_market(&mut env);
+    let disputer = env.trader(&market, 100 * USDC);
+    dispute(&mut env, &market, &disputer).unwrap();
+    // A market naming the creator's own account as its fee collector
+    let creator_ata = env.ata(&creator, &env.usdc);
+    env.update_market(&market, |state| {
+        state.protocol_fee_collector_ata = creator_ata
+    });
+
+    let ix = rule_ix(&env, &env.admin.clone(), &market, Resolution::Yes);
+    assert_error(
+        env.svm.send(&[ix], &[env.admin]),
+        Errors::InvalidProtocolFeeCollector,
+    );
+
+    env.update_market(&market, |state| {
+        state.resolution = Some(Resolution::Invalid);
+        state.status = Status::Resolved;
+    });
+    env.svm.warp_by(DISPUTE_WINDOW);
+    assert_error(
+        settle_bond(&mut env, &market),
+        Errors::InvalidProtocolFeeCollector,
+    );
+    assert_eq!(bond_vault_balance(&env, &market), 20 * USDC);
+}
+
+fn expire(env: &mut TestEnv, market: &Pubkey) -> TxResult {
+    let payer = env.user();
+    env.svm
+        .send(&[instructions::expire_market(market)], &[payer])
+}
+
+#[test]
+fn unresolved_market_expires_and_releases_the_bond() {
+    let mut env = bonded_env();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    let expiry = env.market(&market).market_close_timestamp + RESOLUTION_GRACE_PERIOD;
+
+    env.svm.warp_to(expiry - 1);
+    assert_error(expire(&mut env, &market), Errors::MarketNotExpired);
+    env.svm.warp_to(expiry);
+    expire(&mut env, &market).unwrap();
+
+    let state = env.market(&market);
+    assert_eq!(state.resolution, Some(Resolution::Invalid));
+    assert!(state.is_final(env.svm.now()));
+    assert_error(
+        env.resolve(&market, Resolution::Yes),
+        Errors::InvalidMarketStatus,
+    );
+    assert_error(expire(&mut env, &market), Errors::InvalidMarketStatus);
+
+    // Traders are refunded and the neglected market's bond is slashed
+    env.claim(&market, &trader).unwrap();
+    assert_eq!(env.balance(&trader, &env.usdc), 99 * USDC / 10);
+    let treasury = env.balance(&env.treasury, &env.usdc);
+    settle_bond(&mut env, &market).unwrap();
+    assert_eq!(env.balance(&env.treasury, &env.usdc), treasury + 10 * USDC);
+    assert_eq!(bond_vault_balance(&env, &market), 0);
+}
+
+#[test]
+fn bond_only_returns_to_the_creators_account() {
+    let mut env = bonded_env();
+    let (creator, market) = resolved_market(&mut env);
+    env.svm.warp_by(DISPUTE_WINDOW);
+    let other = env.user();
+    let other_ata = env.fund(&other, 0);
+
+    let state = env.market(&market);
+    let mut ix = instructions::settle_bond(&market, &state, &spl_token::ID);
+    replace_account(&mut ix, &env.ata(&creator, &env.usdc), &other_ata);
+    let payer = env.user();
+    assert_error(env.svm.send(&[ix], &[payer]), Errors::InvalidCreatorAta);
+    assert_eq!(bond_vault_balance(&env, &market), 10 * USDC);
+}
@@ -0,0 +1,70 @@
+//! Clients match on `Errors` codes, which Anchor numbers by position from
+//! 6000. Variants no instruction raises any more keep their place so the
+//! codes after them don't shift.
+
+use anchor_lang::error::ERROR_CODE_OFFSET;
+use predection::errors::Errors;
+
+#[test]
+fn error_codes_are_stable() {
+    let variants = [
+        Errors::ErrorCheck,
+        Errors::ErrorInvalidAmount,
+        Errors::InvalidVault,
+        Errors::InvalidMint,
+        Errors::InvalidMarketAuthority,
+        Errors::InvalidMarketFeeCollector,
+        Errors::InvalidMarketResolver,
+        Errors::ErrorInCalculating,
+        Errors::InvalidMarketStatus,
+        Errors::InvalidMarketFeeCollectorAta,
+        Errors::MarketFinished,
+        Errors::MarketNotClosed,
+        Errors::MarketIsSettled,
+        Errors::TokenAmountIsZero,
+        Errors::CannotResolveOnesideMarket,
+        Errors::FeeIsTooHigh,
+        Errors::InvalidResolver,
+        Errors::QuestionTooShort,
+        Errors::QuestionTooLong,
+        Errors::DurationTooShort,
+        Errors::DurationTooLong,
+        Errors::CollateralNotWhitelisted,
+        Errors::InvalidProtocolFeeCollector,
+        Errors::OpenInterestCapExceeded,
+        Errors::PositionLimitExceeded,
+        Errors::MarketHalted,
+        Errors::InvalidCircuitBreaker,
+        Errors::NotOnAllowlist,
+        Errors::MetadataRequiresToken2022,
+        Errors::MetadataUriTooLong,
+        Errors::InvalidOutcomeTokenProgram,
+        Errors::UnsupportedCollateralExtension,
+        Errors::InvalidProtocolAdmin,
+        Errors::InvalidProgramData,
+        Errors::TradeTooSmall,
+        Errors::CollateralNotNative,
+        Errors::InvalidQuestionEncoding,
+        Errors::MetadataLocked,
+        Errors::MetadataFieldTooLong,
+        Errors::InvalidCategory,
+        Errors::InvalidDisputeWindow,
+        Errors::DisputeWindowClosed,
+        Errors::MarketNotDisputable,
+        Errors::MarketNotDisputed,
+        Errors::ResolutionNotFinal,
+        Errors::BondAlreadySettled,
+        Errors::InvalidDisputer,
+        Errors::InvalidCreatorAta,
+        Errors::InvalidInitialOdds,
+        Errors::WrappedSolAccountNotEmpty,
+        Errors::MarketNotExpired,
+    ];
+    for (index, variant) in variants.into_iter().enumerate() {
+        assert_eq!(
+            u32::from(variant),
+            ERROR_CODE_OFFSET + index as u32,
+            "{variant:?}"
+        );
+    }
+}
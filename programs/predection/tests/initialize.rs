@@ -0,0 +1,381 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Space;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{
+        permanent_delegate::PermanentDelegate, BaseStateWithExtensionsMut, ExtensionType,
+        StateWithExtensionsMut,
+    },
+};
+use common::*;
+use predection::errors::Errors;
+use predection::states::{
+    CategoryRegistry, CreatorProfile, InitialLiquidity, MarketRegistry, Position, PriceHistory,
+    RegistryPage, Status,
+};
+use predection_sdk::pda;
+
+#[test]
+fn creates_market_mints_vault_and_registry_entries() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+
+    let state = env.market(&market);
+    assert_eq!(state.market_id, 0);
+    assert_eq!(state.authority, creator);
+    assert_eq!(state.resolver, creator);
+    assert_eq!(state.status, Status::Open);
+    assert_eq!(state.fee, 100);
+    assert_eq!(
+        state.market_close_timestamp,
+        env.svm.now() + MARKET_DURATION
+    );
+    assert_eq!(state.fee_collector_ata, env.ata(&creator, &env.usdc));
+    assert_eq!(
+        state.protocol_fee_collector_ata,
+        env.ata(&env.treasury, &env.usdc)
+    );
+
+    for mint in [state.yes_mint, state.no_mint] {
+        let mint = env.svm.mint(&mint);
+        assert_eq!(mint.mint_authority, Some(market).into());
+        assert_eq!(mint.decimals, USDC_DECIMALS);
+        assert_eq!(mint.supply, 0);
+    }
+    let vault = env.svm.token_account(&state.market_vault);
+    assert_eq!(
+        (vault.mint, vault.owner, vault.amount),
+        (env.usdc, market, 0)
+    );
+    let history: PriceHistory = env.svm.zero_copy(&pda::price_history(&market).0);
+    assert_eq!(history.market, market);
+
+    let profile: CreatorProfile = env.svm.anchor_account(&pda::creator_profile(&creator).0);
+    assert_eq!((profile.next_market_id, profile.markets_created), (1, 1));
+    let registry: MarketRegistry = env.svm.anchor_account(&pda::registry().0);
+    assert_eq!(registry.total_markets, 1);
+    let page: RegistryPage = env.svm.anchor_account(&pda::registry_page(0).0);
+    assert_eq!(page.markets, vec![market]);
+    let category: CategoryRegistry = env.svm.anchor_account(&pda::category_registry("crypto").0);
+    assert_eq!(category.total_markets, 1);
+}
+
+#[test]
+fn creator_markets_are_numbered_in_order() {
+    let mut env = TestEnv::new();
+    let (creator, first) = env.create_market();
+
+    let params = env.market_params(&creator);
+    assert_eq!(params.market_id, 1);
+    env.initialize(&creator, params).unwrap();
+
+    let second = pda::market(&creator, 1).0;
+    assert_eq!(env.market(&first).market_id, 0);
+    assert_eq!(env.market(&second).market_id, 1);
+    let page: RegistryPage = env.svm.anchor_account(&pda::registry_page(0).0);
+    assert_eq!(page.markets, vec![first, second]);
+}
+
+#[test]
+fn creator_bond_is_locked_in_the_bond_vault() {
+    let mut env = TestEnv::with_collateral(CollateralSettings {
+        creator_bond: 10 * USDC,
+        ..CollateralSettings::default()
+    });
+    let (creator, market) = env.create_market();
+
+    assert_eq!(env.market(&market).bond_amount, 10 * USDC);
+    let bond_vault = env.svm.token_account(&pda::bond_vault(&market).0);
+    assert_eq!((bond_vault.owner, bond_vault.amount), (market, 10 * USDC));
+    assert_eq!(env.balance(&creator, &env.usdc), 990 * USDC);
+}
+
+#[test]
+fn initial_liquidity_opens_at_the_requested_odds() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market_with(|params| {
+        params.initial_liquidity = Some(InitialLiquidity {
+            amount: 100 * USDC,
+            yes_bps: 7_000,
+        });
+    });
+
+    let state = env.market(&market);
+    assert_eq!(env.svm.mint(&state.yes_mint).supply, 70 * USDC);
+    assert_eq!(env.svm.mint(&state.no_mint).supply, 30 * USDC);
+    assert_eq!(env.balance(&creator, &state.yes_mint), 70 * USDC);
+    assert_eq!(env.balance(&creator, &state.no_mint), 30 * USDC);
+    assert_eq!(
+        env.svm.token_account(&state.market_vault).amount,
+        100 * USDC
+    );
+
+    let position: Position = env.svm.anchor_account(&pda::position(&market, &creator).0);
+    assert_eq!(
+        (position.yes_shares, position.no_shares),
+        (70 * USDC, 30 * USDC)
+    );
+    assert_eq!(position.collateral_deposited, 100 * USDC);
+}
+
+#[test]
+fn initial_liquidity_needs_two_sided_odds() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+
+    for yes_bps in [0, 10_000] {
+        let mut params = env.market_params(&creator);
+        params.initial_liquidity = Some(InitialLiquidity {
+            amount: 100 * USDC,
+            yes_bps,
+        });
+        assert_error(env.initialize(&creator, params), Errors::InvalidInitialOdds);
+    }
+}
+
+#[test]
+fn outcome_mints_carry_metadata_on_token_2022() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market_with(|params| {
+        params.outcome_token_program = spl_token_2022::ID;
+        params.metadata_uri = Some("https://example.com/market.json".to_string());
+    });
+
+    let state = env.market(&market);
+    assert_eq!(state.outcome_token_program, spl_token_2022::ID);
+    assert_eq!(env.token_program_of(&state.yes_mint), spl_token_2022::ID);
+    assert_eq!(env.token_program_of(&state.no_mint), spl_token_2022::ID);
+}
+
+#[test]
+fn metadata_needs_token_2022_outcome_mints() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+    let mut params = env.market_params(&creator);
+    params.metadata_uri = Some("https://example.com/market.json".to_string());
+
+    assert_error(
+        env.initialize(&creator, params),
+        Errors::MetadataRequiresToken2022,
+    );
+}
+
+#[test]
+fn rejects_invalid_market_arguments() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+
+    type Customize = fn(&mut predection_sdk::instructions::InitializeParams);
+    let cases: [(Customize, Errors); 10] = [
+        (|params| params.fee = 1_001, Errors::FeeIsTooHigh),
+        (
+            |params| params.protocol_fee_collector = params.resolver,
+            Errors::InvalidProtocolFeeCollector,
+        ),
+        (
+            |params| params.resolver = Pubkey::default(),
+            Errors::InvalidResolver,
+        ),
+        (
+            |params| params.question = "Too short".to_string(),
+            Errors::QuestionTooShort,
+        ),
+        (
+            |params| params.question = "?".repeat(65),
+            Errors::QuestionTooLong,
+        ),
+        (
+            |params| params.limits.max_open_interest = Some(0),
+            Errors::ErrorInvalidAmount,
+        ),
+        (
+            |params| {
+                params.limits.max_open_interest = Some(10 * USDC);
+                params.initial_liquidity = Some(InitialLiquidity {
+                    amount: 100 * USDC,
+                    yes_bps: 5_000,
+                });
+            },
+            Errors::OpenInterestCapExceeded,
+        ),
+        (
+            |params| params.limits.max_price_move_bps = Some(500),
+            Errors::InvalidCircuitBreaker,
+        ),
+        (
+            |params| {
+                params.outcome_token_program = spl_token_2022::ID;
+                params.metadata_uri = Some("x".repeat(201));
+            },
+            Errors::MetadataUriTooLong,
+        ),
+        (
+            |params| params.category = String::new(),
+            Errors::InvalidCategory,
+        ),
+    ];
+    for (customize, expected) in cases {
+        let mut params = env.market_params(&creator);
+        customize(&mut params);
+        assert_error(env.initialize(&creator, params), expected);
+    }
+}
+
+#[test]
+fn rejects_collateral_the_admin_has_not_enabled() {
+    let mut env = TestEnv::new();
+    let admin = env.admin;
+    env.update_collateral_as(&admin, CollateralSettings::default(), false)
+        .unwrap();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+
+    let params = env.market_params(&creator);
+    assert_error(
+        env.initialize(&creator, params),
+        Errors::CollateralNotWhitelisted,
+    );
+}
+
+#[test]
+fn fee_is_capped_by_the_collateral_settings() {
+    let mut env = TestEnv::with_collateral(CollateralSettings {
+        max_fee: 50,
+        ..CollateralSettings::default()
+    });
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+
+    let params = env.market_params(&creator);
+    assert_error(env.initialize(&creator, params), Errors::FeeIsTooHigh);
+}
+
+#[test]
+fn question_must_be_utf8() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+    let mut ix = predection_sdk::instructions::initialize(&creator, env.market_params(&creator));
+    // The question is the first argument after the 32-byte resolver
+    let question = 8 + 32 + 4;
+    ix.data[question] = 0xff;
+
+    assert_error(
+        env.svm.send(&[ix], &[creator]),
+        Errors::InvalidQuestionEncoding,
+    );
+}
+
+#[test]
+fn creator_market_ids_cannot_wrap() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    env.fund(&creator, 1_000 * USDC);
+    let (profile, bump) = pda::creator_profile(&creator);
+    env.svm.set_anchor_account(
+        profile,
+        &CreatorProfile {
+            creator,
+            next_market_id: u64::MAX,
+            markets_created: 1,
+            total_volume: 0,
+            disputes_lost: 0,
+            bump,
+        },
+        8 + CreatorProfile::INIT_SPACE,
+    );
+
+    let params = env.market_params(&creator);
+    assert_eq!(params.market_id, u64::MAX);
+    assert_error(env.initialize(&creator, params), Errors::ErrorInCalculating);
+}
+
+#[test]
+fn fee_collector_account_must_hold_the_collateral() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    let creator_ata = env.fund(&creator, 1_000 * USDC);
+    let other_mint = Pubkey::new_unique();
+    let other_ata = Pubkey::new_unique();
+    env.svm.set_mint(
+        other_mint,
+        spl_token::ID,
+        Pubkey::new_unique(),
+        0,
+        USDC_DECIMALS,
+    );
+    env.svm
+        .set_token_account(other_ata, spl_token::ID, other_mint, creator, 0);
+
+    // The SDK passes the creator's collateral account as the fee collector
+    let mut ix = predection_sdk::instructions::initialize(&creator, env.market_params(&creator));
+    replace_account(&mut ix, &creator_ata, &other_ata);
+    assert_error(
+        env.svm.send(&[ix], &[creator]),
+        Errors::InvalidMarketFeeCollectorAta,
+    );
+}
+
+#[test]
+fn protocol_fees_go_to_the_treasurys_account() {
+    let mut env = TestEnv::new();
+    let creator = env.user();
+    let creator_ata = env.fund(&creator, 1_000 * USDC);
+
+    let params = env.market_params(&creator);
+    let mut ix = predection_sdk::instructions::initialize(&creator, params);
+    let treasury_ata = env.ata(&env.treasury, &env.usdc);
+    replace_account(&mut ix, &treasury_ata, &creator_ata);
+    assert_error(
+        env.svm.send(&[ix], &[creator]),
+        Errors::InvalidProtocolFeeCollector,
+    );
+}
+
+#[test]
+fn rejects_token_2022_collateral_with_unsafe_extensions() {
+    let mut env = TestEnv::new();
+    let usdc = env.usdc;
+
+    // Whitelisted, but anyone holding the permanent delegate could drain the vault
+    let len = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+        ExtensionType::PermanentDelegate,
+    ])
+    .unwrap();
+    let mut data = vec![0; len];
+    let mut mint =
+        StateWithExtensionsMut::<spl_token_2022::state::Mint>::unpack_uninitialized(&mut data)
+            .unwrap();
+    mint.init_extension::<PermanentDelegate>(true)
+        .unwrap()
+        .delegate = Some(Pubkey::new_unique()).try_into().unwrap();
+    mint.base = spl_token_2022::state::Mint {
+        mint_authority: Some(Pubkey::new_unique()).into(),
+        decimals: USDC_DECIMALS,
+        is_initialized: true,
+        ..Default::default()
+    };
+    mint.pack_base();
+    mint.init_account_type().unwrap();
+    env.svm
+        .set_account(usdc, Account::new(data, spl_token_2022::ID));
+
+    let creator = env.user();
+    for owner in [creator, env.treasury] {
+        let ata = env.ata(&owner, &usdc);
+        env.svm
+            .set_token_account(ata, spl_token_2022::ID, usdc, owner, 0);
+    }
+    let mut params = env.market_params(&creator);
+    params.token_program = spl_token_2022::ID;
+    assert_error(
+        env.initialize(&creator, params),
+        Errors::UnsupportedCollateralExtension,
+    );
+}
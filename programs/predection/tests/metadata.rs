@@ -0,0 +1,86 @@
+mod common;
+
+use common::*;
+use predection::errors::Errors;
+use predection::states::{MarketMetadata, MetadataField, MAX_TAGS};
+use predection_sdk::pda;
+
+fn update_ix(
+    creator: &anchor_lang::prelude::Pubkey,
+    market: &anchor_lang::prelude::Pubkey,
+    field: MetadataField,
+) -> anchor_lang::solana_program::instruction::Instruction {
+    build(
+        predection::accounts::UpdateMarketMetadata {
+            market_creator: *creator,
+            market: *market,
+            market_metadata: pda::metadata(market).0,
+            system_program: anchor_lang::system_program::ID,
+        },
+        predection::instruction::UpdateMarketMetadata { field },
+    )
+}
+
+#[test]
+fn creator_writes_metadata_fields() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+    let metadata = env.init_metadata(&market);
+
+    for field in [
+        MetadataField::Description("Settles on the Friday close.".to_string()),
+        MetadataField::Tags(vec!["sol".to_string(), "price".to_string()]),
+    ] {
+        env.svm
+            .send(&[update_ix(&creator, &market, field)], &[creator])
+            .unwrap();
+    }
+
+    let metadata: MarketMetadata = env.svm.anchor_account(&metadata);
+    assert_eq!(metadata.market, market);
+    assert_eq!(metadata.description, "Settles on the Friday close.");
+    assert_eq!(metadata.tags, ["sol", "price"]);
+}
+
+#[test]
+fn metadata_locks_after_first_trade() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+    env.init_metadata(&market);
+    env.update_market(&market, |state| state.trade_count = 1);
+
+    let ix = update_ix(
+        &creator,
+        &market,
+        MetadataField::Rules("New rules".to_string()),
+    );
+    assert_error(env.svm.send(&[ix], &[creator]), Errors::MetadataLocked);
+}
+
+#[test]
+fn oversized_metadata_is_rejected() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+    env.init_metadata(&market);
+
+    let tags = vec!["tag".to_string(); MAX_TAGS + 1];
+    let ix = update_ix(&creator, &market, MetadataField::Tags(tags));
+    assert_error(
+        env.svm.send(&[ix], &[creator]),
+        Errors::MetadataFieldTooLong,
+    );
+}
+
+#[test]
+fn only_the_creator_writes_metadata() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.init_metadata(&market);
+    let outsider = env.user();
+
+    let ix = update_ix(&outsider, &market, MetadataField::ContentHash([7; 32]));
+    assert_error(
+        env.svm.send(&[ix], &[outsider]),
+        Errors::InvalidMarketAuthority,
+    );
+}
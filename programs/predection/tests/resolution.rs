@@ -0,0 +1,137 @@
+mod common;
+
+use common::*;
+use predection::errors::Errors;
+use predection::math::pro_rata;
+use predection::states::{Position, Resolution, Status};
+use predection_sdk::pda;
+
+#[test]
+fn resolver_settles_outcome_after_close() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 10 * USDC, 5 * USDC);
+
+    let result = env.resolve(&market, Resolution::Yes);
+    assert_error(result, Errors::MarketNotClosed);
+
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Yes).unwrap();
+
+    let state = env.market(&market);
+    assert!(matches!(state.status, Status::Resolved));
+    assert!(matches!(state.resolution, Some(Resolution::Yes)));
+    assert_eq!(state.option, Some(true));
+    assert_eq!(state.resolved_at, env.svm.now());
+}
+
+#[test]
+fn only_the_resolver_resolves() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 10 * USDC, 5 * USDC);
+    env.svm.warp_by(MARKET_DURATION);
+    let outsider = env.user();
+
+    let result = env.resolve_as(&outsider, &market, Resolution::No);
+    assert_error(result, Errors::InvalidMarketResolver);
+}
+
+#[test]
+fn one_sided_market_can_only_be_voided() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 10 * USDC, 0);
+    env.svm.warp_by(MARKET_DURATION);
+
+    let result = env.resolve(&market, Resolution::Yes);
+    assert_error(result, Errors::CannotResolveOnesideMarket);
+
+    env.resolve(&market, Resolution::Invalid).unwrap();
+    let state = env.market(&market);
+    assert!(matches!(state.resolution, Some(Resolution::Invalid)));
+    assert_eq!(state.option, None);
+}
+
+#[test]
+fn market_resolves_only_once() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 10 * USDC, 5 * USDC);
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::No).unwrap();
+
+    let result = env.resolve(&market, Resolution::Yes);
+    assert_error(result, Errors::InvalidMarketStatus);
+    assert!(matches!(
+        env.market(&market).resolution,
+        Some(Resolution::No)
+    ));
+}
+
+#[test]
+fn halted_market_can_be_resolved() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 10 * USDC, 5 * USDC);
+    env.svm.warp_by(MARKET_DURATION);
+    let halted_until = env.svm.now() + 600;
+    env.update_market(&market, |state| {
+        state.status = Status::Halted;
+        state.halted_until = halted_until;
+    });
+
+    env.resolve(&market, Resolution::Yes).unwrap();
+    assert!(matches!(env.market(&market).status, Status::Resolved));
+}
+
+#[test]
+fn winners_split_the_vault_by_their_shares() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let yes_1 = env.trader(&market, 100 * USDC);
+    let yes_2 = env.trader(&market, 100 * USDC);
+    let no = env.trader(&market, 100 * USDC);
+    env.buy(&market, &yes_1, 30 * USDC, true).unwrap();
+    env.buy(&market, &yes_2, 10 * USDC, true).unwrap();
+    env.buy(&market, &no, 60 * USDC, false).unwrap();
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Yes).unwrap();
+
+    let state = env.market(&market);
+    let vault = env.svm.token_account(&state.market_vault).amount;
+    let shares_1 = env.balance(&yes_1, &state.yes_mint);
+    let supply = env.svm.mint(&state.yes_mint).supply;
+    env.claim(&market, &yes_1).unwrap();
+
+    let payout = pro_rata(shares_1, vault, supply).unwrap();
+    assert_eq!(env.balance(&yes_1, &env.usdc), 70 * USDC + payout);
+    assert_eq!(env.balance(&yes_1, &state.yes_mint), 0);
+    let position: Position = env.svm.anchor_account(&pda::position(&market, &yes_1).0);
+    assert_eq!(position.yes_shares, 0);
+    assert_eq!(position.collateral_withdrawn, payout);
+
+    // The last winner takes what is left
+    env.claim(&market, &yes_2).unwrap();
+    assert_eq!(env.svm.token_account(&state.market_vault).amount, 0);
+    assert_error(env.claim(&market, &no), Errors::TokenAmountIsZero);
+}
+
+#[test]
+fn invalid_market_refunds_every_share_alike() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 100 * USDC);
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    env.buy(&market, &trader, 10 * USDC, false).unwrap();
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Invalid).unwrap();
+
+    let state = env.market(&market);
+    let vault = env.svm.token_account(&state.market_vault).amount;
+    env.claim(&market, &trader).unwrap();
+
+    assert_eq!(env.balance(&trader, &env.usdc), 80 * USDC + vault);
+    assert_eq!(env.balance(&trader, &state.yes_mint), 0);
+    assert_eq!(env.balance(&trader, &state.no_mint), 0);
+}
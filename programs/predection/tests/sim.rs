@@ -0,0 +1,329 @@
+//! Replays random markets and order flows through the program and through
+//! `predection-sim`'s `SimMarket`, which backtests against the same rules.
+//! Every order must fill, be rejected or trip the circuit breaker on both,
+//! leaving the same supplies, vault, fees and payouts.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use predection::states::{MarketLimits, Resolution, Status};
+use predection_sdk::{pda, InitialLiquidity};
+use predection_sim::{MarketParams, Outcome, Side, SimError, SimMarket};
+use proptest::prelude::*;
+
+const USERS: usize = 4;
+const STARTING_BALANCE: u64 = 1_000_000 * USDC;
+const CREATOR_BALANCE: u64 = 1_000 * USDC;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Buy {
+        user: usize,
+        side: Side,
+        amount: u64,
+    },
+    /// Sells `percent` of the user's shares on one side; over 100 oversells.
+    Sell {
+        user: usize,
+        side: Side,
+        percent: u64,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct Case {
+    params: MarketParams,
+    /// Seconds since the previous order, and the order
+    ops: Vec<(i64, Op)>,
+    outcome: Outcome,
+}
+
+struct Parity {
+    env: TestEnv,
+    sim: SimMarket,
+    creator: Pubkey,
+    market: Pubkey,
+    traders: Vec<Pubkey>,
+    opened_at: i64,
+}
+
+impl Parity {
+    /// Creates the market on both sides, or returns None once both have
+    /// rejected it.
+    fn new(params: MarketParams) -> Option<Self> {
+        let mut env = TestEnv::with_collateral(CollateralSettings {
+            min_trade_size: params.min_trade_size,
+            ..CollateralSettings::default()
+        });
+        let creator = env.user();
+        env.fund(&creator, CREATOR_BALANCE);
+        let mut initialize = env.market_params(&creator);
+        initialize.fee = params.fee_bps;
+        initialize.limits = MarketLimits {
+            max_open_interest: params.max_open_interest,
+            max_position_per_wallet: params.max_position_per_wallet,
+            max_price_move_bps: params.max_price_move_bps,
+            price_move_window: params.price_move_window,
+            halt_cooldown: params.halt_cooldown,
+            breaker_min_liquidity: params.breaker_min_liquidity,
+        };
+        initialize.initial_liquidity = (params.initial_liquidity > 0).then_some(InitialLiquidity {
+            amount: params.initial_liquidity,
+            yes_bps: params.initial_yes_bps,
+        });
+        let market = pda::market(&creator, initialize.market_id).0;
+
+        let created = env.initialize(&creator, initialize);
+        let sim = SimMarket::new(params);
+        match (created, sim) {
+            (Ok(()), Ok(sim)) => {
+                let opened_at = env.svm.now();
+                let traders = (0..USERS)
+                    .map(|_| env.trader(&market, STARTING_BALANCE))
+                    .collect();
+                Some(Self {
+                    env,
+                    sim,
+                    creator,
+                    market,
+                    traders,
+                    opened_at,
+                })
+            }
+            (Err(_), Err(_)) => None,
+            (created, sim) => panic!("initialize: program {created:?}, sim {sim:?}"),
+        }
+    }
+
+    fn apply(&mut self, op: &Op) {
+        let before = self.env.market(&self.market);
+        let (result, sim) = match *op {
+            Op::Buy { user, side, amount } => (
+                self.env
+                    .buy(&self.market, &self.traders[user], amount, side == Side::Yes),
+                self.sim.buy(&user.to_string(), side, amount),
+            ),
+            Op::Sell {
+                user,
+                side,
+                percent,
+            } => {
+                let shares = self.shares(user, side) * percent / 100;
+                (
+                    self.env
+                        .sell(&self.market, &self.traders[user], shares, side == Side::Yes),
+                    self.sim.sell(&user.to_string(), side, shares),
+                )
+            }
+        };
+
+        let after = self.env.market(&self.market);
+        match sim {
+            Ok(_) => {
+                assert!(result.is_ok(), "{op:?} filled in the sim: {result:?}");
+                assert_eq!(after.trade_count, before.trade_count + 1, "{op:?}");
+            }
+            Err(SimError::CircuitBreakerTripped) => {
+                assert!(result.is_ok(), "{op:?} tripped the sim: {result:?}");
+                assert!(matches!(after.status, Status::Halted), "{op:?}");
+                let halted_until = self.sim.halted_until.map(|until| self.opened_at + until);
+                assert_eq!(Some(after.halted_until), halted_until, "{op:?}");
+                assert_eq!(after.trade_count, before.trade_count, "{op:?}");
+            }
+            Err(error) => assert!(result.is_err(), "{op:?} rejected by the sim: {error}"),
+        }
+        self.check_balances();
+    }
+
+    fn shares(&self, user: usize, side: Side) -> u64 {
+        let state = self.env.market(&self.market);
+        let mint = match side {
+            Side::Yes => state.yes_mint,
+            Side::No => state.no_mint,
+        };
+        self.env.balance(&self.traders[user], &mint)
+    }
+
+    fn check_balances(&self) {
+        let state = self.env.market(&self.market);
+        let svm = &self.env.svm;
+        assert_eq!(svm.mint(&state.yes_mint).supply, self.sim.yes_supply);
+        assert_eq!(svm.mint(&state.no_mint).supply, self.sim.no_supply);
+        assert_eq!(
+            svm.token_account(&state.market_vault).amount,
+            self.sim.vault
+        );
+        assert_eq!(
+            self.env.balance(&self.env.treasury, &self.env.usdc),
+            self.sim.protocol_fees
+        );
+        assert_eq!(
+            self.env.balance(&self.creator, &self.env.usdc),
+            CREATOR_BALANCE - self.sim.params.initial_liquidity
+                + self.sim.creator_fees
+                + self.sim.lp.paid_out
+        );
+        for (user, trader) in self.traders.iter().enumerate() {
+            let holding = self
+                .sim
+                .traders
+                .get(&user.to_string())
+                .copied()
+                .unwrap_or_default();
+            assert_eq!(self.shares(user, Side::Yes), holding.yes, "user {user}");
+            assert_eq!(self.shares(user, Side::No), holding.no, "user {user}");
+            assert_eq!(
+                self.env.balance(trader, &self.env.usdc),
+                STARTING_BALANCE - holding.paid_in + holding.paid_out,
+                "user {user}"
+            );
+        }
+    }
+
+    /// Resolves both, then claims in the order `claim_all` does: traders
+    /// by name, then the creator.
+    fn settle(&mut self, outcome: Outcome) {
+        let close = self.env.market(&self.market).market_close_timestamp;
+        self.env.svm.warp_to(close.max(self.env.svm.now()));
+        let resolution = match outcome {
+            Outcome::Yes => Resolution::Yes,
+            Outcome::No => Resolution::No,
+            Outcome::Invalid => Resolution::Invalid,
+        };
+        let resolved = self.env.resolve(&self.market, resolution);
+        match self.sim.resolve(outcome) {
+            Ok(()) => assert!(resolved.is_ok(), "{outcome:?}: {resolved:?}"),
+            Err(error) => {
+                assert!(
+                    resolved.is_err(),
+                    "{outcome:?} rejected by the sim: {error}"
+                );
+                return;
+            }
+        }
+
+        let winning = |yes: u64, no: u64| match outcome {
+            Outcome::Yes => yes,
+            Outcome::No => no,
+            Outcome::Invalid => yes + no,
+        };
+        let mut claimants: Vec<Pubkey> = (0..USERS)
+            .filter(|&user| winning(self.shares(user, Side::Yes), self.shares(user, Side::No)) > 0)
+            .map(|user| self.traders[user])
+            .collect();
+        if winning(self.sim.lp.yes, self.sim.lp.no) > 0 {
+            claimants.push(self.creator);
+        }
+        for claimant in claimants {
+            self.env.claim(&self.market, &claimant).unwrap();
+        }
+        self.sim.claim_all().unwrap();
+        self.check_balances();
+    }
+}
+
+fn side() -> impl Strategy<Value = Side> {
+    prop_oneof![Just(Side::Yes), Just(Side::No)]
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let user = 0..USERS;
+    prop_oneof![
+        3 => (user.clone(), side(), 0..=300 * USDC)
+            .prop_map(|(user, side, amount)| Op::Buy { user, side, amount }),
+        1 => (user, side(), 1..=120u64)
+            .prop_map(|(user, side, percent)| Op::Sell { user, side, percent }),
+    ]
+}
+
+/// `Just(0)` one time in `1 + weight`, otherwise `values`.
+fn or_zero<T: Default + Clone + std::fmt::Debug + 'static>(
+    weight: u32,
+    values: impl Strategy<Value = T> + 'static,
+) -> BoxedStrategy<T> {
+    prop_oneof![1 => Just(T::default()), weight => values].boxed()
+}
+
+/// Mostly valid settings, with the edges the program rejects mixed in.
+fn params() -> impl Strategy<Value = MarketParams> {
+    let odds = prop_oneof![18 => 1..10_000u64, 1 => Just(0), 1 => Just(10_000)];
+    let seed = prop_oneof![
+        1 => Just((0, 5_000)),
+        // Tiny seeds at lopsided odds leave a side without shares
+        1 => (1..=100u64, odds.clone()),
+        4 => (1..=500 * USDC, odds),
+    ];
+    let cap = prop_oneof![3 => Just(None), 1 => or_zero(9, 1..=1_000 * USDC).prop_map(Some)];
+    let breaker = prop_oneof![
+        1 => Just((None, 0, 0, 0)),
+        3 => (
+            or_zero(19, 1..=5_000u64),
+            or_zero(19, 1..=600i64),
+            or_zero(19, 1..=600i64),
+            0..=400 * USDC,
+        )
+            .prop_map(|(bps, window, cooldown, min)| (Some(bps), window, cooldown, min)),
+    ];
+    (
+        0..=1_000u64,
+        seed,
+        or_zero(1, 1..=10 * USDC),
+        cap.clone(),
+        cap,
+        breaker,
+    )
+        .prop_map(
+            |(fee_bps, (liquidity, yes_bps), min_trade_size, open_interest, position, breaker)| {
+                let (max_price_move_bps, price_move_window, halt_cooldown, min_liquidity) = breaker;
+                MarketParams {
+                    fee_bps,
+                    initial_liquidity: liquidity,
+                    initial_yes_bps: yes_bps,
+                    min_trade_size,
+                    max_open_interest: open_interest,
+                    max_position_per_wallet: position,
+                    max_price_move_bps,
+                    price_move_window,
+                    halt_cooldown,
+                    breaker_min_liquidity: min_liquidity,
+                }
+            },
+        )
+}
+
+fn case() -> impl Strategy<Value = Case> {
+    let outcome = prop_oneof![
+        Just(Outcome::Yes),
+        Just(Outcome::No),
+        Just(Outcome::Invalid)
+    ];
+    (
+        params(),
+        prop::collection::vec((0..=120i64, op()), 1..24),
+        outcome,
+    )
+        .prop_map(|(params, ops, outcome)| Case {
+            params,
+            ops,
+            outcome,
+        })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn sim_agrees_with_the_program(case in case()) {
+        let Some(mut parity) = Parity::new(case.params) else {
+            return Ok(());
+        };
+        parity.check_balances();
+        for (wait, op) in &case.ops {
+            parity.env.svm.warp_by(*wait);
+            parity.sim.advance_to(parity.env.svm.now() - parity.opened_at);
+            parity.apply(op);
+        }
+        parity.settle(case.outcome);
+    }
+}
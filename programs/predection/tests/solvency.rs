@@ -0,0 +1,382 @@
+//! Property tests for vault solvency.
+//!
+//! Random sequences of `buy_share`, `sell_share`, `resolve_market` and
+//! `claim_winning` run through the program, on markets with and without a
+//! creator seed and open interest and position limits, with the invariants
+//! checked against token balances after every step.
+
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use common::*;
+use predection::errors::Errors;
+use predection::states::{InitialLiquidity, MarketLimits, Position, Resolution};
+use predection_sdk::pda;
+use proptest::prelude::*;
+
+const USERS: usize = 4;
+/// A billion USDC each, so pro-rata products run far past u64.
+const STARTING_BALANCE: u64 = 1_000_000_000_000_000;
+/// What `create_market_with` funds the creator with.
+const CREATOR_BALANCE: u64 = 1_000 * USDC;
+
+#[derive(Clone, Debug)]
+struct Setup {
+    fee: u64,
+    /// The creator's seed amount and YES share in basis points
+    seed: Option<(u64, u64)>,
+    max_open_interest: Option<u64>,
+    max_position_per_wallet: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    /// Buys `percent` of what the user can still spend, so up to 100 it
+    /// must fill and past it may run into the balance or a cap.
+    Buy {
+        user: usize,
+        percent: u64,
+        is_yes: bool,
+    },
+    /// Sells `percent` of the user's shares on one side.
+    Sell {
+        user: usize,
+        percent: u64,
+        is_yes: bool,
+    },
+    Resolve(Resolution),
+    /// `user` may be `USERS`, the creator, who only holds shares on a
+    /// seeded market and otherwise stands for the first trader.
+    Claim {
+        user: usize,
+    },
+}
+
+impl Op {
+    /// Errors a valid sequence can run into: trading or claiming at the
+    /// wrong stage, spending more than the user holds, or hitting the
+    /// market's limits. Anything else fails the test.
+    fn expected_errors(&self) -> &'static [Errors] {
+        match self {
+            Op::Buy { .. } => &[
+                Errors::InvalidMarketStatus,
+                Errors::ErrorInvalidAmount,
+                Errors::OpenInterestCapExceeded,
+                Errors::PositionLimitExceeded,
+            ],
+            Op::Sell { .. } => &[Errors::InvalidMarketStatus],
+            Op::Resolve(_) => &[
+                Errors::InvalidMarketStatus,
+                Errors::CannotResolveOnesideMarket,
+            ],
+            Op::Claim { .. } => &[Errors::MarketIsSettled, Errors::TokenAmountIsZero],
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Solvency {
+    env: TestEnv,
+    market: Pubkey,
+    /// The traders, then the creator if they seeded the market. The
+    /// creator's collateral account also collects the creator fees.
+    holders: Vec<Pubkey>,
+    /// Collateral in existence: the traders', the creator's and the
+    /// treasury's. Nothing may mint or burn any.
+    total_collateral: u64,
+}
+
+impl Solvency {
+    fn new(setup: &Setup) -> Self {
+        let mut env = TestEnv::new();
+        let (creator, market) = env.create_market_with(|params| {
+            params.fee = setup.fee;
+            params.initial_liquidity = setup
+                .seed
+                .map(|(amount, yes_bps)| InitialLiquidity { amount, yes_bps });
+            params.limits = MarketLimits {
+                max_open_interest: setup.max_open_interest,
+                max_position_per_wallet: setup.max_position_per_wallet,
+                ..params.limits.clone()
+            };
+        });
+        assert_eq!(
+            env.market(&market).fee_collector_ata,
+            env.ata(&creator, &env.usdc)
+        );
+        let mut holders: Vec<Pubkey> = (0..USERS)
+            .map(|_| env.trader(&market, STARTING_BALANCE))
+            .collect();
+        if setup.seed.is_some() {
+            holders.push(creator);
+        }
+        let mut solvency = Self {
+            env,
+            market,
+            holders,
+            total_collateral: 0,
+        };
+        solvency.total_collateral = solvency.held_collateral();
+        solvency
+    }
+
+    /// Runs `op`, failing the test on any error it isn't expected to meet
+    /// and on any buy within the user's headroom that doesn't fill.
+    fn apply(&mut self, op: &Op) -> bool {
+        let result = match *op {
+            Op::Buy {
+                user,
+                percent,
+                is_yes,
+            } => {
+                let amount = (self.headroom(user) as u128 * percent as u128 / 100) as u64;
+                let result = self
+                    .env
+                    .buy(&self.market, &self.holders[user], amount.max(1), is_yes);
+                // Below a unit the fee may leave nothing to deposit
+                let open = self.env.market(&self.market).resolution.is_none();
+                if open && percent <= 100 && amount >= USDC {
+                    assert!(result.is_ok(), "{op:?} of {amount} failed: {result:?}");
+                }
+                result
+            }
+            Op::Sell {
+                user,
+                percent,
+                is_yes,
+            } => {
+                let held = self.shares(user, is_yes);
+                self.env.sell(
+                    &self.market,
+                    &self.holders[user],
+                    held * percent / 100,
+                    is_yes,
+                )
+            }
+            Op::Resolve(resolution) => self.resolve(resolution),
+            Op::Claim { user } => self.claim(user % self.holders.len()).map(|_| ()),
+        };
+        let Err(error) = &result else {
+            return true;
+        };
+        let expected = op.expected_errors();
+        assert!(
+            expected
+                .iter()
+                .any(|&expected| error_code(&result) == Some(expected.into())),
+            "{op:?} failed with {error:?}, expected one of {expected:?}"
+        );
+        false
+    }
+
+    /// Resolves once trading has closed, as the resolver would.
+    fn resolve(&mut self, resolution: Resolution) -> TxResult {
+        let close = self.env.market(&self.market).market_close_timestamp;
+        if self.env.svm.now() < close {
+            self.env.svm.warp_to(close);
+        }
+        self.env.resolve(&self.market, resolution)
+    }
+
+    /// The most `user` can spend on one buy: their balance, capped by the
+    /// open interest and position limits left.
+    fn headroom(&self, user: usize) -> u64 {
+        let state = self.env.market(&self.market);
+        let open_interest = state
+            .max_open_interest
+            .map(|cap| cap.saturating_sub(self.vault()));
+        let position = state.max_position_per_wallet.map(|cap| {
+            let position: Position = self
+                .env
+                .svm
+                .anchor_account(&pda::position(&self.market, &self.holders[user]).0);
+            cap.saturating_sub(position.total_shares())
+        });
+        [open_interest, position]
+            .into_iter()
+            .flatten()
+            .fold(self.collateral(user), u64::min)
+    }
+
+    /// Claims for `user`, returning the collateral paid out.
+    fn claim(&mut self, user: usize) -> Result<u64, TxError> {
+        let before = self.collateral(user);
+        self.env.claim(&self.market, &self.holders[user])?;
+        Ok(self.collateral(user) - before)
+    }
+
+    fn collateral(&self, user: usize) -> u64 {
+        self.env.balance(&self.holders[user], &self.env.usdc)
+    }
+
+    fn shares(&self, user: usize, is_yes: bool) -> u64 {
+        let state = self.env.market(&self.market);
+        let mint = if is_yes {
+            state.yes_mint
+        } else {
+            state.no_mint
+        };
+        self.env.balance(&self.holders[user], &mint)
+    }
+
+    fn vault(&self) -> u64 {
+        let vault = self.env.market(&self.market).market_vault;
+        self.env.svm.token_account(&vault).amount
+    }
+
+    /// The treasury's collateral and the creator's, fees included.
+    fn fees_collected(&self) -> u64 {
+        let state = self.env.market(&self.market);
+        let creator = self.env.svm.token_account(&state.fee_collector_ata).amount;
+        let treasury = self
+            .env
+            .svm
+            .token_account(&state.protocol_fee_collector_ata)
+            .amount;
+        creator + treasury
+    }
+
+    fn held_collateral(&self) -> u64 {
+        let traders: u64 = (0..USERS).map(|user| self.collateral(user)).sum();
+        traders + self.vault() + self.fees_collected()
+    }
+
+    fn supply(&self, outcome: Resolution) -> u64 {
+        let state = self.env.market(&self.market);
+        let yes = self.env.svm.mint(&state.yes_mint).supply;
+        let no = self.env.svm.mint(&state.no_mint).supply;
+        match outcome {
+            Resolution::Yes => yes,
+            Resolution::No => no,
+            Resolution::Invalid => yes + no,
+        }
+    }
+
+    fn check_invariants(&self) {
+        assert_eq!(
+            self.held_collateral(),
+            self.total_collateral,
+            "collateral was created or destroyed"
+        );
+
+        let resolution = self.env.market(&self.market).resolution;
+        if resolution.is_none() {
+            for user in 0..USERS {
+                assert!(
+                    self.collateral(user) <= STARTING_BALANCE,
+                    "user {user} profited before resolution"
+                );
+            }
+        }
+
+        // Every outcome still reachable must pay all its winners in full
+        let outcomes: &[Resolution] = match resolution {
+            Some(resolution) => &[resolution][..],
+            None => &[Resolution::Yes, Resolution::No, Resolution::Invalid],
+        };
+        for &outcome in outcomes {
+            let mut settled = self.clone();
+            if resolution.is_none() && settled.resolve(outcome).is_err() {
+                continue;
+            }
+            for user in 0..settled.holders.len() {
+                let winning = match outcome {
+                    Resolution::Yes => settled.shares(user, true),
+                    Resolution::No => settled.shares(user, false),
+                    Resolution::Invalid => settled.shares(user, true) + settled.shares(user, false),
+                };
+                if winning > 0 {
+                    if let Err(error) = settled.claim(user) {
+                        panic!("holder {user} can't claim {outcome:?}: {error:?}");
+                    }
+                }
+            }
+            assert_eq!(
+                settled.supply(outcome),
+                0,
+                "unclaimed winning shares after {outcome:?}"
+            );
+        }
+    }
+}
+
+fn resolution() -> impl Strategy<Value = Resolution> {
+    prop_oneof![
+        Just(Resolution::Yes),
+        Just(Resolution::No),
+        Just(Resolution::Invalid)
+    ]
+}
+
+/// Markets with and without a seed and limits. Caps run from a few trades'
+/// worth up to more than every trader holds, and the open interest cap
+/// always leaves room past the seed.
+fn setup() -> impl Strategy<Value = Setup> {
+    let seed = (USDC..=CREATOR_BALANCE, 1..10_000u64);
+    let cap = || USDC..=STARTING_BALANCE * USERS as u64;
+    (
+        0..=1_000u64,
+        prop::option::of(seed),
+        prop::option::of(cap()),
+        prop::option::of(cap()),
+    )
+        .prop_map(|(fee, seed, max_open_interest, max_position_per_wallet)| {
+            let seeded = seed.map_or(0, |(amount, _)| amount);
+            Setup {
+                fee,
+                max_open_interest: max_open_interest.map(|cap| cap + seeded),
+                seed,
+                max_position_per_wallet,
+            }
+        })
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let user = 0..USERS;
+    prop_oneof![
+        6 => (user.clone(), 1..=120u64, any::<bool>())
+            .prop_map(|(user, percent, is_yes)| Op::Buy { user, percent, is_yes }),
+        3 => (user, 1..=100u64, any::<bool>())
+            .prop_map(|(user, percent, is_yes)| Op::Sell { user, percent, is_yes }),
+        1 => resolution().prop_map(Op::Resolve),
+        2 => (0..=USERS).prop_map(|user| Op::Claim { user }),
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn vault_stays_solvent(setup in setup(), ops in prop::collection::vec(op(), 1..32)) {
+        let mut solvency = Solvency::new(&setup);
+        for op in &ops {
+            solvency.apply(op);
+            solvency.check_invariants();
+        }
+    }
+
+    #[test]
+    fn claims_pay_at_most_the_vault(
+        setup in setup(),
+        buys in prop::collection::vec((0..USERS, 1..=100u64, any::<bool>()), 2..16),
+        resolution in resolution(),
+    ) {
+        let mut solvency = Solvency::new(&setup);
+        for (user, percent, is_yes) in buys {
+            solvency.apply(&Op::Buy { user, percent, is_yes });
+        }
+        let vault = solvency.vault();
+        prop_assume!(solvency.resolve(resolution).is_ok());
+
+        let mut claimed = 0;
+        for user in 0..solvency.holders.len() {
+            let before = solvency.collateral(user);
+            if solvency.apply(&Op::Claim { user }) {
+                claimed += solvency.collateral(user) - before;
+            }
+        }
+        prop_assert!(claimed <= vault);
+        // Pro-rata rounding leaves at most one unit of dust per claimant
+        prop_assert!(vault - claimed <= USERS as u64 + 1);
+    }
+}
@@ -0,0 +1,368 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token::{self, error::TokenError};
+use anchor_spl::token_2022::spl_token_2022;
+use common::*;
+use predection::errors::Errors;
+use predection::math::quote_buy;
+use predection::merkle::{allowlist_leaf, hash_pair};
+use predection::states::{
+    CreatorProfile, Position, PriceHistory, Resolution, Status, TradeOutcome,
+};
+use predection_sdk::{instructions, pda};
+
+#[test]
+fn buy_mints_shares_and_splits_the_fee() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    let quote = quote_buy(5 * USDC, 100).unwrap();
+
+    env.buy(&market, &trader, 5 * USDC, true).unwrap();
+
+    let state = env.market(&market);
+    assert_eq!(env.balance(&trader, &env.usdc), 5 * USDC);
+    assert_eq!(env.balance(&trader, &state.yes_mint), quote.net_amount);
+    assert_eq!(env.svm.mint(&state.yes_mint).supply, quote.net_amount);
+    assert_eq!(
+        env.svm.token_account(&state.market_vault).amount,
+        quote.net_amount
+    );
+    assert_eq!(
+        env.balance(&creator, &env.usdc),
+        1_000 * USDC + quote.fee.creator
+    );
+    assert_eq!(env.balance(&env.treasury, &env.usdc), quote.fee.protocol);
+
+    let position: Position = env.svm.anchor_account(&pda::position(&market, &trader).0);
+    assert_eq!(position.yes_shares, quote.net_amount);
+    assert_eq!(position.collateral_deposited, quote.net_amount);
+    assert_eq!(state.trade_count, 1);
+    let profile: CreatorProfile = env.svm.anchor_account(&pda::creator_profile(&creator).0);
+    assert_eq!(profile.total_volume, quote.net_amount);
+    let history: PriceHistory = env.svm.zero_copy(&pda::price_history(&market).0);
+    assert_eq!(history.count, 1);
+}
+
+#[test]
+fn first_buy_creates_the_traders_accounts() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.user();
+    env.fund(&trader, 10 * USDC);
+
+    env.buy(&market, &trader, USDC, false).unwrap();
+
+    let state = env.market(&market);
+    let net = quote_buy(USDC, 100).unwrap().net_amount;
+    assert_eq!(env.balance(&trader, &state.no_mint), net);
+    assert_eq!(env.balance(&trader, &state.yes_mint), 0);
+    let position: Position = env.svm.anchor_account(&pda::position(&market, &trader).0);
+    assert_eq!((position.owner, position.no_shares), (trader, net));
+}
+
+#[test]
+fn sell_burns_shares_for_collateral() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    let held = env.balance(&trader, &env.market(&market).yes_mint);
+
+    env.sell(&market, &trader, USDC, true).unwrap();
+
+    let state = env.market(&market);
+    assert_eq!(env.balance(&trader, &state.yes_mint), held - USDC);
+    assert_eq!(env.balance(&trader, &env.usdc), USDC);
+    assert_eq!(
+        env.svm.token_account(&state.market_vault).amount,
+        held - USDC
+    );
+    let position: Position = env.svm.anchor_account(&pda::position(&market, &trader).0);
+    assert_eq!(position.yes_shares, held - USDC);
+    assert_eq!(position.collateral_withdrawn, USDC);
+}
+
+#[test]
+fn selling_shares_not_held_fails_the_burn() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.buy(&market, &trader, USDC, true).unwrap();
+
+    let result = env.sell(&market, &trader, USDC, true);
+    assert_eq!(
+        result,
+        Err(TxError::Instruction {
+            index: 0,
+            error: TokenError::InsufficientFunds.into(),
+        })
+    );
+}
+
+#[test]
+fn buy_needs_enough_collateral() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+
+    let result = env.buy(&market, &trader, 10 * USDC + 1, true);
+    assert_error(result, Errors::ErrorInvalidAmount);
+}
+
+#[test]
+fn buy_below_min_trade_size_is_rejected() {
+    let mut env = TestEnv::with_collateral(CollateralSettings {
+        min_trade_size: USDC,
+        ..CollateralSettings::default()
+    });
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+
+    let result = env.buy(&market, &trader, USDC - 1, true);
+    assert_error(result, Errors::TradeTooSmall);
+}
+
+#[test]
+fn halted_market_rejects_trades_until_cooldown() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    let halted_until = env.svm.now() + 600;
+    env.update_market(&market, |state| {
+        state.status = Status::Halted;
+        state.halted_until = halted_until;
+    });
+
+    assert_error(env.buy(&market, &trader, USDC, true), Errors::MarketHalted);
+    assert_error(env.sell(&market, &trader, USDC, true), Errors::MarketHalted);
+
+    env.svm.warp_to(halted_until);
+    env.buy(&market, &trader, USDC, true).unwrap();
+    assert!(matches!(env.market(&market).status, Status::Open));
+}
+
+#[test]
+fn breaching_trade_halts_the_market_without_filling() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market_with(|params| {
+        params.limits.max_price_move_bps = Some(1_000);
+        params.limits.price_move_window = 60;
+        params.limits.halt_cooldown = 600;
+        params.limits.breaker_min_liquidity = 15 * USDC;
+    });
+    let trader = env.trader(&market, 100 * USDC);
+
+    // Thin pools swing freely: YES goes from 100% to 50% unchecked
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    env.buy(&market, &trader, 10 * USDC, false).unwrap();
+    let state = env.market(&market);
+    let supplies = (
+        env.svm.mint(&state.yes_mint).supply,
+        env.svm.mint(&state.no_mint).supply,
+    );
+    let balance = env.balance(&trader, &env.usdc);
+
+    // 50% to 67% is refused, but the halt sticks
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    let state = env.market(&market);
+    assert_eq!(state.status, Status::Halted);
+    assert_eq!(state.halted_until, env.svm.now() + 600);
+    assert_eq!(
+        env.trade_outcome(),
+        TradeOutcome::Halted {
+            halted_until: state.halted_until
+        }
+    );
+    assert_eq!(
+        (
+            env.svm.mint(&state.yes_mint).supply,
+            env.svm.mint(&state.no_mint).supply,
+        ),
+        supplies
+    );
+    assert_eq!(env.balance(&trader, &env.usdc), balance);
+    assert_error(env.buy(&market, &trader, USDC, true), Errors::MarketHalted);
+
+    // Moves within the threshold fill once the cooldown is over
+    env.svm.warp_by(600);
+    env.buy(&market, &trader, USDC, true).unwrap();
+    assert_eq!(env.market(&market).status, Status::Open);
+    assert_eq!(env.balance(&trader, &env.usdc), balance - USDC);
+    assert_eq!(
+        env.trade_outcome(),
+        TradeOutcome::Filled {
+            shares: quote_buy(USDC, 100).unwrap().net_amount
+        }
+    );
+}
+
+#[test]
+fn breaching_sell_reports_the_halt() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market_with(|params| {
+        params.limits.max_price_move_bps = Some(1_000);
+        params.limits.price_move_window = 60;
+        params.limits.halt_cooldown = 600;
+        params.limits.breaker_min_liquidity = 15 * USDC;
+    });
+    let trader = env.trader(&market, 100 * USDC);
+    env.buy(&market, &trader, 10 * USDC, true).unwrap();
+    env.buy(&market, &trader, 10 * USDC, false).unwrap();
+    let shares = env.balance(&trader, &env.market(&market).no_mint);
+
+    env.sell(&market, &trader, USDC, false).unwrap();
+    assert_eq!(env.trade_outcome(), TradeOutcome::Filled { shares: USDC });
+
+    // Selling most of NO swings YES far past the threshold
+    env.sell(&market, &trader, shares - 2 * USDC, false)
+        .unwrap();
+    let state = env.market(&market);
+    assert_eq!(
+        env.trade_outcome(),
+        TradeOutcome::Halted {
+            halted_until: state.halted_until
+        }
+    );
+    assert_eq!(env.balance(&trader, &state.no_mint), shares - USDC);
+}
+
+#[test]
+fn resolved_market_rejects_trades() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.set_resolved(&market, Resolution::Yes);
+
+    assert_error(
+        env.buy(&market, &trader, USDC, true),
+        Errors::InvalidMarketStatus,
+    );
+    assert_error(
+        env.sell(&market, &trader, USDC, false),
+        Errors::InvalidMarketStatus,
+    );
+}
+
+#[test]
+fn buys_respect_position_limit() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.update_market(&market, |state| state.max_position_per_wallet = Some(USDC));
+
+    let result = env.buy(&market, &trader, 2 * USDC, true);
+    assert_error(result, Errors::PositionLimitExceeded);
+}
+
+#[test]
+fn buys_respect_open_interest_cap() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.update_market(&market, |state| state.max_open_interest = Some(USDC));
+
+    let result = env.buy(&market, &trader, 2 * USDC, true);
+    assert_error(result, Errors::OpenInterestCapExceeded);
+    assert_eq!(env.balance(&trader, &env.usdc), 10 * USDC);
+}
+
+#[test]
+fn allowlisted_market_checks_proofs() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let member = env.trader(&market, 10 * USDC);
+    let outsider = env.trader(&market, 10 * USDC);
+    let other = Pubkey::new_unique();
+    let root = hash_pair(&allowlist_leaf(&member), &allowlist_leaf(&other));
+    env.update_market(&market, |state| state.allowlist_root = Some(root));
+
+    let ix = env.buy_ix(&market, &outsider, USDC, true, vec![allowlist_leaf(&other)]);
+    assert_error(env.svm.send(&[ix], &[outsider]), Errors::NotOnAllowlist);
+
+    // A valid proof gets past the allowlist to the balance check
+    let ix = env.buy_ix(
+        &market,
+        &member,
+        11 * USDC,
+        true,
+        vec![allowlist_leaf(&other)],
+    );
+    assert_error(env.svm.send(&[ix], &[member]), Errors::ErrorInvalidAmount);
+}
+
+#[test]
+fn buy_rejects_substituted_accounts() {
+    let mut env = TestEnv::new();
+    let (creator, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    let state = env.market(&market);
+
+    // The trader's own USDC account stands in for each market-owned one
+    let trader_ata = env.ata(&trader, &env.usdc);
+    for (from, expected) in [
+        (state.market_vault, Errors::InvalidVault),
+        (state.fee_collector_ata, Errors::InvalidMarketFeeCollector),
+        (
+            state.protocol_fee_collector_ata,
+            Errors::InvalidProtocolFeeCollector,
+        ),
+    ] {
+        let mut ix = env.buy_ix(&market, &trader, USDC, true, Vec::new());
+        replace_account(&mut ix, &from, &trader_ata);
+        assert_error(env.svm.send(&[ix], &[trader]), expected);
+    }
+    assert_eq!(env.balance(&creator, &env.usdc), 1_000 * USDC);
+}
+
+#[test]
+fn claims_wait_for_final_resolution() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.update_market(&market, |state| state.dispute_window = 600);
+    env.set_resolved(&market, Resolution::Yes);
+
+    assert_error(env.claim(&market, &trader), Errors::ResolutionNotFinal);
+
+    // Past the window the claim gets as far as the empty YES balance
+    env.svm.warp_by(600);
+    assert_error(env.claim(&market, &trader), Errors::TokenAmountIsZero);
+}
+
+#[test]
+fn settled_market_rejects_claims() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    env.set_resolved(&market, Resolution::No);
+    env.update_market(&market, |state| state.status = Status::Settled);
+
+    assert_error(env.claim(&market, &trader), Errors::MarketIsSettled);
+}
+
+#[test]
+fn trades_use_the_markets_outcome_token_program() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+    let state = env.market(&market);
+
+    // Instructions passing the program the outcome mints live under, for a
+    // market that recorded another one
+    let buy = env.buy_ix(&market, &trader, USDC, true, Vec::new());
+    let sell =
+        instructions::sell_share(&market, &state, &trader, &spl_token::ID, USDC, true, false);
+    let claim = instructions::claim_winning(&market, &state, &trader, &spl_token::ID, false);
+    env.set_resolved(&market, Resolution::Yes);
+    env.update_market(&market, |state| {
+        state.outcome_token_program = spl_token_2022::ID
+    });
+    for ix in [buy, sell, claim] {
+        assert_error(
+            env.svm.send(&[ix], &[trader]),
+            Errors::InvalidOutcomeTokenProgram,
+        );
+    }
+}
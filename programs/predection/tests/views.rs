@@ -0,0 +1,104 @@
+mod common;
+
+use anchor_lang::error::ErrorCode;
+use anchor_lang::AnchorDeserialize;
+use anchor_spl::token::spl_token;
+use common::*;
+use predection::errors::Errors;
+use predection::instructions::{PriceView, StatusView};
+use predection::states::{Resolution, Status};
+use predection_sdk::instructions;
+
+/// Decodes the view the last transaction returned.
+fn returned<T: AnchorDeserialize>(env: &TestEnv) -> T {
+    let (program_id, data) = env.svm.return_data().expect("the view returns data");
+    assert_eq!(program_id, predection::ID);
+    T::try_from_slice(&data).unwrap()
+}
+
+#[test]
+fn price_view_reads_supplies_and_vault() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.set_supplies(&market, 3 * USDC, USDC);
+    let state = env.market(&market);
+    env.svm.set_token_account(
+        state.market_vault,
+        spl_token::ID,
+        env.usdc,
+        market,
+        4 * USDC,
+    );
+
+    env.svm
+        .send(&[instructions::get_price(&market, &state)], &[])
+        .unwrap();
+    assert_eq!(
+        returned::<PriceView>(&env),
+        PriceView {
+            yes_price: 750_000,
+            no_price: 250_000,
+            yes_supply: 3 * USDC,
+            no_supply: USDC,
+            vault_balance: 4 * USDC,
+            fee_bps: 100,
+            min_trade_size: 0,
+        }
+    );
+
+    let mut swapped = instructions::get_price(&market, &state);
+    replace_account(&mut swapped, &state.yes_mint, &state.no_mint);
+    assert_error(env.svm.send(&[swapped], &[]), Errors::InvalidMint);
+}
+
+#[test]
+fn status_view_follows_halts_and_dispute_window() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    env.svm
+        .send(&[instructions::get_status(&market)], &[])
+        .unwrap();
+
+    let open: StatusView = returned(&env);
+    assert_eq!(open, StatusView::new(&env.market(&market), env.svm.now()));
+    assert!(open.trading_open && !open.claimable);
+    assert_eq!(open.status, Status::Open);
+    assert_eq!(open.close_timestamp, env.svm.now() + MARKET_DURATION);
+    assert_eq!(open.dispute_deadline, None);
+
+    let halted_until = env.svm.now() + 60;
+    env.update_market(&market, |state| {
+        state.status = Status::Halted;
+        state.halted_until = halted_until;
+    });
+    let halted = env.market(&market);
+    assert!(!StatusView::new(&halted, halted_until - 1).trading_open);
+    // A trade at the end of the cooldown reopens the market first
+    assert!(StatusView::new(&halted, halted_until).trading_open);
+
+    env.set_resolved(&market, Resolution::No);
+    env.update_market(&market, |state| state.dispute_window = 100);
+    let resolved = env.market(&market);
+    let deadline = resolved.resolved_at + 100;
+    let pending = StatusView::new(&resolved, deadline - 1);
+    assert!(!pending.trading_open && !pending.claimable);
+    assert_eq!(pending.resolution, Some(Resolution::No));
+    assert_eq!(pending.dispute_deadline, Some(deadline));
+    assert!(StatusView::new(&resolved, deadline).claimable);
+}
+
+#[test]
+fn views_reject_markets_off_their_pda() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let impostor = env.user();
+    let state = env.market(&market);
+    env.set_market(&impostor, &state);
+
+    let result = env.svm.send(&[instructions::get_status(&impostor)], &[]);
+    assert_error(result, ErrorCode::ConstraintSeeds);
+    let result = env
+        .svm
+        .send(&[instructions::get_price(&impostor, &state)], &[]);
+    assert_error(result, ErrorCode::ConstraintSeeds);
+}
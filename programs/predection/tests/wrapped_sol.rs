@@ -0,0 +1,134 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token::{self, native_mint};
+use common::*;
+use predection::errors::Errors;
+use predection::states::Resolution;
+use predection_sdk::instructions;
+
+/// One SOL in lamports.
+const SOL: u64 = 1_000_000_000;
+
+fn wsol_env() -> TestEnv {
+    TestEnv::with_collateral_mint(native_mint::ID, 9, CollateralSettings::default())
+}
+
+fn lamports(env: &TestEnv, address: &Pubkey) -> u64 {
+    env.svm
+        .account(address)
+        .map_or(0, |account| account.lamports)
+}
+
+fn buy_with_sol(env: &mut TestEnv, market: &Pubkey, trader: &Pubkey, amount: u64) -> TxResult {
+    let state = env.market(market);
+    let ix = instructions::buy_share(
+        market,
+        &state,
+        trader,
+        &spl_token::ID,
+        amount,
+        true,
+        Vec::new(),
+        true,
+    );
+    env.svm.send(&[ix], &[*trader])
+}
+
+fn sell_for_sol(env: &mut TestEnv, market: &Pubkey, trader: &Pubkey, amount: u64) -> TxResult {
+    let state = env.market(market);
+    let ix = instructions::sell_share(market, &state, trader, &spl_token::ID, amount, true, true);
+    env.svm.send(&[ix], &[*trader])
+}
+
+fn claim_as_sol(env: &mut TestEnv, market: &Pubkey, trader: &Pubkey) -> TxResult {
+    let state = env.market(market);
+    let ix = instructions::claim_winning(market, &state, trader, &spl_token::ID, true);
+    env.svm.send(&[ix], &[*trader])
+}
+
+#[test]
+fn buy_wraps_lamports_and_closes_the_spent_account() {
+    let mut env = wsol_env();
+    let (_, market) = env.create_market();
+    let trader = env.user();
+
+    buy_with_sol(&mut env, &market, &trader, SOL).unwrap();
+
+    let state = env.market(&market);
+    assert_eq!(env.balance(&trader, &state.yes_mint), SOL / 100 * 99);
+    assert_eq!(lamports(&env, &env.ata(&trader, &native_mint::ID)), 0);
+}
+
+#[test]
+fn wrapping_leaves_existing_wsol_alone() {
+    let mut env = wsol_env();
+    let (_, market) = env.create_market();
+    let trader = env.user();
+    let ata = env.fund(&trader, 2 * SOL);
+
+    assert_error(
+        buy_with_sol(&mut env, &market, &trader, SOL),
+        Errors::WrappedSolAccountNotEmpty,
+    );
+    assert_eq!(env.svm.token_account(&ata).amount, 2 * SOL);
+}
+
+#[test]
+fn sell_unwraps_only_the_payout() {
+    let mut env = wsol_env();
+    let (_, market) = env.create_market();
+    let trader = env.user();
+    buy_with_sol(&mut env, &market, &trader, 2 * SOL).unwrap();
+
+    let before = lamports(&env, &trader);
+    sell_for_sol(&mut env, &market, &trader, SOL).unwrap();
+    assert_eq!(lamports(&env, &trader), before + SOL);
+
+    // Held wSOL would be unwrapped along with the payout
+    let ata = env.fund(&trader, SOL / 2);
+    assert_error(
+        sell_for_sol(&mut env, &market, &trader, SOL / 2),
+        Errors::WrappedSolAccountNotEmpty,
+    );
+    assert_eq!(env.svm.token_account(&ata).amount, SOL / 2);
+}
+
+#[test]
+fn claim_unwraps_only_the_payout() {
+    let mut env = wsol_env();
+    let (_, market) = env.create_market();
+    let trader = env.user();
+    buy_with_sol(&mut env, &market, &trader, SOL).unwrap();
+    env.svm.warp_by(MARKET_DURATION);
+    env.resolve(&market, Resolution::Invalid).unwrap();
+
+    let ata = env.fund(&trader, SOL);
+    assert_error(
+        claim_as_sol(&mut env, &market, &trader),
+        Errors::WrappedSolAccountNotEmpty,
+    );
+
+    env.svm
+        .set_token_account(ata, spl_token::ID, native_mint::ID, trader, 0);
+    let rent = lamports(&env, &ata);
+    let before = lamports(&env, &trader);
+    claim_as_sol(&mut env, &market, &trader).unwrap();
+    assert_eq!(lamports(&env, &trader), before + rent + SOL / 100 * 99);
+}
+
+#[test]
+fn only_wsol_markets_wrap_and_unwrap() {
+    let mut env = TestEnv::new();
+    let (_, market) = env.create_market();
+    let trader = env.trader(&market, 10 * USDC);
+
+    let result = buy_with_sol(&mut env, &market, &trader, USDC);
+    assert_error(result, Errors::CollateralNotNative);
+    let result = sell_for_sol(&mut env, &market, &trader, USDC);
+    assert_error(result, Errors::CollateralNotNative);
+    env.set_resolved(&market, Resolution::Yes);
+    let result = claim_as_sol(&mut env, &market, &trader);
+    assert_error(result, Errors::CollateralNotNative);
+    assert_eq!(env.balance(&trader, &env.usdc), 10 * USDC);
+}
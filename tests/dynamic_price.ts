@@ -9,6 +9,7 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
+import { whitelistCollateral } from "./utils";
 
 describe("dynamic_price", () => {
   anchor.setProvider(anchor.AnchorProvider.env());
@@ -18,10 +19,11 @@ describe("dynamic_price", () => {
   let user = anchor.web3.Keypair.generate();
   let market_creator = anchor.web3.Keypair.generate();
   let resolver = anchor.web3.Keypair.generate();
-  let protocolFeeCollector = anchor.web3.Keypair.generate();
+  // The protocol config's treasury, set to the provider wallet in utils.ts
+  let protocolFeeCollector = provider.wallet;
   
   // Use a different market ID to avoid collision with other tests if running in parallel (though they run sequentially usually)
-  let marketId = 999;
+  let marketId = 0; // first market of a fresh creator profile
   const marketIdBuffer = Buffer.alloc(8);
   marketIdBuffer.writeBigUInt64LE(BigInt(marketId));
 
@@ -68,6 +70,7 @@ describe("dynamic_price", () => {
       null,
       6
     );
+    await whitelistCollateral(program, provider, collateralMint);
 
     // 3. Create Market
     [yesMintPda] = await anchor.web3.PublicKey.findProgramAddressSync(
@@ -90,10 +93,21 @@ describe("dynamic_price", () => {
     await program.methods
       .initialize(
         resolver.publicKey,
-        new anchor.BN(marketId),
         Buffer.from("Dynamic Price Test?"),
         new anchor.BN(0),
-        new anchor.BN(0) // 0 Fee for easier math
+        new anchor.BN(0), // 0 Fee for easier math
+        {
+          maxOpenInterest: null,
+          maxPositionPerWallet: null,
+          maxPriceMoveBps: null,
+          priceMoveWindow: new anchor.BN(0),
+          haltCooldown: new anchor.BN(0),
+          breakerMinLiquidity: new anchor.BN(0),
+        },
+        null, // public market, no allowlist
+        null, // plain SPL outcome mints, no metadata
+        "crypto",
+        null // no seeded liquidity, opens at 50/50
       )
       .accounts({
         marketCreator: market_creator.publicKey,
@@ -106,6 +120,7 @@ describe("dynamic_price", () => {
         noMint: noMintPda,
         marketVault: marketVault,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -130,7 +145,7 @@ describe("dynamic_price", () => {
 
     // Step 1: Establish Initial Pool
     // Buy 60 YES
-    await program.methods.buyShare(new anchor.BN(60 * 1_000_000), new anchor.BN(marketId), true)
+    await program.methods.buyShare(new anchor.BN(60 * 1_000_000), true, [], false)
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -144,6 +159,7 @@ describe("dynamic_price", () => {
         yesMintAta: await getAssociatedTokenAddress(yesMintPda, user.publicKey),
         noMintAta: await getAssociatedTokenAddress(noMintPda, user.publicKey),
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -151,7 +167,7 @@ describe("dynamic_price", () => {
       .rpc();
 
     // Buy 40 NO
-    await program.methods.buyShare(new anchor.BN(40 * 1_000_000), new anchor.BN(marketId), false)
+    await program.methods.buyShare(new anchor.BN(40 * 1_000_000), false, [], false)
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -165,6 +181,7 @@ describe("dynamic_price", () => {
         yesMintAta: await getAssociatedTokenAddress(yesMintPda, user.publicKey),
         noMintAta: await getAssociatedTokenAddress(noMintPda, user.publicKey),
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -183,7 +200,7 @@ describe("dynamic_price", () => {
     const balanceBefore = (await provider.connection.getTokenAccountBalance(userCollateralAta)).value.uiAmount;
 
     // Buy 6 USDC worth of YES
-    await program.methods.buyShare(new anchor.BN(investmentAmount), new anchor.BN(marketId), true)
+    await program.methods.buyShare(new anchor.BN(investmentAmount), true, [], false)
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -197,6 +214,7 @@ describe("dynamic_price", () => {
         yesMintAta: await getAssociatedTokenAddress(yesMintPda, user.publicKey),
         noMintAta: await getAssociatedTokenAddress(noMintPda, user.publicKey),
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -208,7 +226,7 @@ describe("dynamic_price", () => {
     // YES Supply: 60 + 6 = 66.
     
     // Step 3: Resolve YES
-    await program.methods.resolveMarket(true)
+    await program.methods.resolveMarket({ yes: {} })
       .accounts({
         resolver: resolver.publicKey,
         market: marketPDA,
@@ -219,7 +237,7 @@ describe("dynamic_price", () => {
       .rpc();
 
     // Step 4: Claim
-    await program.methods.claimWinning()
+    await program.methods.claimWinning(false)
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -231,6 +249,7 @@ describe("dynamic_price", () => {
         yesMintAta: await getAssociatedTokenAddress(yesMintPda, user.publicKey),
         noMintAta: await getAssociatedTokenAddress(noMintPda, user.publicKey),
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
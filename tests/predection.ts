@@ -10,6 +10,7 @@ import {
   TOKEN_PROGRAM_ID,
   ASSOCIATED_TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
+import { whitelistCollateral } from "./utils";
 
 describe("predection", () => {
   // Configure the client to use the local cluster.
@@ -21,9 +22,10 @@ describe("predection", () => {
   let market_creator = anchor.web3.Keypair.generate();
   let lamport = anchor.web3.LAMPORTS_PER_SOL;
   let resolver = anchor.web3.Keypair.generate();
-  let protocolFeeCollector = anchor.web3.Keypair.generate();
+  // The protocol config's treasury, set to the provider wallet in utils.ts
+  let protocolFeeCollector = provider.wallet;
 
-  let marketId = 1;
+  let marketId = 0; // ids are allocated by the creator profile, starting at 0
   const marketIdBuffer = Buffer.alloc(8); // ?? i dont not know what is this
   marketIdBuffer.writeBigUInt64LE(BigInt(marketId)); // what this do ??
 
@@ -57,11 +59,6 @@ describe("predection", () => {
         lamports: 5 * lamport,
       }),
 anchor.web3.SystemProgram.transfer({
-          fromPubkey: provider.wallet.publicKey,
-          toPubkey: protocolFeeCollector.publicKey,
-          lamports: 5 * lamport,
-        }),
-        anchor.web3.SystemProgram.transfer({
           fromPubkey: provider.wallet.publicKey,
           toPubkey: resolver.publicKey,
           lamports: 5 * lamport,
@@ -90,6 +87,7 @@ anchor.web3.SystemProgram.transfer({
       null, // Freeze authority
       6 // Decimals (like USDC)
     );
+    await whitelistCollateral(program, provider, collateralMint);
     
     // Don't create YES/NO mints manually - the program will create them via init
     // They are PDAs created during the initialize instruction
@@ -143,7 +141,7 @@ anchor.web3.SystemProgram.transfer({
 
   it("Is initialized!", async () => {
     // Add your test here.
-    let market_id = new anchor.BN(1);
+    let market_id = new anchor.BN(0);
     let question = "Will BTC reach $150k by end of 2026?";  // Longer question (>= 10 chars)
     let questionBytes = Buffer.from(question, 'utf-8');
     let duration_time = new anchor.BN(0); // immediate resolution for testing
@@ -162,7 +160,14 @@ anchor.web3.SystemProgram.transfer({
     console.log("Original Market PDA:", marketPDA.toString());
     
     const tx = await program.methods
-      .initialize(resolver.publicKey, market_id, questionBytes, duration_time, fee)
+      .initialize(resolver.publicKey, questionBytes, duration_time, fee, {
+        maxOpenInterest: null,
+        maxPositionPerWallet: null,
+        maxPriceMoveBps: null,
+        priceMoveWindow: new anchor.BN(0),
+        haltCooldown: new anchor.BN(0),
+        breakerMinLiquidity: new anchor.BN(0),
+      }, null, null, "crypto", null)
       .accounts({
         marketCreator: market_creator.publicKey,
         market: derivedMarketPDA,  // Use the correctly derived PDA
@@ -174,6 +179,7 @@ anchor.web3.SystemProgram.transfer({
         noMint: noMintPda,
         marketVault: marketVault,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -229,7 +235,7 @@ anchor.web3.SystemProgram.transfer({
     
     // Re-derive market PDA
     const market_id_buffer = Buffer.alloc(8);
-    market_id_buffer.writeBigUInt64LE(BigInt(1));
+    market_id_buffer.writeBigUInt64LE(BigInt(0));
     
     const [marketPDA] = anchor.web3.PublicKey.findProgramAddressSync(
       [Buffer.from("market"), market_creator.publicKey.toBytes(), market_id_buffer],
@@ -268,7 +274,7 @@ anchor.web3.SystemProgram.transfer({
     console.log("📊 Buying 10 USDC worth of YES shares...");
     
     const tx = await program.methods
-      .buyShare(buyAmount, new anchor.BN(1), true) // amount, market_id, is_yes
+      .buyShare(buyAmount, true, [], false) // amount, is_yes, proof, wrap_sol
       .accounts({
         signer: user.publicKey,
         feeCollectorAta: feeCollectorColletralAta,
@@ -282,6 +288,7 @@ anchor.web3.SystemProgram.transfer({
         yesMintAta: userYesAta,
         noMintAta: userNoAta,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -334,7 +341,7 @@ anchor.web3.SystemProgram.transfer({
     const preUserYes = await provider.connection.getTokenAccountBalance(userYesAta);
     
     const tx = await program.methods
-      .sellShare(sellAmount, true) // amount, is_yes
+      .sellShare(sellAmount, true, false) // amount, is_yes, unwrap_sol
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -346,6 +353,7 @@ anchor.web3.SystemProgram.transfer({
         yesMintAta: userYesAta,
         noMintAta: userNoAta,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -374,7 +382,7 @@ anchor.web3.SystemProgram.transfer({
     const userYesAta = await getAssociatedTokenAddress(yesMintPda, user.publicKey);
     const userNoAta = await getAssociatedTokenAddress(noMintPda, user.publicKey);
     const buyNoTx = await program.methods
-      .buyShare(buyAmount, new anchor.BN(1), false)
+      .buyShare(buyAmount, false, [], false)
       .accounts({
         signer: user.publicKey,
         feeCollectorAta: feeCollectorColletralAta,
@@ -388,6 +396,7 @@ anchor.web3.SystemProgram.transfer({
         yesMintAta: userYesAta,
         noMintAta: userNoAta,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -396,7 +405,7 @@ anchor.web3.SystemProgram.transfer({
     await provider.connection.confirmTransaction(buyNoTx);
 
     const resolveTx = await program.methods
-      .resolveMarket(true)
+      .resolveMarket({ yes: {} })
       .accounts({
         resolver: resolver.publicKey,
         market: marketPDA,
@@ -417,7 +426,7 @@ anchor.web3.SystemProgram.transfer({
     const userNoAta = await getAssociatedTokenAddress(noMintPda, user.publicKey);
 
     const claimTx = await program.methods
-      .claimWinning()
+      .claimWinning(false)
       .accounts({
         signer: user.publicKey,
         market: marketPDA,
@@ -429,6 +438,7 @@ anchor.web3.SystemProgram.transfer({
         yesMintAta: userYesAta,
         noMintAta: userNoAta,
         tokenProgram: TOKEN_PROGRAM_ID,
+        outcomeTokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -0,0 +1,38 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Predection } from "../target/types/predection";
+
+const BPF_LOADER_UPGRADEABLE = new anchor.web3.PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+// The protocol config is global, so only the first test file creates it.
+// The local validator deploys with the provider wallet as upgrade authority,
+// and the same wallet is the treasury markets must pay protocol fees to.
+export async function whitelistCollateral(
+  program: Program<Predection>,
+  provider: anchor.Provider,
+  collateralMint: anchor.web3.PublicKey
+) {
+  const admin = provider.wallet.publicKey;
+  const [configPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+
+  if (!(await provider.connection.getAccountInfo(configPda))) {
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE
+    );
+    await program.methods
+      .initProtocolConfig(admin)
+      .accounts({ admin, programData })
+      .rpc();
+  }
+
+  await program.methods
+    .addCollateral(new anchor.BN(0), new anchor.BN(1000), new anchor.BN(0), new anchor.BN(0)) // no creator bond or disputes in tests
+    .accounts({ admin, collateralMint })
+    .rpc();
+}